@@ -1,13 +1,31 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use crypto::{AesGcmEncrypter, EncryptedData, Encrypter};
+use crypto::{
+    AesGcmEncrypter, EncryptedData, Encrypter, KdfParams, SrpClient, SrpClientSession, Totp,
+};
+use serde::Serialize;
 use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::State;
 
 #[derive(Default)]
 struct AppState {
     pub encrypter: Mutex<Option<crypto::AesGcmEncrypter>>,
+    pub srp_client: Mutex<Option<SrpClient>>,
+    pub srp_session: Mutex<Option<SrpClientSession>>,
+}
+
+#[derive(Serialize)]
+struct SrpRegistration {
+    srp_salt: [u8; 32],
+    verifier: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct TotpCode {
+    code: String,
+    seconds_remaining: u64,
 }
 
 #[tauri::command]
@@ -15,15 +33,71 @@ fn create_encrypter(
     state: State<AppState>,
     plain_master_password: String,
     salt: [u8; 32],
+    kdf_params: Option<KdfParams>,
 ) -> Result<(), String> {
-    let encrypter = match AesGcmEncrypter::build(plain_master_password, &salt) {
-        Ok(encrypter) => encrypter,
-        Err(_) => return Err("Failed to create encrypter".to_string()),
-    };
+    let kdf_params = kdf_params.unwrap_or_default();
+    let encrypter =
+        match AesGcmEncrypter::build_with_params(plain_master_password, &salt, &kdf_params) {
+            Ok(encrypter) => encrypter,
+            Err(_) => return Err("Failed to create encrypter".to_string()),
+        };
     *state.encrypter.lock().unwrap() = Some(encrypter);
     Ok(())
 }
 
+#[tauri::command]
+fn srp_register(plain_master_password: String) -> Result<SrpRegistration, String> {
+    let srp_salt = crypto::srp_salt();
+    match crypto::srp_verifier(&plain_master_password, &srp_salt) {
+        Ok(verifier) => Ok(SrpRegistration { srp_salt, verifier }),
+        Err(_) => Err("Failed to create verifier".to_string()),
+    }
+}
+
+#[tauri::command]
+fn srp_login_start(state: State<AppState>) -> Vec<u8> {
+    let client = SrpClient::new();
+    let client_public_key = client.public_key();
+    *state.srp_client.lock().unwrap() = Some(client);
+    client_public_key
+}
+
+#[tauri::command]
+fn srp_login_finish(
+    state: State<AppState>,
+    username: String,
+    plain_master_password: String,
+    srp_salt: [u8; 32],
+    server_public_key: Vec<u8>,
+) -> Result<[u8; 32], String> {
+    let client = match state.srp_client.lock().unwrap().take() {
+        Some(client) => client,
+        None => return Err("Login not started".to_string()),
+    };
+    let session = match client.process(
+        &username,
+        &plain_master_password,
+        &srp_salt,
+        &server_public_key,
+    ) {
+        Ok(session) => session,
+        Err(_) => return Err("Invalid server public key".to_string()),
+    };
+    let client_proof = *session.client_proof();
+    *state.srp_session.lock().unwrap() = Some(session);
+    Ok(client_proof)
+}
+
+#[tauri::command]
+fn srp_verify_server(state: State<AppState>, server_proof: Vec<u8>) -> Result<(), String> {
+    match state.srp_session.lock().unwrap().take() {
+        Some(session) => session
+            .verify_server(&server_proof)
+            .map_err(|_| "Server could not prove it knows the account".to_string()),
+        None => Err("Login not started".to_string()),
+    }
+}
+
 #[tauri::command]
 fn encrypt(state: State<AppState>, data: String) -> Result<(Vec<u8>, [u8; 12]), String> {
     match state
@@ -56,11 +130,76 @@ fn decrypt(state: State<AppState>, data: Vec<u8>, nonce: [u8; 12]) -> Result<Str
     }
 }
 
+#[tauri::command]
+fn search_tokens(state: State<AppState>, domain_name: String) -> Result<Vec<[u8; 32]>, String> {
+    match state.encrypter.lock().unwrap().as_ref() {
+        Some(encrypter) => Ok(encrypter.blind_indexer().domain_indexes(&domain_name)),
+        None => Err("Failed to create search tokens".to_string()),
+    }
+}
+
+#[tauri::command]
+fn search_token(state: State<AppState>, query: String) -> Result<String, String> {
+    match state.encrypter.lock().unwrap().as_ref() {
+        Some(encrypter) => Ok(encrypter
+            .blind_indexer()
+            .index(&crypto::normalize_domain(&query))
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()),
+        None => Err("Failed to create search token".to_string()),
+    }
+}
+
+#[tauri::command]
+fn totp_code(state: State<AppState>, totp: Vec<u8>, nonce: [u8; 12]) -> Result<TotpCode, String> {
+    let secret = match state.encrypter.lock().unwrap().as_ref() {
+        Some(encrypter) => match encrypter.decrypt(EncryptedData {
+            content: totp,
+            nonce,
+        }) {
+            Ok(secret) => secret,
+            Err(_) => return Err("Failed to decrypt data".to_string()),
+        },
+        None => return Err("Failed to decrypt data".to_string()),
+    };
+
+    let totp = match Totp::parse(&secret) {
+        Some(totp) => totp,
+        None => return Err("Invalid TOTP secret".to_string()),
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| "Invalid system time".to_string())?
+        .as_secs();
+    Ok(TotpCode {
+        code: totp.code_at(timestamp),
+        seconds_remaining: totp.seconds_remaining(timestamp),
+    })
+}
+
+#[tauri::command]
+fn validate_totp(secret: String) -> bool {
+    Totp::parse(&secret).is_some()
+}
+
 fn main() {
     let app_state = AppState::default();
     tauri::Builder::default()
         .manage(app_state)
-        .invoke_handler(tauri::generate_handler![create_encrypter, encrypt, decrypt])
+        .invoke_handler(tauri::generate_handler![
+            create_encrypter,
+            srp_register,
+            srp_login_start,
+            srp_login_finish,
+            srp_verify_server,
+            encrypt,
+            decrypt,
+            search_tokens,
+            search_token,
+            totp_code,
+            validate_totp
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -17,6 +17,17 @@ async function decrypt(data, nonce) {
     return result
 }
 
+// WEBAUTHN ENCODING
+function base64urlToBuffer(value) {
+    const base64 = value.replace(/-/g, "+").replace(/_/g, "/");
+    return Uint8Array.from(atob(base64), c => c.charCodeAt(0)).buffer;
+}
+
+function bufferToBase64url(buffer) {
+    const base64 = btoa(String.fromCharCode(...new Uint8Array(buffer)));
+    return base64.replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+}
+
 // STRUCTURES
 class User {
     constructor(user_id, username, plain_master_password, salt) {
@@ -28,23 +39,28 @@ class User {
 }
 
 class Session {
-    constructor(session_id) {
-        this.session_id = session_id
+    constructor(access_token, refresh_token, expires_in) {
+        this.access_token = access_token,
+        this.refresh_token = refresh_token,
+        this.expires_in = expires_in  // seconds
     }
 }
 
 class Password {
-    constructor(password_id, domain_name, username, password, nonce) {
+    constructor(password_id, domain_name, username, password, nonce, totp, totp_nonce) {
         this.password_id = password_id;
         this.domain_name = domain_name;
         this.username = username;
         this.password = password;
         this.nonce = nonce;
+        this.totp = totp;
+        this.totp_nonce = totp_nonce;
     }
 
     static from(data) {
         return new Password(
-            data.password_id, data.domain_name, data.username, data.password, data.nonce
+            data.password_id, data.domain_name, data.username, data.password, data.nonce,
+            data.totp, data.totp_nonce
         )
     }
 }
@@ -74,59 +90,170 @@ class Server {
 
     async initializeClient() {
         this.client = await this.http.getClient();
+        await this.getVersion();
+    }
+
+    // servers predating /v1 answer 404, their unversioned routes are used instead
+    async getVersion() {
+        const response = await this.client.request({
+            method: "GET",
+            url: this.address + "/v1/version",
+            responseType: this.http.ResponseType.JSON
+        });
+        this.api = response.ok ? this.address + "/v1" : this.address;
+        this.capabilities = response.ok ? response.data.capabilities : [];
+        return response
+    }
+
+    async postUsersRegister(username, srp_salt, verifier) {
+        const response = await this.client.request({
+            method: "POST",
+            url: this.api + "/users/register",
+            headers: {
+                ContentType: "application/json"
+            },
+            body: this.http.Body.json({
+                username: username,
+                srp_salt: srp_salt,
+                verifier: verifier
+            }),
+            responseType: this.http.ResponseType.JSON
+        });
+        return response
     }
 
-    async postUsersRegister(username, password) {
+    async postUsersPrelogin(username) {
         const response = await this.client.request({
             method: "POST",
-            url: this.address + "/users/register",
+            url: this.api + "/users/prelogin",
+            headers: {
+                ContentType: "application/json"
+            },
+            body: this.http.Body.json({
+                username: username
+            }),
+            responseType: this.http.ResponseType.JSON
+        });
+        return response
+    }
+
+    async postUsersLoginStart(username, client_public_key) {
+        const response = await this.client.request({
+            method: "POST",
+            url: this.api + "/users/login/start",
             headers: {
                 ContentType: "application/json"
             },
             body: this.http.Body.json({
                 username: username,
-                password: password
+                client_public_key: client_public_key
             }),
             responseType: this.http.ResponseType.JSON
         });
         return response
     }
 
-    async postUsersLogin(username, password) {
+    async postUsersLogin(username, login_id, client_proof) {
         const response = await this.client.request({
             method: "POST",
-            url: this.address + "/users/login",
+            url: this.api + "/users/login",
             headers: {
                 ContentType: "application/json"
             },
             body: this.http.Body.json({
                 username: username,
-                password: password
+                login_id: login_id,
+                client_proof: client_proof
+            }),
+            responseType: this.http.ResponseType.JSON
+        });
+        return response
+    }
+
+    async postUsersLoginTotp(challenge_id, code) {
+        const response = await this.client.request({
+            method: "POST",
+            url: this.api + "/users/login/totp",
+            headers: {
+                ContentType: "application/json"
+            },
+            body: this.http.Body.json({
+                challenge_id: challenge_id,
+                code: code
             }),
             responseType: this.http.ResponseType.JSON
         });
         return response
     }
 
-    async postUsersLogout(session_id) {
+    async postUsersWebauthnLoginStart(challenge_id) {
         const response = await this.client.request({
             method: "POST",
-            url: this.address + "/users/logout",
+            url: this.api + "/users/webauthn/login/start",
             headers: {
-                session_id: session_id
+                ContentType: "application/json"
+            },
+            body: this.http.Body.json({
+                challenge_id: challenge_id
+            }),
+            responseType: this.http.ResponseType.JSON
+        });
+        return response
+    }
+
+    async postUsersWebauthnLoginFinish(challenge_id, credential) {
+        const response = await this.client.request({
+            method: "POST",
+            url: this.api + "/users/webauthn/login/finish",
+            headers: {
+                ContentType: "application/json"
+            },
+            body: this.http.Body.json({
+                challenge_id: challenge_id,
+                credential_id: bufferToBase64url(credential.rawId),
+                client_data_json: bufferToBase64url(credential.response.clientDataJSON),
+                authenticator_data: bufferToBase64url(credential.response.authenticatorData),
+                signature: bufferToBase64url(credential.response.signature)
+            }),
+            responseType: this.http.ResponseType.JSON
+        });
+        return response
+    }
+
+    async postUsersRefresh(refresh_token) {
+        const response = await this.client.request({
+            method: "POST",
+            url: this.api + "/users/refresh",
+            headers: {
+                ContentType: "application/json"
+            },
+            body: this.http.Body.json({
+                refresh_token: refresh_token
+            }),
+            responseType: this.http.ResponseType.JSON
+        });
+        return response
+    }
+
+    async postUsersLogout(access_token) {
+        const response = await this.client.request({
+            method: "POST",
+            url: this.api + "/users/logout",
+            headers: {
+                Authorization: "Bearer " + access_token
             },
             responseType: this.http.ResponseType.JSON
         });
         return response;
     }
 
-    async postPasswords(session_id, domain_name, username, password, nonce) {
+    async postPasswords(access_token, domain_name, username, password, nonce) {
         const response = await this.client.request({
             method: "POST",
-            url: this.address + "/passwords",
+            url: this.api + "/passwords",
             headers: {
                 ContentType: "application/json",
-                session_id: session_id
+                Authorization: "Bearer " + access_token
             },
             body: this.http.Body.json({
                 domain_name: domain_name,
@@ -139,49 +266,50 @@ class Server {
         return response
     }
 
-    async getPasswordsId(session_id, password_id) {
+    async getPasswordsId(access_token, password_id) {
         const response = await this.client.request({
             method: "GET",
-            url: this.address + "/passwords/" + password_id,
+            url: this.api + "/passwords/" + password_id,
             headers: {
-                session_id: session_id
+                Authorization: "Bearer " + access_token
             },
             responseType: this.http.ResponseType.JSON
         });
         return response;
     }
 
-    async getPasswords(session_id) {
+    async getPasswords(access_token, after) {
         const response = await this.client.request({
             method: "GET",
-            url: this.address + "/passwords",
+            url: this.api + "/passwords",
             headers: {
-                session_id: session_id
+                Authorization: "Bearer " + access_token
             },
+            query: after ? { limit: PAGE_LIMIT, after: after } : { limit: PAGE_LIMIT },
             responseType: this.http.ResponseType.JSON
         });
         return response;
     }
 
-    async deletePasswordsId(session_id, password_id) {
+    async deletePasswordsId(access_token, password_id) {
         const response = await this.client.request({
             method: "DELETE",
-            url: this.address + "/passwords/" + password_id,
+            url: this.api + "/passwords/" + password_id,
             headers: {
-                session_id: session_id
+                Authorization: "Bearer " + access_token
             },
             responseType: this.http.ResponseType.JSON
         });
         return response;
     }
 
-    async patchPasswordsId(session_id, password_id, domain_name, username, password, nonce) {
+    async patchPasswordsId(access_token, password_id, domain_name, username, password, nonce) {
         const response = await this.client.request({
             method: "PATCH",
-            url: this.address + "/passwords/" + password_id,
+            url: this.api + "/passwords/" + password_id,
             headers: {
                 ContentType: "application/json",
-                session_id: session_id
+                Authorization: "Bearer " + access_token
             },
             body: this.http.Body.json({
                 domain_name: domain_name,
@@ -194,13 +322,59 @@ class Server {
         return response;
     }
 
-    async postNotes(session_id, title, title_nonce, content, content_nonce) {
+    async putPasswordsIdSearchTokens(access_token, password_id, tokens) {
+        const response = await this.client.request({
+            method: "PUT",
+            url: this.api + "/passwords/" + password_id + "/search_tokens",
+            headers: {
+                ContentType: "application/json",
+                Authorization: "Bearer " + access_token
+            },
+            body: this.http.Body.json({
+                tokens: tokens
+            }),
+            responseType: this.http.ResponseType.JSON
+        });
+        return response;
+    }
+
+    async putPasswordsIdTotp(access_token, password_id, totp, totp_nonce) {
+        const response = await this.client.request({
+            method: "PUT",
+            url: this.api + "/passwords/" + password_id + "/totp",
+            headers: {
+                ContentType: "application/json",
+                Authorization: "Bearer " + access_token
+            },
+            body: this.http.Body.json({
+                totp: totp,
+                totp_nonce: totp_nonce
+            }),
+            responseType: this.http.ResponseType.JSON
+        });
+        return response;
+    }
+
+    async getPasswordsSearch(access_token, token) {
+        const response = await this.client.request({
+            method: "GET",
+            url: this.api + "/passwords/search",
+            headers: {
+                Authorization: "Bearer " + access_token
+            },
+            query: { token: token },
+            responseType: this.http.ResponseType.JSON
+        });
+        return response;
+    }
+
+    async postNotes(access_token, title, title_nonce, content, content_nonce) {
         const response = await this.client.request({
             method: "POST",
-            url: this.address + "/notes",
+            url: this.api + "/notes",
             headers: {
                 ContentType: "application/json",
-                session_id: session_id
+                Authorization: "Bearer " + access_token
             },
             body: this.http.Body.json({
                 title: title,
@@ -213,49 +387,50 @@ class Server {
         return response
     }
 
-    async getNotes(session_id) {
+    async getNotes(access_token, after) {
         const response = await this.client.request({
             method: "GET",
-            url: this.address + "/notes",
+            url: this.api + "/notes",
             headers: {
-                session_id: session_id
+                Authorization: "Bearer " + access_token
             },
+            query: after ? { limit: PAGE_LIMIT, after: after } : { limit: PAGE_LIMIT },
             responseType: this.http.ResponseType.JSON
         });
         return response;
     }
 
-    async getNotesId(session_id, note_id) {
+    async getNotesId(access_token, note_id) {
         const response = await this.client.request({
             method: "GET",
-            url: this.address + "/notes/" + note_id,
+            url: this.api + "/notes/" + note_id,
             headers: {
-                session_id: session_id
+                Authorization: "Bearer " + access_token
             },
             responseType: this.http.ResponseType.JSON
         });
         return response;
     }
 
-    async deleteNotesId(session_id, note_id) {
+    async deleteNotesId(access_token, note_id) {
         const response = await this.client.request({
             method: "DELETE",
-            url: this.address + "/notes/" + note_id,
+            url: this.api + "/notes/" + note_id,
             headers: {
-                session_id: session_id
+                Authorization: "Bearer " + access_token
             },
             responseType: this.http.ResponseType.JSON
         });
         return response;
     }
 
-    async patchNotesId(session_id, note_id, title, title_nonce, content, content_nonce) {
+    async patchNotesId(access_token, note_id, title, title_nonce, content, content_nonce) {
         const response = await this.client.request({
             method: "PATCH",
-            url: this.address + "/notes/" + note_id,
+            url: this.api + "/notes/" + note_id,
             headers: {
                 ContentType: "application/json",
-                session_id: session_id
+                Authorization: "Bearer " + access_token
             },
             body: this.http.Body.json({
                 title: title,
@@ -325,9 +500,11 @@ class App  {
             const editorRegisterServerAddress = document.querySelector(".editor-register-server-address");
             this.server = new Server(editorRegisterServerAddress.value);
             await this.server.initializeClient();
-            await this.server.postUsersRegister(
-                editorRegisterUsername.value, editorRegisterPassword.value
-            )
+            // only the SRP verifier leaves the client, never the master password
+            await invoke("srp_register", { plainMasterPassword: editorRegisterPassword.value })
+                .then((registration) => this.server.postUsersRegister(
+                    editorRegisterUsername.value, registration.srp_salt, registration.verifier
+                ))
                 .then((result) => {
                     if (result.ok == false) this.showFailureNotification(result.data.message)
                     else this.showSuccessNotification(result.data.message)
@@ -341,18 +518,55 @@ class App  {
         editorLoginBtn.addEventListener("click", async () => {
             const editorLoginUsername = document.querySelector(".editor-login-username");
             const editorLoginPassword = document.querySelector(".editor-login-password");
+            const editorLoginCode = document.querySelector(".editor-login-code");
             const editorLoginServerAddress = document.querySelector(".editor-login-server-address");
             this.server = new Server(editorLoginServerAddress.value);
             await this.server.initializeClient();
-            await this.server.postUsersLogin(
-                editorLoginUsername.value, editorLoginPassword.value
-            )
+            // derive the vault key from the prelogin salt before authenticating
+            await this.server.postUsersPrelogin(editorLoginUsername.value)
+                .then(async (result) => {
+                    if (result.ok == false) throw result.data.message;
+                    await invoke("create_encrypter", {
+                        plainMasterPassword: editorLoginPassword.value,
+                        salt: result.data.salt,
+                        kdfParams: result.data.kdf_params
+                    });
+                    const clientPublicKey = await invoke("srp_login_start");
+                    return this.server.postUsersLoginStart(editorLoginUsername.value, clientPublicKey);
+                })
+                .then(async (result) => {
+                    if (result.ok == false) throw result.data.message;
+                    const clientProof = await invoke("srp_login_finish", {
+                        username: editorLoginUsername.value,
+                        plainMasterPassword: editorLoginPassword.value,
+                        srpSalt: result.data.srp_salt,
+                        serverPublicKey: result.data.server_public_key
+                    });
+                    return this.server.postUsersLogin(editorLoginUsername.value, result.data.login_id, clientProof);
+                })
+                .then(async (result) => {
+                    if (result.ok) {
+                        await invoke("srp_verify_server", { serverProof: result.data.server_proof });
+                    }
+                    // second factor required, answer the challenge with the one-time code
+                    if (result.status == 202) {
+                        if (editorLoginCode.value != "") {
+                            return this.server.postUsersLoginTotp(result.data.challenge_id, editorLoginCode.value);
+                        }
+                        if (result.data.second_factors.includes("webauthn")) {
+                            return this.loginWithSecurityKey(result.data.challenge_id);
+                        }
+                        throw "One-time code required";
+                    }
+                    return result;
+                })
                 .then((result) => {
                     if (result.ok == false) this.showFailureNotification(result.data.message)
                     else {
                         this.showSuccessNotification("Logged in");
                         this.user = new User(result.data.user_id, result.data.username, editorLoginPassword.value, result.data.salt);
-                        this.session = new Session(result.headers.session_id);
+                        this.session = new Session(result.data.access_token, result.data.refresh_token, result.data.expires_in);
+                        this.scheduleSessionRefresh();
                         this.unselectEditor();
                         this.setupAfterLogin();
                     }
@@ -365,6 +579,38 @@ class App  {
         this.selectEditor(loginEditor);
     }
 
+    async loginWithSecurityKey(challenge_id) {
+        const options = await this.server.postUsersWebauthnLoginStart(challenge_id);
+        if (options.ok == false) return options;
+
+        const credential = await navigator.credentials.get({
+            publicKey: {
+                ...options.data,
+                challenge: base64urlToBuffer(options.data.challenge),
+                allowCredentials: options.data.allowCredentials.map(descriptor => ({
+                    ...descriptor,
+                    id: base64urlToBuffer(descriptor.id)
+                }))
+            }
+        });
+        return await this.server.postUsersWebauthnLoginFinish(challenge_id, credential);
+    }
+
+    scheduleSessionRefresh() {
+        // swap the short lived access token shortly before it expires
+        const delay = Math.max(this.session.expires_in - 30, 5) * 1000;
+        this.sessionRefreshTimeout = setTimeout(async () => {
+            if (this.session == null) return;
+            const result = await this.server.postUsersRefresh(this.session.refresh_token);
+            if (result.ok == false) {
+                this.showFailureNotification(result.data.message);
+                return;
+            }
+            this.session = new Session(result.data.access_token, result.data.refresh_token, result.data.expires_in);
+            this.scheduleSessionRefresh();
+        }, delay);
+    }
+
     async setupAfterLogin() {
         // hide login / register buttons, show logout button
         document.querySelector(".login-btn").classList.add("d-none");
@@ -372,7 +618,7 @@ class App  {
         const logoutBtn = document.querySelector(".logout-btn");
         logoutBtn.classList.remove("d-none");
         logoutBtn.addEventListener("click", async () => {
-            await this.server.postUsersLogout(this.session.session_id)
+            await this.server.postUsersLogout(this.session.access_token)
                 .then((result) => {
                     if (result.ok == false) this.showFailureNotification(result.data.message)
                     else {
@@ -380,6 +626,8 @@ class App  {
                         this.unselectEditor();
                         this.unselectEntry();
                         this.deleteEntries();
+                        clearInterval(this.totpInterval);
+                        clearTimeout(this.sessionRefreshTimeout);
                         this.session = null;
                         this.server = null;
                         this.user = null;
@@ -393,17 +641,13 @@ class App  {
                 })
         })
 
+        this.totpInterval = setInterval(() => this.refreshTotpCodes(), 1000);
+
         // automatically select passwords tab after login
         const passwordsTabBtn = document.querySelector(".passwords-tab-btn");
         const passwordEntries = document.querySelector(".password-entries");
         this.selectEntries(passwordEntries, passwordsTabBtn);
 
-        // initialize encrypter
-        await invoke("create_encrypter", {
-            plainMasterPassword: this.user.plain_master_password,
-            salt: this.user.salt
-        });
-
         // enable `NEW` buttons
         const newPasswordBtn = document.querySelector(".new-password-btn");
         newPasswordBtn.addEventListener("click", () => {
@@ -421,40 +665,21 @@ class App  {
             this.selectEditor(noteEditor);
         })
 
-        // load all existing passwords
-        const passwords = [];
-        await this.server.getPasswords(this.session.session_id)
-            .then((result) => {
-                if (result.ok == false) this.showFailureNotification(result.data.message)
-                else result.data.forEach(password => {
-                    passwords.push(Password.from(password))
-                });
-            })
-            .catch((error) => {
-                this.showFailureNotification(error);
-            })
-        passwords.forEach(password => {
-            this.addPasswordEntry(password);
-        });
+        // load first pages of passwords and notes, next ones are loaded while scrolling
+        this.passwordsPage = { cursor: null, done: false, loading: false };
+        this.notesPage = { cursor: null, done: false, loading: false };
+        await this.loadPasswords();
+        await this.loadNotes();
 
-        // load all existing notes
-        const notes = [];
-        await this.server.getNotes(this.session.session_id)
-            .then((result) => {
-                console.log(result);
-                if (result.ok == false) this.showFailureNotification(result.data.message)
-                else result.data.forEach(note => {
-                    notes.push(Note.from(note))
-                });
-            })
-            .catch((error) => {
-                console.log(error);
-                this.showFailureNotification(error);
-            })
-        console.log(notes);
-        notes.forEach(note => {
-            this.addNoteEntry(note);
-        });
+        const entriesContainer = document.querySelector(".entries-container");
+        entriesContainer.onscroll = async () => {
+            const bottom = entriesContainer.scrollHeight - entriesContainer.clientHeight;
+            if (entriesContainer.scrollTop < bottom - SCROLL_THRESHOLD) return;
+
+            const passwordEntries = document.querySelector(".password-entries");
+            if (passwordEntries.classList.contains("d-none")) await this.loadNotes();
+            else await this.loadPasswords();
+        }
 
         // enable passwords editor buttons
         const newPasswordEditorSaveBtn = document.querySelector(".new-password-editor-save-btn");
@@ -462,14 +687,20 @@ class App  {
             const domainName = document.querySelector(".new-password-editor-domain").value;
             const username = document.querySelector(".new-password-editor-username").value;
             const password = document.querySelector(".new-password-editor-password").value;
+            const totp = document.querySelector(".new-password-editor-totp").value;
+            if (totp != "" && !await invoke("validate_totp", { secret: totp })) {
+                this.showFailureNotification("Invalid TOTP secret");
+                return;
+            }
 
             let encryptedData = await encrypt(password);
 
-            await this.server.postPasswords(this.session.session_id, domainName, username, encryptedData[0], encryptedData[1])
-                .then((result) => {
+            await this.server.postPasswords(this.session.access_token, domainName, username, encryptedData[0], encryptedData[1])
+                .then(async (result) => {
                     if (result.ok == false) {this.showFailureNotification("Failed to create a new password")}
                     else {
-                        const password = Password.from(result.data);
+                        const password = await this.updatePasswordTotp(Password.from(result.data), totp);
+                        this.updateSearchTokens(password);
                         this.addPasswordEntry(password);
                         this.unselectEditor();
                         this.showSuccessNotification("Password created");
@@ -484,14 +715,20 @@ class App  {
             const domainName = document.querySelector(".password-editor-domain").value;
             const username = document.querySelector(".password-editor-username").value;
             const password = document.querySelector(".password-editor-password").value;
+            const totp = document.querySelector(".password-editor-totp").value;
+            if (totp != "" && !await invoke("validate_totp", { secret: totp })) {
+                this.showFailureNotification("Invalid TOTP secret");
+                return;
+            }
 
             let encryptedData = await encrypt(password);
-            await this.server.patchPasswordsId(this.session.session_id, passwordId, domainName, username, encryptedData[0], encryptedData[1])
-                .then((result) => {
+            await this.server.patchPasswordsId(this.session.access_token, passwordId, domainName, username, encryptedData[0], encryptedData[1])
+                .then(async (result) => {
                     if (result.ok == false) {this.showFailureNotification("Failed to update password")}
                     else {
                         this.deletePasswordEntry(passwordId);
-                        const password = Password.from(result.data);
+                        const password = await this.updatePasswordTotp(Password.from(result.data), totp);
+                        this.updateSearchTokens(password);
                         this.addPasswordEntry(password);
                         this.unselectEditor();
                         this.showSuccessNotification("Password updated");
@@ -504,7 +741,7 @@ class App  {
         passwordEditorDeleteBtn.addEventListener("click", async () => {
             const passwordId = document.querySelector(".password-editor-id").dataset.passwordId;
 
-            await this.server.deletePasswordsId(this.session.session_id, passwordId)
+            await this.server.deletePasswordsId(this.session.access_token, passwordId)
                 .then((_result) => {
                     this.deletePasswordEntry(passwordId);
                     this.unselectEditor();
@@ -526,7 +763,7 @@ class App  {
                 .catch((error) => this.showFailureNotification(error));
             
 
-            await this.server.postNotes(this.session.session_id,
+            await this.server.postNotes(this.session.access_token,
                 encryptedTitleData[0], encryptedTitleData[1],
                 encryptedContentData[0], encryptedContentData[1]
             )
@@ -554,7 +791,7 @@ class App  {
             .catch((error) => this.showFailureNotification(error));
 
             await this.server.patchNotesId(
-                this.session.session_id, noteId,
+                this.session.access_token, noteId,
                 encryptedTitleData[0], encryptedTitleData[1],
                 encryptedContentData[0], encryptedContentData[1]
             
@@ -576,7 +813,7 @@ class App  {
         noteEditorDeleteBtn.addEventListener("click", async () => {
             const noteId = document.querySelector(".note-editor-id").dataset.noteId;
 
-            await this.server.deleteNotesId(this.session.session_id, noteId)
+            await this.server.deleteNotesId(this.session.access_token, noteId)
                 .then((_result) => {
                     this.deleteNoteEntry(noteId);
                     this.unselectEditor();
@@ -604,6 +841,56 @@ class App  {
                 entry.classList.toggle("d-none", !isVisible);
             });
         })
+
+        // search passwords that are not loaded yet when the user confirms the query
+        searchInput.addEventListener("keydown", async (event) => {
+            if (event.key !== "Enter" || event.target.value.trim() === "") return;
+
+            const token = await invoke("search_token", { query: event.target.value });
+            await this.server.getPasswordsSearch(this.session.access_token, token)
+                .then((result) => {
+                    if (result.ok == false) this.showFailureNotification(result.data.message)
+                    else result.data.forEach(password => {
+                        this.deletePasswordEntry(password.password_id);
+                        this.addPasswordEntry(Password.from(password));
+                    });
+                })
+                .catch((error) => { this.showFailureNotification(error) })
+        })
+    }
+
+    async updatePasswordTotp(password, totp) {
+        let encryptedData = [null, null];
+        if (totp != "") encryptedData = await encrypt(totp);
+        return await this.server.putPasswordsIdTotp(this.session.access_token, password.password_id, encryptedData[0], encryptedData[1])
+            .then((result) => {
+                if (result.ok == false) {
+                    this.showFailureNotification("Failed to update TOTP secret");
+                    return password;
+                }
+                return Password.from(result.data);
+            })
+            .catch((error) => {
+                this.showFailureNotification(error);
+                return password;
+            })
+    }
+
+    async refreshTotpCodes() {
+        const codeValues = document.querySelectorAll(".entry-totp-code");
+        for (const codeValue of codeValues) {
+            await invoke("totp_code", { totp: codeValue.totp, nonce: codeValue.totpNonce })
+                .then((result) => {
+                    codeValue.innerText = result.code + " (" + result.seconds_remaining + "s)";
+                })
+                .catch((error) => { codeValue.innerText = error })
+        }
+    }
+
+    async updateSearchTokens(password) {
+        const tokens = await invoke("search_tokens", { domainName: password.domain_name });
+        await this.server.putPasswordsIdSearchTokens(this.session.access_token, password.password_id, tokens)
+            .catch((error) => { this.showFailureNotification(error) })
     }
     
     unselectEditor() {
@@ -623,10 +910,12 @@ class App  {
         const domainInput = document.querySelector(".password-editor-domain");
         const usernameInput = document.querySelector(".password-editor-username");
         const passwordInput = document.querySelector(".password-editor-password");
+        const totpInput = document.querySelector(".password-editor-totp");
         idInput.dataset.passwordId = passwordEntry.dataset.passwordId;
         domainInput.value = passwordEntry.dataset.domainName;
         usernameInput.value = passwordEntry.dataset.username;
         passwordInput.value = passwordEntry.dataset.password;
+        totpInput.value = passwordEntry.dataset.totp;
     }
 
     fillNoteEditor(noteEntry) {
@@ -643,19 +932,23 @@ class App  {
         const domainInput = document.querySelector(".password-editor-domain");
         const usernameInput = document.querySelector(".password-editor-username");
         const passwordInput = document.querySelector(".password-editor-password");
+        const totpInput = document.querySelector(".password-editor-totp");
         idInput.dataset.PasswordId = "";
         domainInput.value = "";
         usernameInput.value = "";
         passwordInput.value = "";
+        totpInput.value = "";
     }
 
     clearNewPasswordEditor() {
         const domainInput = document.querySelector(".new-password-editor-domain");
         const usernameInput = document.querySelector(".new-password-editor-username");
         const passwordInput = document.querySelector(".new-password-editor-password");
+        const totpInput = document.querySelector(".new-password-editor-totp");
         domainInput.value = "";
         usernameInput.value = "";
         passwordInput.value = "";
+        totpInput.value = "";
     }
 
     clearNoteEditor() {
@@ -686,9 +979,11 @@ class App  {
     clearLoginEditor() {
         const usernameInput = document.querySelector(".editor-login-username");
         const passwordInput = document.querySelector(".editor-login-password");
+        const codeInput = document.querySelector(".editor-login-code");
         const serverAddressInput = document.querySelector(".editor-login-server-address");
         usernameInput.value = "";
         passwordInput.value = "";
+        codeInput.value = "";
         serverAddressInput.value = "";
     }
 
@@ -774,9 +1069,29 @@ class App  {
         passwordEntry.dataset.domainName = password.domain_name;
         passwordEntry.dataset.username = password.username;
         passwordEntry.dataset.password = decryptedPassword;
+        passwordEntry.dataset.totp = "";
         passwordEntry.appendChild(domainPair);
         passwordEntry.appendChild(usernamePair);
 
+        // current one-time code, refreshed by refreshTotpCodes
+        if (password.totp) {
+            passwordEntry.dataset.totp = await decrypt(password.totp, password.totp_nonce)
+                .catch((error) => this.showFailureNotification(error));
+
+            const codeLabel = document.createElement("label");
+            codeLabel.classList.add("entry-label");
+            codeLabel.innerText = "code";
+            const codeValue = document.createElement("span");
+            codeValue.classList.add("entry-value", "entry-totp-code");
+            codeValue.totp = password.totp;
+            codeValue.totpNonce = password.totp_nonce;
+            const codePair = document.createElement("div");
+            codePair.classList.add("entry-pair-row");
+            codePair.appendChild(codeLabel);
+            codePair.appendChild(codeValue);
+            passwordEntry.appendChild(codePair);
+        }
+
         passwordEntry.addEventListener("click", () => {
             this.selectEntry(passwordEntry);
             const passwordEditor = document.querySelector(".password-editor");
@@ -847,6 +1162,48 @@ class App  {
         });
     }
 
+    async loadPasswords() {
+        const page = this.passwordsPage;
+        if (page.done || page.loading) return;
+        page.loading = true;
+        await this.server.getPasswords(this.session.access_token, page.cursor)
+            .then((result) => {
+                if (result.ok == false) this.showFailureNotification(result.data.message)
+                else {
+                    result.data.entries.forEach(password => {
+                        this.addPasswordEntry(Password.from(password));
+                    });
+                    page.cursor = result.data.next_cursor;
+                    page.done = page.cursor === null;
+                }
+            })
+            .catch((error) => {
+                this.showFailureNotification(error);
+            })
+        page.loading = false;
+    }
+
+    async loadNotes() {
+        const page = this.notesPage;
+        if (page.done || page.loading) return;
+        page.loading = true;
+        await this.server.getNotes(this.session.access_token, page.cursor)
+            .then((result) => {
+                if (result.ok == false) this.showFailureNotification(result.data.message)
+                else {
+                    result.data.entries.forEach(note => {
+                        this.addNoteEntry(Note.from(note));
+                    });
+                    page.cursor = result.data.next_cursor;
+                    page.done = page.cursor === null;
+                }
+            })
+            .catch((error) => {
+                this.showFailureNotification(error);
+            })
+        page.loading = false;
+    }
+
     deleteEntries() {
         const entries = document.querySelectorAll(".entry");
         entries.forEach(entry => {
@@ -856,6 +1213,8 @@ class App  {
 }
 
 const invoke = window.__TAURI__.invoke;
+const PAGE_LIMIT = 50;
+const SCROLL_THRESHOLD = 100;
 const app = new App();
 app.main();
 
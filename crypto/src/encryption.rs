@@ -1,22 +1,67 @@
+use crate::search::BlindIndexer;
 use aes_gcm::{aead::Aead, AeadCore, Aes256Gcm, Key, KeyInit};
-use argon2::{password_hash::rand_core::OsRng, Argon2};
+use argon2::{password_hash::rand_core::OsRng, Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
 
 pub trait Encrypter {
     fn encrypt(&self, data: String) -> Result<EncryptedData, aes_gcm::Error>;
     fn decrypt(&self, encrypted_data: EncryptedData) -> Result<String, aes_gcm::Error>;
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_cost: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    pub const ALGORITHM: &'static str = "argon2id";
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_cost: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
 pub struct AesGcmEncrypter {
     key: Key<Aes256Gcm>,
 }
 
 impl AesGcmEncrypter {
     pub fn build(plain_password: String, salt: &[u8; 32]) -> Result<Self, argon2::Error> {
+        Self::build_with_params(plain_password, salt, &KdfParams::default())
+    }
+
+    pub fn build_with_params(
+        plain_password: String,
+        salt: &[u8; 32],
+        kdf_params: &KdfParams,
+    ) -> Result<Self, argon2::Error> {
+        let params = Params::new(
+            kdf_params.memory_cost,
+            kdf_params.iterations,
+            kdf_params.parallelism,
+            None,
+        )?;
         let mut key = [0u8; 32];
-        Argon2::default().hash_password_into(plain_password.as_bytes(), salt, &mut key)?;
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(
+            plain_password.as_bytes(),
+            salt,
+            &mut key,
+        )?;
         let key = key.into();
         Ok(Self { key })
     }
+
+    pub fn blind_indexer(&self) -> BlindIndexer {
+        BlindIndexer::new(&self.key.into())
+    }
 }
 
 impl Encrypter for AesGcmEncrypter {
@@ -63,4 +108,30 @@ mod tests {
 
         assert_eq!(plain_password, decrypted_password);
     }
+
+    #[test]
+    fn kdf_params() {
+        let salt = [1u8; 32];
+        let encrypter = AesGcmEncrypter::build("my_master_password".to_string(), &salt).unwrap();
+        let same = AesGcmEncrypter::build_with_params(
+            "my_master_password".to_string(),
+            &salt,
+            &KdfParams::default(),
+        )
+        .unwrap();
+        let encrypted_data = encrypter.encrypt("my_password".to_string()).unwrap();
+        assert_eq!(same.decrypt(encrypted_data).unwrap(), "my_password");
+
+        let other = AesGcmEncrypter::build_with_params(
+            "my_master_password".to_string(),
+            &salt,
+            &KdfParams {
+                iterations: 3,
+                ..KdfParams::default()
+            },
+        )
+        .unwrap();
+        let encrypted_data = encrypter.encrypt("my_password".to_string()).unwrap();
+        assert!(other.decrypt(encrypted_data).is_err());
+    }
 }
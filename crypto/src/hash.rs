@@ -2,8 +2,11 @@ use argon2::{
     password_hash::{rand_core::OsRng, Error, PasswordHash, PasswordHasher, SaltString},
     Argon2, PasswordVerifier,
 };
+use hmac::{Hmac, Mac};
 use sha3::{Digest, Sha3_256};
 
+const FAKE_SALT_CONTEXT: &[u8] = b"vault fake salt";
+
 pub trait Hasher {
     fn hash_data(&self, data: &String) -> Result<String, Error>;
     fn cmp_data(&self, plain_data: &String, hashed_data: &String) -> Result<bool, Error>;
@@ -44,6 +47,14 @@ pub fn hash_with_sha3(data: &String) -> [u8; 32] {
     hasher.finalize().into()
 }
 
+/// Salt reported for usernames that do not exist, stable for a given server secret.
+pub fn fake_salt(secret: &[u8], username: &str) -> [u8; 32] {
+    let mut mac = Hmac::<Sha3_256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(FAKE_SALT_CONTEXT);
+    mac.update(username.to_lowercase().as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -64,4 +75,12 @@ mod tests {
             .unwrap();
         assert_eq!(result, false);
     }
+
+    #[test]
+    fn fake_salts() {
+        let salt = fake_salt(b"secret", "Alice");
+        assert_eq!(salt, fake_salt(b"secret", "alice"));
+        assert_ne!(salt, fake_salt(b"secret", "bob"));
+        assert_ne!(salt, fake_salt(b"other secret", "alice"));
+    }
 }
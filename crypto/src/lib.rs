@@ -1,5 +1,15 @@
 mod encryption;
 mod hash;
+mod search;
+mod srp;
+mod totp;
+mod webauthn;
 
-pub use encryption::{AesGcmEncrypter, EncryptedData, Encrypter};
-pub use hash::{hash_with_sha3, Argon2Hasher, Hasher};
+pub use encryption::{AesGcmEncrypter, EncryptedData, Encrypter, KdfParams};
+pub use hash::{fake_salt, hash_with_sha3, Argon2Hasher, Hasher};
+pub use search::{domain_tokens, normalize_domain, BlindIndexer};
+pub use srp::{
+    srp_salt, srp_verifier, SrpClient, SrpClientSession, SrpError, SrpServer, SrpServerSession,
+};
+pub use totp::{hotp, Totp, TotpAlgorithm};
+pub use webauthn::{Webauthn, WebauthnAssertion, WebauthnCredential, WebauthnError};
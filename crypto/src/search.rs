@@ -0,0 +1,100 @@
+use hmac::{Hmac, Mac};
+use sha3::Sha3_256;
+
+type HmacSha3 = Hmac<Sha3_256>;
+
+const SEARCH_KEY_CONTEXT: &[u8] = b"vault blind index key";
+
+pub struct BlindIndexer {
+    key: [u8; 32],
+}
+
+impl BlindIndexer {
+    pub fn new(vault_key: &[u8; 32]) -> Self {
+        let mut mac = HmacSha3::new_from_slice(vault_key).expect("HMAC accepts any key length");
+        mac.update(SEARCH_KEY_CONTEXT);
+        Self {
+            key: mac.finalize().into_bytes().into(),
+        }
+    }
+
+    pub fn index(&self, token: &str) -> [u8; 32] {
+        let mut mac = HmacSha3::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(token.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+
+    pub fn domain_indexes(&self, domain_name: &str) -> Vec<[u8; 32]> {
+        domain_tokens(domain_name)
+            .iter()
+            .map(|token| self.index(token))
+            .collect()
+    }
+}
+
+pub fn normalize_domain(domain_name: &str) -> String {
+    let domain_name = domain_name.trim().to_lowercase();
+    let host = match domain_name.split_once("://") {
+        Some((_, rest)) => rest,
+        None => &domain_name,
+    };
+    let host = host.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = host.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default();
+    let host = host.trim_end_matches('.');
+    host.strip_prefix("www.").unwrap_or(host).to_string()
+}
+
+pub fn domain_tokens(domain_name: &str) -> Vec<String> {
+    let host = normalize_domain(domain_name);
+    let labels: Vec<&str> = host.split('.').filter(|label| !label.is_empty()).collect();
+
+    let mut tokens = Vec::new();
+    for start in 0..labels.len().saturating_sub(1) {
+        tokens.push(labels[start..].join("."));
+    }
+    for label in &labels {
+        tokens.push(label.to_string());
+    }
+    tokens.sort();
+    tokens.dedup();
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domain_normalization() {
+        assert_eq!(
+            normalize_domain("https://WWW.GitHub.com/login?x=1"),
+            "github.com"
+        );
+        assert_eq!(
+            normalize_domain(" user@mail.example.org:443 "),
+            "mail.example.org"
+        );
+        assert_eq!(normalize_domain("example.com."), "example.com");
+
+        let tokens = domain_tokens("https://login.github.com");
+        assert_eq!(
+            tokens,
+            vec!["com", "github", "github.com", "login", "login.github.com"]
+        );
+    }
+
+    #[test]
+    fn blind_indexes() {
+        let indexer = BlindIndexer::new(&[1u8; 32]);
+        let other_indexer = BlindIndexer::new(&[2u8; 32]);
+
+        assert_eq!(indexer.index("github"), indexer.index("github"));
+        assert_ne!(indexer.index("github"), indexer.index("gitlab"));
+        assert_ne!(indexer.index("github"), other_indexer.index("github"));
+
+        let indexes = indexer.domain_indexes("https://www.github.com/");
+        assert!(indexes.contains(&indexer.index("github.com")));
+        assert!(indexes.contains(&indexer.index("github")));
+    }
+}
@@ -0,0 +1,363 @@
+use crate::encryption::KdfParams;
+use argon2::{Algorithm, Argon2, Params, Version};
+use num_bigint::BigUint;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+// RFC 5054 2048-bit group
+const N_HEX: &[u8] = b"\
+    AC6BDB41324A9A9BF166DE5E1389582FAF72B6651987EE07FC3192943DB56050\
+    A37329CBB4A099ED8193E0757767A13DD52312AB4B03310DCD7F48A9DA04FD50\
+    E8083969EDB767B0CF6095179A163AB3661A05FBD5FAAAE82918A9962F0B93B8\
+    55F97993EC975EEAA80D740ADBF4FF747359D041D5C33EA71D281E446B14773B\
+    CA97B43A23FB801676BD207A436C6481F1D2B9078717461A5B9D32E688F87748\
+    544523B524B0D57D5EA77A2775D2ECFA032CFBDBF52FB3786160279004E57AE6\
+    AF874E7303CE53299CCC041C7BC308D82A5698F3A8D0C38271AE35F8E9DBFBB6\
+    94B5C803D89F7AE435DE236D525F54759B65E372FCD68EF20FA7111F9E4AFF73";
+const G: u32 = 2;
+const LENGTH: usize = 256;
+const EPHEMERAL_LENGTH: usize = 32;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SrpError {
+    Kdf,
+    PublicKey,
+    Proof,
+}
+
+struct Group {
+    n: BigUint,
+    g: BigUint,
+    k: BigUint,
+}
+
+impl Group {
+    fn new() -> Self {
+        let n = BigUint::parse_bytes(N_HEX, 16).expect("valid group prime");
+        let g = BigUint::from(G);
+        let k = BigUint::from_bytes_be(&hash(&[&pad(&n), &pad(&g)]));
+        Self { n, g, k }
+    }
+}
+
+fn pad(value: &BigUint) -> [u8; LENGTH] {
+    let bytes = value.to_bytes_be();
+    let mut padded = [0u8; LENGTH];
+    padded[LENGTH - bytes.len()..].copy_from_slice(&bytes);
+    padded
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn random_ephemeral() -> BigUint {
+    let mut bytes = [0u8; EPHEMERAL_LENGTH];
+    OsRng.fill_bytes(&mut bytes);
+    BigUint::from_bytes_be(&bytes)
+}
+
+// x is stretched with argon2id, so a leaked verifier costs as much to attack as a password hash
+fn private_key(password: &str, salt: &[u8; 32]) -> Result<BigUint, SrpError> {
+    let kdf_params = KdfParams::default();
+    let params = Params::new(
+        kdf_params.memory_cost,
+        kdf_params.iterations,
+        kdf_params.parallelism,
+        None,
+    )
+    .map_err(|_| SrpError::Kdf)?;
+    let mut x = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut x)
+        .map_err(|_| SrpError::Kdf)?;
+    Ok(BigUint::from_bytes_be(&hash(&[salt, &x])))
+}
+
+struct Proofs {
+    client_proof: [u8; 32],
+    server_proof: [u8; 32],
+}
+
+// M1 = H(H(N) xor H(g) | H(I) | s | A | B | K), M2 = H(A | M1 | K)
+fn proofs(
+    group: &Group,
+    username: &str,
+    salt: &[u8; 32],
+    a_pub: &BigUint,
+    b_pub: &BigUint,
+    secret: &BigUint,
+) -> Proofs {
+    let key = hash(&[&pad(secret)]);
+    let hash_n = hash(&[&pad(&group.n)]);
+    let hash_g = hash(&[&pad(&group.g)]);
+    let group_hash: Vec<u8> = hash_n.iter().zip(hash_g).map(|(n, g)| n ^ g).collect();
+    let hash_username = hash(&[username.to_lowercase().as_bytes()]);
+    let (a_pub, b_pub) = (pad(a_pub), pad(b_pub));
+    let client_proof = hash(&[&group_hash, &hash_username, salt, &a_pub, &b_pub, &key]);
+    let server_proof = hash(&[&a_pub, &client_proof, &key]);
+    Proofs {
+        client_proof,
+        server_proof,
+    }
+}
+
+fn scrambler(a_pub: &BigUint, b_pub: &BigUint) -> BigUint {
+    BigUint::from_bytes_be(&hash(&[&pad(a_pub), &pad(b_pub)]))
+}
+
+fn parse_public_key(group: &Group, public_key: &[u8]) -> Result<BigUint, SrpError> {
+    let public_key = BigUint::from_bytes_be(public_key);
+    if public_key >= group.n || (&public_key % &group.n) == BigUint::default() {
+        return Err(SrpError::PublicKey);
+    }
+    Ok(public_key)
+}
+
+pub fn srp_salt() -> [u8; 32] {
+    let mut salt = [0u8; 32];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Registration value stored by the server instead of a password hash.
+pub fn srp_verifier(password: &str, salt: &[u8; 32]) -> Result<Vec<u8>, SrpError> {
+    let group = Group::new();
+    let x = private_key(password, salt)?;
+    Ok(pad(&group.g.modpow(&x, &group.n)).to_vec())
+}
+
+pub struct SrpClient {
+    group: Group,
+    a: BigUint,
+    a_pub: BigUint,
+}
+
+impl SrpClient {
+    pub fn new() -> Self {
+        let group = Group::new();
+        let a = random_ephemeral();
+        let a_pub = group.g.modpow(&a, &group.n);
+        Self { group, a, a_pub }
+    }
+
+    pub fn public_key(&self) -> Vec<u8> {
+        pad(&self.a_pub).to_vec()
+    }
+
+    pub fn process(
+        &self,
+        username: &str,
+        password: &str,
+        salt: &[u8; 32],
+        server_public_key: &[u8],
+    ) -> Result<SrpClientSession, SrpError> {
+        let group = &self.group;
+        let b_pub = parse_public_key(group, server_public_key)?;
+        let u = scrambler(&self.a_pub, &b_pub);
+        if u == BigUint::default() {
+            return Err(SrpError::PublicKey);
+        }
+        let x = private_key(password, salt)?;
+
+        // S = (B - k * g^x) ^ (a + u * x)
+        let kgx = (&group.k * group.g.modpow(&x, &group.n)) % &group.n;
+        let base = (&b_pub + &group.n - kgx) % &group.n;
+        let secret = base.modpow(&(&self.a + &u * &x), &group.n);
+
+        let proofs = proofs(group, username, salt, &self.a_pub, &b_pub, &secret);
+        Ok(SrpClientSession {
+            client_proof: proofs.client_proof,
+            server_proof: proofs.server_proof,
+        })
+    }
+}
+
+impl Default for SrpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct SrpClientSession {
+    client_proof: [u8; 32],
+    server_proof: [u8; 32],
+}
+
+impl SrpClientSession {
+    pub fn client_proof(&self) -> &[u8; 32] {
+        &self.client_proof
+    }
+
+    /// Checks that the server knew the verifier, so a fake server is detected.
+    pub fn verify_server(&self, server_proof: &[u8]) -> Result<(), SrpError> {
+        match bool::from(self.server_proof.ct_eq(server_proof)) {
+            true => Ok(()),
+            false => Err(SrpError::Proof),
+        }
+    }
+}
+
+pub struct SrpServer {
+    group: Group,
+    v: BigUint,
+    b: BigUint,
+    b_pub: BigUint,
+}
+
+impl SrpServer {
+    pub fn new(verifier: &[u8]) -> Self {
+        let group = Group::new();
+        let v = BigUint::from_bytes_be(verifier);
+        let b = random_ephemeral();
+
+        // B = k * v + g^b
+        let b_pub = (&group.k * &v + group.g.modpow(&b, &group.n)) % &group.n;
+        Self { group, v, b, b_pub }
+    }
+
+    pub fn public_key(&self) -> Vec<u8> {
+        pad(&self.b_pub).to_vec()
+    }
+
+    pub fn process(
+        &self,
+        username: &str,
+        salt: &[u8; 32],
+        client_public_key: &[u8],
+    ) -> Result<SrpServerSession, SrpError> {
+        let group = &self.group;
+        let a_pub = parse_public_key(group, client_public_key)?;
+        let u = scrambler(&a_pub, &self.b_pub);
+
+        // S = (A * v^u) ^ b
+        let base = (&a_pub * self.v.modpow(&u, &group.n)) % &group.n;
+        let secret = base.modpow(&self.b, &group.n);
+
+        let proofs = proofs(group, username, salt, &a_pub, &self.b_pub, &secret);
+        Ok(SrpServerSession::new(
+            proofs.client_proof,
+            proofs.server_proof,
+        ))
+    }
+}
+
+/// Expected proofs of a login, small enough to keep between the two requests.
+pub struct SrpServerSession {
+    client_proof: [u8; 32],
+    server_proof: [u8; 32],
+}
+
+impl SrpServerSession {
+    pub fn new(client_proof: [u8; 32], server_proof: [u8; 32]) -> Self {
+        Self {
+            client_proof,
+            server_proof,
+        }
+    }
+
+    pub fn client_proof(&self) -> &[u8; 32] {
+        &self.client_proof
+    }
+
+    pub fn server_proof(&self) -> &[u8; 32] {
+        &self.server_proof
+    }
+
+    /// Returns the proof to send back once the client proved it knows the password.
+    pub fn verify_client(&self, client_proof: &[u8]) -> Result<[u8; 32], SrpError> {
+        match bool::from(self.client_proof.ct_eq(client_proof)) {
+            true => Ok(self.server_proof),
+            false => Err(SrpError::Proof),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USERNAME: &str = "alice";
+    const PASSWORD: &str = "mve53!#*qwp627.[fgm31";
+    const SALT: [u8; 32] = [7u8; 32];
+
+    fn login(password: &str) -> (SrpClientSession, Result<[u8; 32], SrpError>) {
+        let verifier = srp_verifier(PASSWORD, &SALT).unwrap();
+
+        let client = SrpClient::new();
+        let server = SrpServer::new(&verifier);
+        let server_session = server
+            .process(USERNAME, &SALT, &client.public_key())
+            .unwrap();
+        let client_session = client
+            .process(USERNAME, password, &SALT, &server.public_key())
+            .unwrap();
+
+        let server_proof = server_session.verify_client(client_session.client_proof());
+        (client_session, server_proof)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (client_session, server_proof) = login(PASSWORD);
+        let server_proof = server_proof.unwrap();
+        assert_eq!(client_session.verify_server(&server_proof), Ok(()));
+    }
+
+    #[test]
+    fn wrong_password() {
+        let (_, server_proof) = login("lin354v2v23c@^Y");
+        assert_eq!(server_proof, Err(SrpError::Proof));
+    }
+
+    #[test]
+    fn fake_server() {
+        // a server without the verifier cannot produce the expected proof
+        let fake_verifier = srp_verifier("guess", &SALT).unwrap();
+        let client = SrpClient::new();
+        let server = SrpServer::new(&fake_verifier);
+        let server_session = server
+            .process(USERNAME, &SALT, &client.public_key())
+            .unwrap();
+        let client_session = client
+            .process(USERNAME, PASSWORD, &SALT, &server.public_key())
+            .unwrap();
+
+        assert_eq!(
+            client_session.verify_server(server_session.server_proof()),
+            Err(SrpError::Proof)
+        );
+    }
+
+    #[test]
+    fn invalid_public_keys() {
+        let verifier = srp_verifier(PASSWORD, &SALT).unwrap();
+        let group = Group::new();
+        let server = SrpServer::new(&verifier);
+        for public_key in [vec![0u8; LENGTH], pad(&group.n).to_vec()] {
+            assert!(matches!(
+                server.process(USERNAME, &SALT, &public_key),
+                Err(SrpError::PublicKey)
+            ));
+            assert!(matches!(
+                SrpClient::new().process(USERNAME, PASSWORD, &SALT, &public_key),
+                Err(SrpError::PublicKey)
+            ));
+        }
+    }
+
+    #[test]
+    fn verifier_does_not_contain_password() {
+        let verifier = srp_verifier(PASSWORD, &SALT).unwrap();
+        assert_eq!(verifier.len(), LENGTH);
+        assert_ne!(verifier, srp_verifier(PASSWORD, &[8u8; 32]).unwrap());
+        assert!(!verifier
+            .windows(PASSWORD.len())
+            .any(|window| window == PASSWORD.as_bytes()));
+    }
+}
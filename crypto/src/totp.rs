@@ -0,0 +1,337 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{digest::KeyInit, Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use std::str::FromStr;
+use subtle::ConstantTimeEq;
+
+const SECRET_LENGTH: usize = 20;
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u64 = 30;
+const MIN_DIGITS: u32 = 6;
+const MAX_DIGITS: u32 = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TotpAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl TotpAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+            Self::Sha512 => "SHA512",
+        }
+    }
+}
+
+impl FromStr for TotpAlgorithm {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_uppercase().replace('-', "").as_str() {
+            "SHA1" => Ok(Self::Sha1),
+            "SHA256" => Ok(Self::Sha256),
+            "SHA512" => Ok(Self::Sha512),
+            _ => Err(()),
+        }
+    }
+}
+
+pub struct Totp {
+    secret: Vec<u8>,
+    algorithm: TotpAlgorithm,
+    digits: u32,
+    period: u64,
+}
+
+impl Totp {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self {
+            secret,
+            algorithm: TotpAlgorithm::default(),
+            digits: DEFAULT_DIGITS,
+            period: DEFAULT_PERIOD,
+        }
+    }
+
+    pub fn generate() -> Self {
+        let mut secret = vec![0u8; SECRET_LENGTH];
+        OsRng.fill_bytes(&mut secret);
+        Self::new(secret)
+    }
+
+    pub fn from_base32(secret: &str) -> Option<Self> {
+        let secret = secret.trim().replace(' ', "").to_uppercase();
+        let secret = BASE32_NOPAD
+            .decode(secret.trim_end_matches('=').as_bytes())
+            .ok()?;
+        if secret.is_empty() {
+            return None;
+        }
+        Some(Self::new(secret))
+    }
+
+    /// Parses an `otpauth://totp/...` URI, rejecting an empty secret, digits outside 6-8 and a
+    /// zero period.
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        let rest = uri.trim().strip_prefix("otpauth://")?;
+        let (kind, rest) = rest.split_once('/')?;
+        if !kind.eq_ignore_ascii_case("totp") {
+            return None;
+        }
+        let (_, query) = rest.split_once('?')?;
+
+        let mut totp = None;
+        let mut algorithm = TotpAlgorithm::default();
+        let mut digits = DEFAULT_DIGITS;
+        let mut period = DEFAULT_PERIOD;
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            match key.to_lowercase().as_str() {
+                "secret" => totp = Some(Self::from_base32(value)?),
+                "algorithm" => algorithm = value.parse().ok()?,
+                "digits" => digits = value.parse().ok()?,
+                "period" => period = value.parse().ok()?,
+                _ => {}
+            }
+        }
+
+        totp?
+            .with_algorithm(algorithm)
+            .with_digits(digits)?
+            .with_period(period)
+    }
+
+    /// Accepts either an `otpauth://` URI or a bare base32 secret.
+    pub fn parse(value: &str) -> Option<Self> {
+        if value.trim().starts_with("otpauth://") {
+            Self::from_uri(value)
+        } else {
+            Self::from_base32(value)
+        }
+    }
+
+    pub fn with_algorithm(mut self, algorithm: TotpAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Returns `None` when `digits` is outside 6 to 8, the lengths authenticator apps support.
+    pub fn with_digits(mut self, digits: u32) -> Option<Self> {
+        if !(MIN_DIGITS..=MAX_DIGITS).contains(&digits) {
+            return None;
+        }
+        self.digits = digits;
+        Some(self)
+    }
+
+    /// Returns `None` for a zero period, codes are counted in whole periods.
+    pub fn with_period(mut self, period: u64) -> Option<Self> {
+        if period == 0 {
+            return None;
+        }
+        self.period = period;
+        Some(self)
+    }
+
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    pub fn secret_base32(&self) -> String {
+        BASE32_NOPAD.encode(&self.secret)
+    }
+
+    pub fn step(&self, timestamp: u64) -> u64 {
+        timestamp / self.period
+    }
+
+    pub fn seconds_remaining(&self, timestamp: u64) -> u64 {
+        self.period - timestamp % self.period
+    }
+
+    pub fn code_at(&self, timestamp: u64) -> String {
+        self.code_for_step(self.step(timestamp))
+    }
+
+    fn code_for_step(&self, step: u64) -> String {
+        let code = hotp(self.algorithm, &self.secret, step, self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+
+    /// Returns the time step the code belongs to, accepting `skew` steps around `timestamp`.
+    pub fn verify(&self, code: &str, timestamp: u64, skew: u64) -> Option<u64> {
+        let step = self.step(timestamp);
+        (step.saturating_sub(skew)..=step + skew).find(|&step| {
+            self.code_for_step(step)
+                .as_bytes()
+                .ct_eq(code.trim().as_bytes())
+                .into()
+        })
+    }
+
+    pub fn otpauth_uri(&self, issuer: &str, account: &str) -> String {
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm={}&digits={}&period={}",
+            percent_encode(issuer),
+            percent_encode(account),
+            self.secret_base32(),
+            percent_encode(issuer),
+            self.algorithm.name(),
+            self.digits,
+            self.period
+        )
+    }
+}
+
+pub fn hotp(algorithm: TotpAlgorithm, secret: &[u8], counter: u64, digits: u32) -> u32 {
+    let hash = match algorithm {
+        TotpAlgorithm::Sha1 => mac::<Hmac<Sha1>>(secret, &counter.to_be_bytes()),
+        TotpAlgorithm::Sha256 => mac::<Hmac<Sha256>>(secret, &counter.to_be_bytes()),
+        TotpAlgorithm::Sha512 => mac::<Hmac<Sha512>>(secret, &counter.to_be_bytes()),
+    };
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    binary % 10u32.pow(digits)
+}
+
+fn mac<M: Mac + KeyInit>(secret: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as KeyInit>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn hotp_rfc4226_vectors() {
+        let expected = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(
+                hotp(TotpAlgorithm::Sha1, RFC_SECRET, counter as u64, 6),
+                *code
+            );
+        }
+    }
+
+    #[test]
+    fn totp_rfc6238_vectors() {
+        let totp = Totp::new(RFC_SECRET.to_vec()).with_digits(8).unwrap();
+        let vectors = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+        for (timestamp, code) in vectors {
+            assert_eq!(totp.code_at(timestamp), code);
+        }
+    }
+
+    #[test]
+    fn totp_rfc6238_sha2_vectors() {
+        let sha256 = Totp::new(b"12345678901234567890123456789012".to_vec())
+            .with_algorithm(TotpAlgorithm::Sha256)
+            .with_digits(8)
+            .unwrap();
+        let sha512 =
+            Totp::new(b"1234567890123456789012345678901234567890123456789012345678901234".to_vec())
+                .with_algorithm(TotpAlgorithm::Sha512)
+                .with_digits(8)
+                .unwrap();
+        let vectors = [
+            (59, "46119246", "90693936"),
+            (1111111109, "68084774", "25091201"),
+            (1111111111, "67062674", "99943326"),
+            (1234567890, "91819424", "93441116"),
+            (2000000000, "90698825", "38618901"),
+            (20000000000, "77737706", "47863826"),
+        ];
+        for (timestamp, sha256_code, sha512_code) in vectors {
+            assert_eq!(sha256.code_at(timestamp), sha256_code);
+            assert_eq!(sha512.code_at(timestamp), sha512_code);
+        }
+    }
+
+    #[test]
+    fn totp_digits_and_period_are_bounded() {
+        assert!(Totp::new(RFC_SECRET.to_vec()).with_digits(5).is_none());
+        assert!(Totp::new(RFC_SECRET.to_vec()).with_digits(10).is_none());
+        assert!(Totp::new(RFC_SECRET.to_vec()).with_period(0).is_none());
+        assert!(Totp::new(RFC_SECRET.to_vec()).with_period(60).is_some());
+    }
+
+    #[test]
+    fn totp_uri_parsing() {
+        let totp = Totp::from_uri(
+            "otpauth://totp/ACME:ops?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=ACME&algorithm=SHA256&digits=7&period=60",
+        )
+        .unwrap();
+        assert_eq!(totp.secret(), RFC_SECRET);
+        assert_eq!(totp.code_at(59).len(), 7);
+        assert_eq!(totp.step(119), 1);
+        assert_eq!(totp.seconds_remaining(100), 20);
+        assert_eq!(
+            totp.code_at(59),
+            format!("{:07}", hotp(TotpAlgorithm::Sha256, RFC_SECRET, 0, 7))
+        );
+
+        let reparsed = Totp::parse(&totp.otpauth_uri("ACME", "ops")).unwrap();
+        assert_eq!(reparsed.code_at(1234), totp.code_at(1234));
+
+        assert!(Totp::from_uri("otpauth://hotp/x?secret=GEZDGNBV").is_none());
+        assert!(Totp::from_uri("otpauth://totp/x?secret=GEZDGNBV&digits=9").is_none());
+        assert!(Totp::from_uri("otpauth://totp/x?secret=GEZDGNBV&period=0").is_none());
+        assert!(Totp::from_uri("otpauth://totp/x?secret=GEZDGNBV&algorithm=MD5").is_none());
+        assert!(Totp::from_uri("otpauth://totp/x?secret=&issuer=ACME").is_none());
+        assert!(Totp::parse("gezd gnbv").is_some());
+        assert!(Totp::parse("").is_none());
+        assert!(Totp::parse("  =").is_none());
+    }
+
+    #[test]
+    fn totp_verification() {
+        let totp = Totp::generate();
+        let code = totp.code_at(1_000_000);
+        assert_eq!(totp.verify(&code, 1_000_000, 1), Some(totp.step(1_000_000)));
+        assert_eq!(totp.verify(&code, 1_000_030, 1), Some(totp.step(1_000_000)));
+        assert_eq!(totp.verify(&code, 1_000_090, 1), None);
+
+        let parsed = Totp::from_base32(&totp.secret_base32()).unwrap();
+        assert_eq!(parsed.secret(), totp.secret());
+        assert!(totp
+            .otpauth_uri("vault", "alice smith")
+            .starts_with("otpauth://totp/vault:alice%20smith?secret="));
+    }
+}
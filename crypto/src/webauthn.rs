@@ -0,0 +1,361 @@
+use ciborium::Value;
+use data_encoding::BASE64URL_NOPAD;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use rand_core::{OsRng, RngCore};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+const CHALLENGE_LENGTH: usize = 32;
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+const COSE_KEY_TYPE_EC2: i128 = 2;
+const COSE_ALGORITHM_ES256: i128 = -7;
+const COSE_CURVE_P256: i128 = 1;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WebauthnError {
+    ClientData,
+    Challenge,
+    Origin,
+    AuthenticatorData,
+    RelyingParty,
+    UserPresence,
+    PublicKey,
+    Signature,
+    Counter,
+}
+
+pub struct WebauthnCredential {
+    pub credential_id: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+pub struct WebauthnAssertion<'a> {
+    pub client_data_json: &'a [u8],
+    pub authenticator_data: &'a [u8],
+    pub signature: &'a [u8],
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+}
+
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    attested: &'a [u8],
+}
+
+#[derive(Clone)]
+pub struct Webauthn {
+    rp_id: String,
+    origin: String,
+}
+
+impl Webauthn {
+    pub fn new(rp_id: String, origin: String) -> Self {
+        Self { rp_id, origin }
+    }
+
+    pub fn rp_id(&self) -> &str {
+        &self.rp_id
+    }
+
+    pub fn create_challenge() -> String {
+        let mut challenge = [0u8; CHALLENGE_LENGTH];
+        OsRng.fill_bytes(&mut challenge);
+        BASE64URL_NOPAD.encode(&challenge)
+    }
+
+    /// Only ES256 credentials are accepted; attestation statements are not verified.
+    pub fn verify_registration(
+        &self,
+        challenge: &str,
+        client_data_json: &[u8],
+        attestation_object: &[u8],
+    ) -> Result<WebauthnCredential, WebauthnError> {
+        self.verify_client_data(challenge, client_data_json, "webauthn.create")?;
+
+        let attestation: Value = ciborium::de::from_reader(attestation_object)
+            .map_err(|_| WebauthnError::AuthenticatorData)?;
+        let auth_data = match map_get(&attestation, "authData") {
+            Some(Value::Bytes(auth_data)) => auth_data,
+            _ => return Err(WebauthnError::AuthenticatorData),
+        };
+        let auth_data = self.verify_authenticator_data(auth_data)?;
+        if auth_data.flags & FLAG_ATTESTED_CREDENTIAL == 0 || auth_data.attested.len() < 18 {
+            return Err(WebauthnError::AuthenticatorData);
+        }
+
+        // aaguid (16 bytes), credential id length (2 bytes), credential id, COSE public key
+        let id_length =
+            u16::from_be_bytes([auth_data.attested[16], auth_data.attested[17]]) as usize;
+        let rest = &auth_data.attested[18..];
+        if rest.len() < id_length {
+            return Err(WebauthnError::AuthenticatorData);
+        }
+        let (credential_id, cose_key) = rest.split_at(id_length);
+
+        Ok(WebauthnCredential {
+            credential_id: credential_id.to_vec(),
+            public_key: parse_cose_key(cose_key)?,
+            sign_count: auth_data.sign_count,
+        })
+    }
+
+    /// Returns the new signature counter which the caller should store.
+    pub fn verify_assertion(
+        &self,
+        challenge: &str,
+        credential: &WebauthnCredential,
+        assertion: &WebauthnAssertion,
+    ) -> Result<u32, WebauthnError> {
+        self.verify_client_data(challenge, assertion.client_data_json, "webauthn.get")?;
+        let auth_data = self.verify_authenticator_data(assertion.authenticator_data)?;
+
+        let public_key = VerifyingKey::from_sec1_bytes(&credential.public_key)
+            .map_err(|_| WebauthnError::PublicKey)?;
+        let signature =
+            Signature::from_der(assertion.signature).map_err(|_| WebauthnError::Signature)?;
+        let mut message = assertion.authenticator_data.to_vec();
+        message.extend_from_slice(&Sha256::digest(assertion.client_data_json));
+        public_key
+            .verify(&message, &signature)
+            .map_err(|_| WebauthnError::Signature)?;
+
+        // authenticators without a counter always report zero
+        if (auth_data.sign_count != 0 || credential.sign_count != 0)
+            && auth_data.sign_count <= credential.sign_count
+        {
+            return Err(WebauthnError::Counter);
+        }
+        Ok(auth_data.sign_count)
+    }
+
+    fn verify_client_data(
+        &self,
+        challenge: &str,
+        client_data_json: &[u8],
+        ceremony: &str,
+    ) -> Result<(), WebauthnError> {
+        let client_data: ClientData =
+            serde_json::from_slice(client_data_json).map_err(|_| WebauthnError::ClientData)?;
+        if client_data.ceremony != ceremony {
+            return Err(WebauthnError::ClientData);
+        }
+        if !bool::from(client_data.challenge.as_bytes().ct_eq(challenge.as_bytes())) {
+            return Err(WebauthnError::Challenge);
+        }
+        if client_data.origin != self.origin {
+            return Err(WebauthnError::Origin);
+        }
+        Ok(())
+    }
+
+    fn verify_authenticator_data<'a>(
+        &self,
+        auth_data: &'a [u8],
+    ) -> Result<AuthenticatorData<'a>, WebauthnError> {
+        if auth_data.len() < 37 {
+            return Err(WebauthnError::AuthenticatorData);
+        }
+        let auth_data = AuthenticatorData {
+            rp_id_hash: &auth_data[..32],
+            flags: auth_data[32],
+            sign_count: u32::from_be_bytes([
+                auth_data[33],
+                auth_data[34],
+                auth_data[35],
+                auth_data[36],
+            ]),
+            attested: &auth_data[37..],
+        };
+        if auth_data.rp_id_hash != Sha256::digest(self.rp_id.as_bytes()).as_slice() {
+            return Err(WebauthnError::RelyingParty);
+        }
+        if auth_data.flags & FLAG_USER_PRESENT == 0 {
+            return Err(WebauthnError::UserPresence);
+        }
+        Ok(auth_data)
+    }
+}
+
+fn map_get<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    value
+        .as_map()?
+        .iter()
+        .find(|(map_key, _)| map_key.as_text() == Some(key))
+        .map(|(_, value)| value)
+}
+
+fn cose_get(value: &Value, key: i128) -> Option<&Value> {
+    value
+        .as_map()?
+        .iter()
+        .find(|(map_key, _)| map_key.as_integer().map(i128::from) == Some(key))
+        .map(|(_, value)| value)
+}
+
+fn cose_integer(value: &Value, key: i128) -> Option<i128> {
+    cose_get(value, key)?.as_integer().map(i128::from)
+}
+
+fn parse_cose_key(cose_key: &[u8]) -> Result<Vec<u8>, WebauthnError> {
+    let key: Value = ciborium::de::from_reader(cose_key).map_err(|_| WebauthnError::PublicKey)?;
+    if cose_integer(&key, 1) != Some(COSE_KEY_TYPE_EC2)
+        || cose_integer(&key, 3) != Some(COSE_ALGORITHM_ES256)
+        || cose_integer(&key, -1) != Some(COSE_CURVE_P256)
+    {
+        return Err(WebauthnError::PublicKey);
+    }
+    let (x, y) = match (cose_get(&key, -2), cose_get(&key, -3)) {
+        (Some(Value::Bytes(x)), Some(Value::Bytes(y))) if x.len() == 32 && y.len() == 32 => (x, y),
+        _ => return Err(WebauthnError::PublicKey),
+    };
+
+    let mut public_key = Vec::with_capacity(65);
+    public_key.push(0x04);
+    public_key.extend_from_slice(x);
+    public_key.extend_from_slice(y);
+    VerifyingKey::from_sec1_bytes(&public_key).map_err(|_| WebauthnError::PublicKey)?;
+    Ok(public_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::Signer, SigningKey};
+
+    const RP_ID: &str = "vault.example";
+    const ORIGIN: &str = "https://vault.example";
+
+    fn client_data(ceremony: &str, challenge: &str, origin: &str) -> Vec<u8> {
+        format!(r#"{{"type":"{ceremony}","challenge":"{challenge}","origin":"{origin}"}}"#)
+            .into_bytes()
+    }
+
+    fn authenticator_data(rp_id: &str, flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut auth_data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        auth_data.push(flags);
+        auth_data.extend_from_slice(&sign_count.to_be_bytes());
+        auth_data
+    }
+
+    fn attestation_object(signing_key: &SigningKey, credential_id: &[u8]) -> Vec<u8> {
+        let point = signing_key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer(2.into())),
+            (Value::Integer(3.into()), Value::Integer((-7).into())),
+            (Value::Integer((-1).into()), Value::Integer(1.into())),
+            (
+                Value::Integer((-2).into()),
+                Value::Bytes(point.x().unwrap().to_vec()),
+            ),
+            (
+                Value::Integer((-3).into()),
+                Value::Bytes(point.y().unwrap().to_vec()),
+            ),
+        ]);
+
+        let mut auth_data = authenticator_data(RP_ID, 0x41, 0);
+        auth_data.extend_from_slice(&[0u8; 16]);
+        auth_data.extend_from_slice(&(credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(credential_id);
+        ciborium::ser::into_writer(&cose_key, &mut auth_data).unwrap();
+
+        let attestation = Value::Map(vec![
+            (Value::Text("fmt".into()), Value::Text("none".into())),
+            (Value::Text("attStmt".into()), Value::Map(vec![])),
+            (Value::Text("authData".into()), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = Vec::new();
+        ciborium::ser::into_writer(&attestation, &mut attestation_object).unwrap();
+        attestation_object
+    }
+
+    #[test]
+    fn webauthn_ceremonies() {
+        let webauthn = Webauthn::new(RP_ID.to_string(), ORIGIN.to_string());
+        let signing_key = SigningKey::random(&mut OsRng);
+
+        let challenge = Webauthn::create_challenge();
+        let attestation_object = attestation_object(&signing_key, b"credential");
+        let client_data_json = client_data("webauthn.create", &challenge, ORIGIN);
+        let credential = webauthn
+            .verify_registration(&challenge, &client_data_json, &attestation_object)
+            .unwrap();
+        assert_eq!(credential.credential_id, b"credential");
+        assert_eq!(
+            webauthn
+                .verify_registration("other", &client_data_json, &attestation_object)
+                .err(),
+            Some(WebauthnError::Challenge)
+        );
+        let foreign = client_data("webauthn.create", &challenge, "https://evil.example");
+        assert_eq!(
+            webauthn
+                .verify_registration(&challenge, &foreign, &attestation_object)
+                .err(),
+            Some(WebauthnError::Origin)
+        );
+
+        let challenge = Webauthn::create_challenge();
+        let client_data_json = client_data("webauthn.get", &challenge, ORIGIN);
+        let auth_data = authenticator_data(RP_ID, 0x01, 1);
+        let mut message = auth_data.clone();
+        message.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature: Signature = signing_key.sign(&message);
+        let signature = signature.to_der();
+        let assertion = WebauthnAssertion {
+            client_data_json: &client_data_json,
+            authenticator_data: &auth_data,
+            signature: signature.as_bytes(),
+        };
+        assert_eq!(
+            webauthn.verify_assertion(&challenge, &credential, &assertion),
+            Ok(1)
+        );
+
+        let replayed = WebauthnCredential {
+            sign_count: 1,
+            ..credential
+        };
+        assert_eq!(
+            webauthn.verify_assertion(&challenge, &replayed, &assertion),
+            Err(WebauthnError::Counter)
+        );
+
+        let other_key = SigningKey::random(&mut OsRng);
+        let other = WebauthnCredential {
+            credential_id: vec![],
+            public_key: other_key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec(),
+            sign_count: 0,
+        };
+        assert_eq!(
+            webauthn.verify_assertion(&challenge, &other, &assertion),
+            Err(WebauthnError::Signature)
+        );
+
+        let wrong_rp = WebauthnAssertion {
+            authenticator_data: &authenticator_data("other.example", 0x01, 2),
+            ..assertion
+        };
+        assert_eq!(
+            webauthn
+                .verify_assertion(&challenge, &replayed, &wrong_rp)
+                .err(),
+            Some(WebauthnError::RelyingParty)
+        );
+    }
+}
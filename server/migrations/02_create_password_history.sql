@@ -0,0 +1,11 @@
+CREATE TABLE password_history (
+    password_id UUID NOT NULL REFERENCES passwords(password_id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    domain_name VARCHAR(255) NOT NULL,
+    username VARCHAR(255) NOT NULL,
+    password BYTEA NOT NULL,
+    nonce BYTEA NOT NULL,
+    replaced_at BIGINT NOT NULL,
+    PRIMARY KEY (password_id, version)
+);
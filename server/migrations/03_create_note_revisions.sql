@@ -0,0 +1,11 @@
+CREATE TABLE note_revisions (
+    note_id UUID NOT NULL REFERENCES notes(note_id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    title BYTEA,
+    title_nonce BYTEA,
+    content BYTEA,
+    content_nonce BYTEA,
+    replaced_at BIGINT NOT NULL,
+    PRIMARY KEY (note_id, revision)
+);
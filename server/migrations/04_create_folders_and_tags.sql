@@ -0,0 +1,29 @@
+CREATE TABLE folders (
+    folder_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    parent_id UUID REFERENCES folders(folder_id) ON DELETE CASCADE,
+    name BYTEA NOT NULL,
+    name_nonce BYTEA NOT NULL
+);
+
+CREATE TABLE tags (
+    tag_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    name BYTEA NOT NULL,
+    name_nonce BYTEA NOT NULL
+);
+
+ALTER TABLE passwords ADD COLUMN folder_id UUID REFERENCES folders(folder_id) ON DELETE SET NULL;
+ALTER TABLE notes ADD COLUMN folder_id UUID REFERENCES folders(folder_id) ON DELETE SET NULL;
+
+CREATE TABLE password_tags (
+    password_id UUID NOT NULL REFERENCES passwords(password_id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES tags(tag_id) ON DELETE CASCADE,
+    PRIMARY KEY (password_id, tag_id)
+);
+
+CREATE TABLE note_tags (
+    note_id UUID NOT NULL REFERENCES notes(note_id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES tags(tag_id) ON DELETE CASCADE,
+    PRIMARY KEY (note_id, tag_id)
+);
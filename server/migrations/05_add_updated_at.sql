@@ -0,0 +1,5 @@
+ALTER TABLE passwords ADD COLUMN updated_at BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE notes ADD COLUMN updated_at BIGINT NOT NULL DEFAULT 0;
+
+CREATE INDEX passwords_user_id_updated_at ON passwords (user_id, updated_at, password_id);
+CREATE INDEX notes_user_id_updated_at ON notes (user_id, updated_at, note_id);
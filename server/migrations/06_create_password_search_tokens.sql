@@ -0,0 +1,8 @@
+CREATE TABLE password_search_tokens (
+    password_id UUID NOT NULL REFERENCES passwords(password_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    token BYTEA NOT NULL,
+    PRIMARY KEY (password_id, token)
+);
+
+CREATE INDEX password_search_tokens_user_id_token ON password_search_tokens (user_id, token);
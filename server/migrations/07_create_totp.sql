@@ -0,0 +1,18 @@
+CREATE TABLE user_totp (
+    user_id UUID PRIMARY KEY REFERENCES users(user_id),
+    secret BYTEA NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    last_used_step BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE TABLE recovery_codes (
+    code_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    code VARCHAR(128) NOT NULL
+);
+
+CREATE TABLE login_challenges (
+    challenge_id BYTEA PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    expires_at BIGINT NOT NULL
+);
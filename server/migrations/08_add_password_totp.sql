@@ -0,0 +1,2 @@
+ALTER TABLE passwords ADD COLUMN totp BYTEA;
+ALTER TABLE passwords ADD COLUMN totp_nonce BYTEA;
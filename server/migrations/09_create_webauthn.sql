@@ -0,0 +1,16 @@
+CREATE TABLE webauthn_credentials (
+    credential_id BYTEA PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    name VARCHAR(64) NOT NULL,
+    public_key BYTEA NOT NULL,
+    sign_count BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE webauthn_challenges (
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    ceremony VARCHAR(16) NOT NULL,
+    challenge VARCHAR(64) NOT NULL,
+    expires_at BIGINT NOT NULL,
+    PRIMARY KEY (user_id, ceremony)
+);
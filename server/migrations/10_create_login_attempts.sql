@@ -0,0 +1,6 @@
+CREATE TABLE login_attempts (
+    attempt_key VARCHAR(128) PRIMARY KEY,
+    failures INTEGER NOT NULL,
+    locked_until BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
@@ -0,0 +1,12 @@
+ALTER TABLE users ALTER COLUMN password DROP NOT NULL;
+ALTER TABLE users ADD COLUMN srp_salt BYTEA NOT NULL DEFAULT ''::BYTEA;
+ALTER TABLE users ADD COLUMN verifier BYTEA NOT NULL DEFAULT ''::BYTEA;
+
+CREATE TABLE srp_logins (
+    login_id BYTEA PRIMARY KEY,
+    user_id UUID REFERENCES users(user_id),
+    username VARCHAR(16) NOT NULL,
+    client_proof BYTEA NOT NULL,
+    server_proof BYTEA NOT NULL,
+    expires_at BIGINT NOT NULL
+);
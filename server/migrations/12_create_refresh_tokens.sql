@@ -0,0 +1,14 @@
+-- existing sessions never expire, they are dropped instead of being turned into access tokens
+DELETE FROM sessions;
+ALTER TABLE sessions ADD COLUMN family_id UUID NOT NULL;
+ALTER TABLE sessions ADD COLUMN expires_at BIGINT NOT NULL;
+CREATE INDEX sessions_family_id ON sessions (family_id);
+
+CREATE TABLE refresh_tokens (
+    token_id BYTEA PRIMARY KEY,
+    family_id UUID NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    used BOOLEAN NOT NULL DEFAULT FALSE,
+    expires_at BIGINT NOT NULL
+);
+CREATE INDEX refresh_tokens_family_id ON refresh_tokens (family_id);
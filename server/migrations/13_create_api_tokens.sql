@@ -0,0 +1,12 @@
+CREATE TABLE api_tokens (
+    token_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    hashed_token BYTEA NOT NULL UNIQUE,
+    name VARCHAR(64) NOT NULL,
+    read_only BOOLEAN NOT NULL,
+    folder_ids UUID[] NOT NULL,
+    entry_ids UUID[] NOT NULL,
+    expires_at BIGINT,
+    last_used_at BIGINT,
+    created_at BIGINT NOT NULL
+);
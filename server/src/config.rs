@@ -0,0 +1,222 @@
+use crate::utils;
+use std::{env, str::FromStr, sync::OnceLock};
+
+const DEFAULT_PASSWORD_HISTORY_DEPTH: i64 = 10;
+const DEFAULT_NOTE_REVISION_POLICY: RevisionPolicy = RevisionPolicy::KeepLast(10);
+const DEFAULT_PAGE_LIMIT: i64 = 50;
+const DEFAULT_MAX_PAGE_LIMIT: i64 = 200;
+const DEFAULT_TOTP_ISSUER: &str = "vault";
+const DEFAULT_WEBAUTHN_RP_ID: &str = "localhost";
+const DEFAULT_WEBAUTHN_ORIGIN: &str = "http://localhost";
+const DEFAULT_LOGIN_USER_MAX_FAILURES: i32 = 5;
+const DEFAULT_LOGIN_IP_MAX_FAILURES: i32 = 20;
+const DEFAULT_LOGIN_START_IP_MAX_ATTEMPTS: i32 = 60;
+const DEFAULT_LOGIN_BACKOFF_SECONDS: i64 = 30;
+const DEFAULT_LOGIN_MAX_LOCKOUT_SECONDS: i64 = 60 * 60;
+const DEFAULT_ACCESS_TOKEN_LIFETIME_SECONDS: i64 = 15 * 60;
+const DEFAULT_REFRESH_TOKEN_LIFETIME_SECONDS: i64 = 30 * 24 * 60 * 60;
+const DEFAULT_LOG_FORMAT: LogFormat = LogFormat::Pretty;
+const DEFAULT_SHUTDOWN_DRAIN_SECONDS: u64 = 30;
+const DEFAULT_PURGE_INTERVAL_SECONDS: u64 = 5 * 60;
+const DEFAULT_TLS_RELOAD_SECONDS: u64 = 60;
+const DEFAULT_HSTS_MAX_AGE_SECONDS: u64 = 365 * 24 * 60 * 60;
+const DEFAULT_MAX_BODY_BYTES: usize = 256 * 1024;
+const DEFAULT_MAX_NOTE_BODY_BYTES: usize = 4 * 1024 * 1024;
+const DEFAULT_REQUEST_TIMEOUT_SECONDS: u64 = 30;
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 512;
+const DEFAULT_UNVERSIONED_API_SUNSET: &str = "Mon, 19 Apr 2027 00:00:00 GMT";
+
+static RANDOM_FAKE_SALT_SECRET: OnceLock<String> = OnceLock::new();
+
+#[derive(Clone)]
+pub struct Config {
+    pub password_history_depth: i64,
+    pub note_revision_policy: RevisionPolicy,
+    pub default_page_limit: i64,
+    pub max_page_limit: i64,
+    pub totp_issuer: String,
+    pub webauthn_rp_id: String,
+    pub webauthn_origin: String,
+    pub login_user_max_failures: i32,
+    pub login_ip_max_failures: i32,
+    pub login_start_ip_max_attempts: i32,
+    pub login_backoff_seconds: i64,
+    pub login_max_lockout_seconds: i64,
+    pub fake_salt_secret: String,
+    pub access_token_lifetime_seconds: i64,
+    pub refresh_token_lifetime_seconds: i64,
+    pub log_format: LogFormat,
+    pub shutdown_drain_seconds: u64,
+    pub purge_interval_seconds: u64,
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
+    pub tls_reload_seconds: u64,
+    pub hsts_max_age_seconds: u64,
+    pub max_body_bytes: usize,
+    pub max_note_body_bytes: usize,
+    pub request_timeout_seconds: u64,
+    pub max_concurrent_requests: usize,
+    pub cors_allowed_origins: Vec<String>,
+    pub unversioned_api_sunset: String,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Self {
+            password_history_depth: get_env_at_least(
+                "PASSWORD_HISTORY_DEPTH",
+                DEFAULT_PASSWORD_HISTORY_DEPTH,
+                0,
+            ),
+            note_revision_policy: get_env_or("NOTE_REVISION_POLICY", DEFAULT_NOTE_REVISION_POLICY),
+            default_page_limit: get_env_or("DEFAULT_PAGE_LIMIT", DEFAULT_PAGE_LIMIT),
+            max_page_limit: get_env_or("MAX_PAGE_LIMIT", DEFAULT_MAX_PAGE_LIMIT),
+            totp_issuer: get_env_or("TOTP_ISSUER", DEFAULT_TOTP_ISSUER.to_string()),
+            webauthn_rp_id: get_env_or("WEBAUTHN_RP_ID", DEFAULT_WEBAUTHN_RP_ID.to_string()),
+            webauthn_origin: get_env_or("WEBAUTHN_ORIGIN", DEFAULT_WEBAUTHN_ORIGIN.to_string()),
+            login_user_max_failures: get_env_or(
+                "LOGIN_USER_MAX_FAILURES",
+                DEFAULT_LOGIN_USER_MAX_FAILURES,
+            ),
+            login_ip_max_failures: get_env_or(
+                "LOGIN_IP_MAX_FAILURES",
+                DEFAULT_LOGIN_IP_MAX_FAILURES,
+            ),
+            login_start_ip_max_attempts: get_env_or(
+                "LOGIN_START_IP_MAX_ATTEMPTS",
+                DEFAULT_LOGIN_START_IP_MAX_ATTEMPTS,
+            ),
+            login_backoff_seconds: get_env_or(
+                "LOGIN_BACKOFF_SECONDS",
+                DEFAULT_LOGIN_BACKOFF_SECONDS,
+            ),
+            login_max_lockout_seconds: get_env_or(
+                "LOGIN_MAX_LOCKOUT_SECONDS",
+                DEFAULT_LOGIN_MAX_LOCKOUT_SECONDS,
+            ),
+            // without a configured secret fake salts change on every restart and differ between
+            // replicas, which gives unknown usernames away, so the server warns about it on start
+            fake_salt_secret: get_env_or(
+                "FAKE_SALT_SECRET",
+                RANDOM_FAKE_SALT_SECRET
+                    .get_or_init(utils::create_session_id)
+                    .clone(),
+            ),
+            access_token_lifetime_seconds: get_env_or(
+                "ACCESS_TOKEN_LIFETIME_SECONDS",
+                DEFAULT_ACCESS_TOKEN_LIFETIME_SECONDS,
+            ),
+            refresh_token_lifetime_seconds: get_env_or(
+                "REFRESH_TOKEN_LIFETIME_SECONDS",
+                DEFAULT_REFRESH_TOKEN_LIFETIME_SECONDS,
+            ),
+            log_format: get_env_or("LOG_FORMAT", DEFAULT_LOG_FORMAT),
+            shutdown_drain_seconds: get_env_or(
+                "SHUTDOWN_DRAIN_SECONDS",
+                DEFAULT_SHUTDOWN_DRAIN_SECONDS,
+            ),
+            purge_interval_seconds: get_env_or(
+                "PURGE_INTERVAL_SECONDS",
+                DEFAULT_PURGE_INTERVAL_SECONDS,
+            ),
+            tls_cert_path: env::var("TLS_CERT_PATH").ok(),
+            tls_key_path: env::var("TLS_KEY_PATH").ok(),
+            tls_reload_seconds: get_env_or("TLS_RELOAD_SECONDS", DEFAULT_TLS_RELOAD_SECONDS),
+            hsts_max_age_seconds: get_env_or("HSTS_MAX_AGE_SECONDS", DEFAULT_HSTS_MAX_AGE_SECONDS),
+            max_body_bytes: get_env_or("MAX_BODY_BYTES", DEFAULT_MAX_BODY_BYTES),
+            max_note_body_bytes: get_env_or("MAX_NOTE_BODY_BYTES", DEFAULT_MAX_NOTE_BODY_BYTES),
+            request_timeout_seconds: get_env_or(
+                "REQUEST_TIMEOUT_SECONDS",
+                DEFAULT_REQUEST_TIMEOUT_SECONDS,
+            ),
+            max_concurrent_requests: get_env_or(
+                "MAX_CONCURRENT_REQUESTS",
+                DEFAULT_MAX_CONCURRENT_REQUESTS,
+            ),
+            // browsers are refused unless their origin is listed, the desktop client needs no cors
+            cors_allowed_origins: env::var("CORS_ALLOWED_ORIGINS")
+                .map(|origins| {
+                    origins
+                        .split(',')
+                        .map(|origin| origin.trim().to_string())
+                        .filter(|origin| !origin.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            unversioned_api_sunset: get_env_or(
+                "UNVERSIONED_API_SUNSET",
+                DEFAULT_UNVERSIONED_API_SUNSET.to_string(),
+            ),
+        }
+    }
+
+    /// Lockout length after `failures` consecutive failures, doubling past `max_failures`.
+    pub fn login_lockout(&self, failures: i32, max_failures: i32) -> i64 {
+        if failures < max_failures {
+            return 0;
+        }
+        let doublings = (failures - max_failures).min(30) as u32;
+        self.login_backoff_seconds
+            .saturating_mul(1 << doublings)
+            .min(self.login_max_lockout_seconds)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum RevisionPolicy {
+    KeepLast(i64),
+    KeepDays(i64),
+}
+
+impl FromStr for RevisionPolicy {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (kind, amount) = value.split_once(':').ok_or(())?;
+        let amount: i64 = amount.parse().map_err(|_| ())?;
+        if amount < 0 {
+            return Err(());
+        }
+        match kind {
+            "last" => Ok(Self::KeepLast(amount)),
+            "days" => Ok(Self::KeepDays(amount)),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+fn get_env_or<T: FromStr>(key: &str, default: T) -> T {
+    match env::var(key) {
+        Ok(value) => match value.parse() {
+            Ok(value) => value,
+            Err(_) => panic!("Invalid {key} value"),
+        },
+        Err(_) => default,
+    }
+}
+
+fn get_env_at_least<T: FromStr + PartialOrd>(key: &str, default: T, min: T) -> T {
+    let value = get_env_or(key, default);
+    if value < min {
+        panic!("Invalid {key} value");
+    }
+    value
+}
@@ -1,12 +1,18 @@
-use crate::error;
+use crate::{
+    config::{Config, RevisionPolicy},
+    error, utils,
+};
+use serde::Deserialize;
 use sqlx::{postgres, types::Uuid, Row};
+use utoipa::ToSchema;
 
 type Result<T> = std::result::Result<T, error::Error>;
 
 pub struct DbUser {
     pub user_id: Uuid,
     pub username: String,
-    pub password: String,
+    pub srp_salt: Vec<u8>,
+    pub verifier: Vec<u8>,
     pub salt: [u8; 32],
     pub created_at: i64,
     pub connected_at: i64,
@@ -16,7 +22,8 @@ impl DbUser {
     fn new(
         user_id: Uuid,
         username: String,
-        password: String,
+        srp_salt: Vec<u8>,
+        verifier: Vec<u8>,
         salt: [u8; 32],
         created_at: i64,
         connected_at: i64,
@@ -24,7 +31,8 @@ impl DbUser {
         Self {
             user_id,
             username,
-            password,
+            srp_salt,
+            verifier,
             salt,
             created_at,
             connected_at,
@@ -32,6 +40,118 @@ impl DbUser {
     }
 }
 
+pub struct DbRefreshToken {
+    pub family_id: Uuid,
+    pub user_id: Uuid,
+    pub reused: bool,
+    pub expires_at: i64,
+}
+
+impl DbRefreshToken {
+    fn from_row(row: &postgres::PgRow) -> Self {
+        Self {
+            family_id: row.get("family_id"),
+            user_id: row.get("user_id"),
+            reused: row.get("reused"),
+            expires_at: row.get("expires_at"),
+        }
+    }
+}
+
+pub struct DbApiTokenScope {
+    pub read_only: bool,
+    pub folder_ids: Vec<Uuid>,
+    pub entry_ids: Vec<Uuid>,
+}
+
+pub struct DbApiToken {
+    pub token_id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub scope: DbApiTokenScope,
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub created_at: i64,
+}
+
+impl DbApiToken {
+    fn from_row(row: &postgres::PgRow) -> Self {
+        Self {
+            token_id: row.get("token_id"),
+            user_id: row.get("user_id"),
+            name: row.get("name"),
+            scope: DbApiTokenScope {
+                read_only: row.get("read_only"),
+                folder_ids: row.get("folder_ids"),
+                entry_ids: row.get("entry_ids"),
+            },
+            expires_at: row.get("expires_at"),
+            last_used_at: row.get("last_used_at"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+pub struct DbTotp {
+    pub secret: Vec<u8>,
+    pub enabled: bool,
+}
+
+impl DbTotp {
+    fn new(secret: Vec<u8>, enabled: bool) -> Self {
+        Self { secret, enabled }
+    }
+}
+
+pub struct DbRecoveryCode {
+    pub code_id: Uuid,
+    pub code: String,
+}
+
+impl DbRecoveryCode {
+    fn new(code_id: Uuid, code: String) -> Self {
+        Self { code_id, code }
+    }
+}
+
+pub struct DbSrpLogin {
+    pub user_id: Option<Uuid>,
+    pub username: String,
+    pub client_proof: Vec<u8>,
+    pub server_proof: Vec<u8>,
+}
+
+impl DbSrpLogin {
+    fn from_row(row: &postgres::PgRow) -> Self {
+        Self {
+            user_id: row.get("user_id"),
+            username: row.get("username"),
+            client_proof: row.get("client_proof"),
+            server_proof: row.get("server_proof"),
+        }
+    }
+}
+
+pub struct DbWebauthnCredential {
+    pub credential_id: Vec<u8>,
+    pub name: String,
+    pub public_key: Vec<u8>,
+    pub sign_count: i64,
+    pub created_at: i64,
+}
+
+impl DbWebauthnCredential {
+    fn from_row(row: &postgres::PgRow) -> Self {
+        Self {
+            credential_id: row.get("credential_id"),
+            name: row.get("name"),
+            public_key: row.get("public_key"),
+            sign_count: row.get("sign_count"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
 pub struct DbPassword {
     pub password_id: Uuid,
     pub user_id: Uuid,
@@ -39,24 +159,59 @@ pub struct DbPassword {
     pub username: String,
     pub password: Vec<u8>,
     pub nonce: [u8; 12],
+    pub totp: Option<Vec<u8>>,
+    pub totp_nonce: Option<[u8; 12]>,
+    pub folder_id: Option<Uuid>,
+    pub tag_ids: Vec<Uuid>,
+    pub updated_at: i64,
 }
 
 impl DbPassword {
+    fn from_row(row: &postgres::PgRow) -> Self {
+        Self {
+            password_id: row.get("password_id"),
+            user_id: row.get("user_id"),
+            domain_name: row.get("domain_name"),
+            username: row.get("username"),
+            password: row.get("password"),
+            nonce: row.get("nonce"),
+            totp: row.get("totp"),
+            totp_nonce: row.get("totp_nonce"),
+            folder_id: row.get("folder_id"),
+            tag_ids: row.get("tag_ids"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
+
+pub struct DbPasswordHistory {
+    pub password_id: Uuid,
+    pub version: i32,
+    pub domain_name: String,
+    pub username: String,
+    pub password: Vec<u8>,
+    pub nonce: [u8; 12],
+    pub replaced_at: i64,
+}
+
+impl DbPasswordHistory {
     fn new(
         password_id: Uuid,
-        user_id: Uuid,
+        version: i32,
         domain_name: String,
         username: String,
         password: Vec<u8>,
         nonce: [u8; 12],
+        replaced_at: i64,
     ) -> Self {
         Self {
             password_id,
-            user_id,
+            version,
             domain_name,
             username,
             password,
             nonce,
+            replaced_at,
         }
     }
 }
@@ -68,43 +223,235 @@ pub struct DbNote {
     pub title_nonce: [u8; 12],
     pub content: Vec<u8>,
     pub content_nonce: [u8; 12],
+    pub folder_id: Option<Uuid>,
+    pub tag_ids: Vec<Uuid>,
+    pub updated_at: i64,
 }
 
 impl DbNote {
+    fn from_row(row: &postgres::PgRow) -> Self {
+        Self {
+            note_id: row.get("note_id"),
+            user_id: row.get("user_id"),
+            title: row.get("title"),
+            title_nonce: row.get("title_nonce"),
+            content: row.get("content"),
+            content_nonce: row.get("content_nonce"),
+            folder_id: row.get("folder_id"),
+            tag_ids: row.get("tag_ids"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
+
+pub struct DbNoteRevision {
+    pub note_id: Uuid,
+    pub revision: i32,
+    pub title: Vec<u8>,
+    pub title_nonce: [u8; 12],
+    pub content: Vec<u8>,
+    pub content_nonce: [u8; 12],
+    pub replaced_at: i64,
+}
+
+impl DbNoteRevision {
     fn new(
         note_id: Uuid,
-        user_id: Uuid,
+        revision: i32,
         title: Vec<u8>,
         title_nonce: [u8; 12],
         content: Vec<u8>,
         content_nonce: [u8; 12],
+        replaced_at: i64,
     ) -> Self {
         Self {
             note_id,
-            user_id,
+            revision,
             title,
             title_nonce,
             content,
             content_nonce,
+            replaced_at,
+        }
+    }
+}
+
+pub struct DbFolder {
+    pub folder_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub name: Vec<u8>,
+    pub name_nonce: [u8; 12],
+}
+
+impl DbFolder {
+    fn new(folder_id: Uuid, parent_id: Option<Uuid>, name: Vec<u8>, name_nonce: [u8; 12]) -> Self {
+        Self {
+            folder_id,
+            parent_id,
+            name,
+            name_nonce,
+        }
+    }
+}
+
+pub struct DbTag {
+    pub tag_id: Uuid,
+    pub name: Vec<u8>,
+    pub name_nonce: [u8; 12],
+}
+
+impl DbTag {
+    fn new(tag_id: Uuid, name: Vec<u8>, name_nonce: [u8; 12]) -> Self {
+        Self {
+            tag_id,
+            name,
+            name_nonce,
         }
     }
 }
 
+#[derive(Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntriesOrder {
+    #[default]
+    Id,
+    UpdatedAt,
+}
+
+pub struct DbCursor {
+    pub updated_at: i64,
+    pub id: Uuid,
+}
+
+pub struct DbPage {
+    pub order: EntriesOrder,
+    pub limit: i64,
+    pub after: Option<DbCursor>,
+}
+
 pub trait Db {
-    async fn create_session(&self, hashed_session_id: &[u8; 32], user_id: &Uuid) -> Result<()>;
-    async fn validate_session(&self, hashed_session_id: &[u8; 32]) -> Result<Uuid>;
+    async fn create_session(
+        &self,
+        hashed_session_id: &[u8; 32],
+        user_id: &Uuid,
+        family_id: &Uuid,
+        expires_at: i64,
+    ) -> Result<()>;
+    async fn validate_session(&self, hashed_session_id: &[u8; 32], now: i64) -> Result<Uuid>;
+    async fn ping(&self) -> Result<()>;
+    async fn count_pending_migrations(&self) -> Result<usize>;
+    async fn purge_expired(&self, now: i64, attempts_before: i64) -> Result<u64>;
+    async fn count_active_sessions(&self, now: i64) -> Result<i64>;
     async fn delete_session(&self, hashed_session_id: &[u8; 32]) -> Result<()>;
+    async fn create_refresh_token(
+        &self,
+        hashed_token_id: &[u8; 32],
+        user_id: &Uuid,
+        family_id: &Uuid,
+        expires_at: i64,
+    ) -> Result<()>;
+    async fn use_refresh_token(&self, hashed_token_id: &[u8; 32]) -> Result<DbRefreshToken>;
+    async fn revoke_session_family(&self, family_id: &Uuid) -> Result<()>;
+    async fn create_api_token(
+        &self,
+        token_id: &Uuid,
+        user_id: &Uuid,
+        hashed_token: &[u8; 32],
+        name: &str,
+        scope: &DbApiTokenScope,
+        expires_at: Option<i64>,
+    ) -> Result<DbApiToken>;
+    async fn get_api_tokens(&self, user_id: &Uuid) -> Result<Vec<DbApiToken>>;
+    async fn delete_api_token(&self, user_id: &Uuid, token_id: &Uuid) -> Result<()>;
+    async fn validate_api_token(&self, hashed_token: &[u8; 32], now: i64) -> Result<DbApiToken>;
+    async fn get_entry_folder(&self, user_id: &Uuid, entry_id: &Uuid) -> Result<Option<Uuid>>;
     async fn create_user(
         &self,
         user_id: &Uuid,
         username: &String,
-        password: &String,
+        srp_salt: &[u8; 32],
+        verifier: &[u8],
         salt: &[u8; 32],
         created_at: i64,
-        connected_at: i64,
     ) -> Result<()>;
     async fn get_user(&self, username: &String) -> Result<DbUser>;
+    async fn get_user_by_id(&self, user_id: &Uuid) -> Result<DbUser>;
+    async fn get_legacy_password(&self, username: &str) -> Result<String>;
+    async fn upgrade_user(
+        &self,
+        username: &str,
+        srp_salt: &[u8; 32],
+        verifier: &[u8],
+    ) -> Result<()>;
     async fn update_user_timestamp(&self, user_id: &Uuid, connected_at: i64) -> Result<()>;
+    async fn set_totp_secret(&self, user_id: &Uuid, secret: &[u8]) -> Result<()>;
+    async fn get_totp(&self, user_id: &Uuid) -> Result<DbTotp>;
+    async fn enable_totp(
+        &self,
+        user_id: &Uuid,
+        step: i64,
+        recovery_codes: &[(Uuid, String)],
+    ) -> Result<()>;
+    async fn update_totp_step(&self, user_id: &Uuid, step: i64) -> Result<()>;
+    async fn delete_totp(&self, user_id: &Uuid) -> Result<()>;
+    async fn get_recovery_codes(&self, user_id: &Uuid) -> Result<Vec<DbRecoveryCode>>;
+    async fn delete_recovery_code(&self, user_id: &Uuid, code_id: &Uuid) -> Result<()>;
+    async fn create_login_challenge(
+        &self,
+        hashed_challenge_id: &[u8; 32],
+        user_id: &Uuid,
+        expires_at: i64,
+    ) -> Result<()>;
+    async fn take_login_challenge(&self, hashed_challenge_id: &[u8; 32], now: i64) -> Result<Uuid>;
+    async fn create_srp_login(
+        &self,
+        hashed_login_id: &[u8; 32],
+        user_id: Option<&Uuid>,
+        username: &str,
+        client_proof: &[u8],
+        server_proof: &[u8],
+        expires_at: i64,
+    ) -> Result<()>;
+    async fn take_srp_login(&self, hashed_login_id: &[u8; 32], now: i64) -> Result<DbSrpLogin>;
+    async fn get_login_challenge(&self, hashed_challenge_id: &[u8; 32], now: i64) -> Result<Uuid>;
+    async fn get_login_lockout(&self, attempt_key: &str) -> Result<i64>;
+    async fn record_login_failure(
+        &self,
+        attempt_key: &str,
+        now: i64,
+        reset_before: i64,
+    ) -> Result<i32>;
+    async fn set_login_lockout(&self, attempt_key: &str, locked_until: i64) -> Result<()>;
+    async fn clear_login_failures(&self, attempt_key: &str) -> Result<()>;
+    async fn set_webauthn_challenge(
+        &self,
+        user_id: &Uuid,
+        ceremony: &str,
+        challenge: &str,
+        expires_at: i64,
+    ) -> Result<()>;
+    async fn take_webauthn_challenge(
+        &self,
+        user_id: &Uuid,
+        ceremony: &str,
+        now: i64,
+    ) -> Result<String>;
+    async fn create_webauthn_credential(
+        &self,
+        user_id: &Uuid,
+        credential_id: &[u8],
+        name: &str,
+        public_key: &[u8],
+        sign_count: i64,
+    ) -> Result<DbWebauthnCredential>;
+    async fn get_webauthn_credentials(&self, user_id: &Uuid) -> Result<Vec<DbWebauthnCredential>>;
+    async fn update_webauthn_sign_count(
+        &self,
+        user_id: &Uuid,
+        credential_id: &[u8],
+        sign_count: i64,
+    ) -> Result<()>;
+    async fn delete_webauthn_credential(&self, user_id: &Uuid, credential_id: &[u8]) -> Result<()>;
     async fn create_password(
         &self,
         password_id: &Uuid,
@@ -115,7 +462,13 @@ pub trait Db {
         nonce: &[u8; 12],
     ) -> Result<DbPassword>;
     async fn get_password(&self, user_id: &Uuid, password_id: &Uuid) -> Result<DbPassword>;
-    async fn get_passwords(&self, user_id: &Uuid) -> Result<Vec<DbPassword>>;
+    async fn get_passwords(
+        &self,
+        user_id: &Uuid,
+        folder_id: Option<&Uuid>,
+        tag_id: Option<&Uuid>,
+        page: &DbPage,
+    ) -> Result<Vec<DbPassword>>;
     async fn delete_password(&self, user_id: &Uuid, password_id: &Uuid) -> Result<()>;
     async fn patch_password(
         &self,
@@ -126,6 +479,24 @@ pub trait Db {
         password: &Vec<u8>,
         nonce: &[u8; 12],
     ) -> Result<DbPassword>;
+    async fn set_password_search_tokens(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+        tokens: &[[u8; 32]],
+    ) -> Result<()>;
+    async fn search_passwords(&self, user_id: &Uuid, token: &[u8; 32]) -> Result<Vec<DbPassword>>;
+    async fn get_password_history(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+    ) -> Result<Vec<DbPasswordHistory>>;
+    async fn restore_password(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+        version: i32,
+    ) -> Result<DbPassword>;
     async fn create_note(
         &self,
         note_id: &Uuid,
@@ -136,7 +507,13 @@ pub trait Db {
         content_nonce: &[u8; 12],
     ) -> Result<DbNote>;
     async fn get_note(&self, user_id: &Uuid, note_id: &Uuid) -> Result<DbNote>;
-    async fn get_notes(&self, user_id: &Uuid) -> Result<Vec<DbNote>>;
+    async fn get_notes(
+        &self,
+        user_id: &Uuid,
+        folder_id: Option<&Uuid>,
+        tag_id: Option<&Uuid>,
+        page: &DbPage,
+    ) -> Result<Vec<DbNote>>;
     async fn delete_note(&self, user_id: &Uuid, note_id: &Uuid) -> Result<()>;
     async fn patch_note(
         &self,
@@ -147,57 +524,364 @@ pub trait Db {
         content: &Vec<u8>,
         content_nonce: &[u8; 12],
     ) -> Result<DbNote>;
+    async fn get_note_revisions(
+        &self,
+        user_id: &Uuid,
+        note_id: &Uuid,
+    ) -> Result<Vec<DbNoteRevision>>;
+    async fn restore_note(&self, user_id: &Uuid, note_id: &Uuid, revision: i32) -> Result<DbNote>;
+    async fn set_password_folder(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+        folder_id: Option<&Uuid>,
+    ) -> Result<DbPassword>;
+    async fn set_password_totp(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+        totp: Option<&[u8]>,
+        totp_nonce: Option<&[u8; 12]>,
+    ) -> Result<DbPassword>;
+    async fn add_password_tag(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+        tag_id: &Uuid,
+    ) -> Result<DbPassword>;
+    async fn remove_password_tag(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+        tag_id: &Uuid,
+    ) -> Result<DbPassword>;
+    async fn set_note_folder(
+        &self,
+        user_id: &Uuid,
+        note_id: &Uuid,
+        folder_id: Option<&Uuid>,
+    ) -> Result<DbNote>;
+    async fn add_note_tag(&self, user_id: &Uuid, note_id: &Uuid, tag_id: &Uuid) -> Result<DbNote>;
+    async fn remove_note_tag(
+        &self,
+        user_id: &Uuid,
+        note_id: &Uuid,
+        tag_id: &Uuid,
+    ) -> Result<DbNote>;
+    async fn create_folder(
+        &self,
+        folder_id: &Uuid,
+        user_id: &Uuid,
+        parent_id: Option<&Uuid>,
+        name: &[u8],
+        name_nonce: &[u8; 12],
+    ) -> Result<DbFolder>;
+    async fn get_folder(&self, user_id: &Uuid, folder_id: &Uuid) -> Result<DbFolder>;
+    async fn get_folders(&self, user_id: &Uuid) -> Result<Vec<DbFolder>>;
+    async fn delete_folder(&self, user_id: &Uuid, folder_id: &Uuid) -> Result<()>;
+    async fn patch_folder(
+        &self,
+        folder_id: &Uuid,
+        user_id: &Uuid,
+        parent_id: Option<&Uuid>,
+        name: &[u8],
+        name_nonce: &[u8; 12],
+    ) -> Result<DbFolder>;
+    async fn create_tag(
+        &self,
+        tag_id: &Uuid,
+        user_id: &Uuid,
+        name: &[u8],
+        name_nonce: &[u8; 12],
+    ) -> Result<DbTag>;
+    async fn get_tag(&self, user_id: &Uuid, tag_id: &Uuid) -> Result<DbTag>;
+    async fn get_tags(&self, user_id: &Uuid) -> Result<Vec<DbTag>>;
+    async fn delete_tag(&self, user_id: &Uuid, tag_id: &Uuid) -> Result<()>;
+    async fn patch_tag(
+        &self,
+        tag_id: &Uuid,
+        user_id: &Uuid,
+        name: &[u8],
+        name_nonce: &[u8; 12],
+    ) -> Result<DbTag>;
 }
 
 #[derive(Clone)]
 pub struct PostgreDb {
     pool: postgres::PgPool,
+    password_history_depth: i64,
+    note_revision_policy: RevisionPolicy,
 }
 
 impl PostgreDb {
-    pub async fn build(url: String) -> Result<Self> {
+    pub async fn build(url: String, config: &Config) -> Result<Self> {
         let pool = postgres::PgPool::connect(&url).await?;
         sqlx::migrate!("./migrations").run(&pool).await?;
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            password_history_depth: config.password_history_depth,
+            note_revision_policy: config.note_revision_policy,
+        })
+    }
+
+    /// Open connections in the pool and how many of them are idle.
+    pub fn pool_usage(&self) -> (u32, usize) {
+        (self.pool.size(), self.pool.num_idle())
+    }
+
+    /// Closes the pool shared by every clone, waiting for borrowed connections to come back.
+    pub async fn close(&self) {
+        self.pool.close().await
     }
 }
 
 impl Db for PostgreDb {
-    async fn create_session(&self, hashed_session_id: &[u8; 32], user_id: &Uuid) -> Result<()> {
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn create_session(
+        &self,
+        hashed_session_id: &[u8; 32],
+        user_id: &Uuid,
+        family_id: &Uuid,
+        expires_at: i64,
+    ) -> Result<()> {
+        let sql = "DELETE FROM sessions WHERE expires_at < $1;";
+        sqlx::query(sql)
+            .bind(utils::get_current_timestamp())
+            .execute(&self.pool)
+            .await?;
+
         let sql = "
-            INSERT INTO sessions (session_id, user_id) VALUES ($1, $2);
+            INSERT INTO sessions (session_id, user_id, family_id, expires_at)
+            VALUES ($1, $2, $3, $4);
         ";
         sqlx::query(sql)
             .bind(hashed_session_id)
             .bind(user_id)
+            .bind(family_id)
+            .bind(expires_at)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
-    async fn validate_session(&self, hashed_session_id: &[u8; 32]) -> Result<Uuid> {
-        let sql = "SELECT user_id FROM sessions WHERE sessions.session_id = $1;";
-        let query = sqlx::query_scalar(sql).bind(hashed_session_id);
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn validate_session(&self, hashed_session_id: &[u8; 32], now: i64) -> Result<Uuid> {
+        let sql = "
+            SELECT user_id FROM sessions
+            WHERE sessions.session_id = $1 AND sessions.expires_at >= $2;
+        ";
+        let query = sqlx::query_scalar(sql).bind(hashed_session_id).bind(now);
         let user_id = query.fetch_one(&self.pool).await?;
         Ok(user_id)
     }
 
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1;").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn count_pending_migrations(&self) -> Result<usize> {
+        let sql = "SELECT version FROM _sqlx_migrations WHERE success;";
+        let applied: Vec<i64> = sqlx::query_scalar(sql).fetch_all(&self.pool).await?;
+        let pending = sqlx::migrate!("./migrations")
+            .iter()
+            .filter(|migration| !applied.contains(&migration.version))
+            .count();
+        Ok(pending)
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn purge_expired(&self, now: i64, attempts_before: i64) -> Result<u64> {
+        let mut transaction = self.pool.begin().await?;
+        let mut purged = 0;
+        for sql in [
+            "DELETE FROM sessions WHERE expires_at < $1;",
+            "DELETE FROM refresh_tokens WHERE expires_at < $1;",
+            "DELETE FROM api_tokens WHERE expires_at < $1;",
+            "DELETE FROM srp_logins WHERE expires_at < $1;",
+            "DELETE FROM login_challenges WHERE expires_at < $1;",
+            "DELETE FROM webauthn_challenges WHERE expires_at < $1;",
+        ] {
+            let result = sqlx::query(sql)
+                .bind(now)
+                .execute(&mut *transaction)
+                .await?;
+            purged += result.rows_affected();
+        }
+        // failures are forgotten after the longest lockout anyway
+        let sql = "DELETE FROM login_attempts WHERE locked_until < $1 AND updated_at < $2;";
+        let result = sqlx::query(sql)
+            .bind(now)
+            .bind(attempts_before)
+            .execute(&mut *transaction)
+            .await?;
+        purged += result.rows_affected();
+        transaction.commit().await?;
+        Ok(purged)
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn count_active_sessions(&self, now: i64) -> Result<i64> {
+        let sql = "SELECT COUNT(*) FROM sessions WHERE sessions.expires_at >= $1;";
+        let count = sqlx::query_scalar(sql)
+            .bind(now)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
     async fn delete_session(&self, hashed_session_id: &[u8; 32]) -> Result<()> {
-        let sql = "DELETE FROM sessions WHERE sessions.session_id = $1;";
+        // logging out ends every token issued since the login, refresh tokens included
+        let sql = "SELECT family_id FROM sessions WHERE sessions.session_id = $1;";
+        let query = sqlx::query_scalar(sql).bind(hashed_session_id);
+        let family_id: Uuid = query.fetch_one(&self.pool).await?;
+        self.revoke_session_family(&family_id).await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn create_refresh_token(
+        &self,
+        hashed_token_id: &[u8; 32],
+        user_id: &Uuid,
+        family_id: &Uuid,
+        expires_at: i64,
+    ) -> Result<()> {
+        let sql = "
+            INSERT INTO refresh_tokens (token_id, family_id, user_id, used, expires_at)
+            VALUES ($1, $2, $3, FALSE, $4);
+        ";
         sqlx::query(sql)
-            .bind(hashed_session_id)
+            .bind(hashed_token_id)
+            .bind(family_id)
+            .bind(user_id)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn use_refresh_token(&self, hashed_token_id: &[u8; 32]) -> Result<DbRefreshToken> {
+        // the row lock makes a concurrent second use see the token as already used
+        let sql = "
+            WITH old AS (
+                SELECT token_id, used FROM refresh_tokens WHERE token_id = $1 FOR UPDATE
+            )
+            UPDATE refresh_tokens SET used = TRUE FROM old
+            WHERE refresh_tokens.token_id = old.token_id
+            RETURNING refresh_tokens.family_id, refresh_tokens.user_id,
+                old.used AS reused, refresh_tokens.expires_at;
+        ";
+        let query = sqlx::query(sql).bind(hashed_token_id);
+        let row = query.fetch_one(&self.pool).await?;
+        Ok(DbRefreshToken::from_row(&row))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn revoke_session_family(&self, family_id: &Uuid) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        let sql = "DELETE FROM refresh_tokens WHERE family_id = $1;";
+        sqlx::query(sql)
+            .bind(family_id)
+            .execute(&mut *transaction)
+            .await?;
+        let sql = "DELETE FROM sessions WHERE family_id = $1;";
+        sqlx::query(sql)
+            .bind(family_id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn create_api_token(
+        &self,
+        token_id: &Uuid,
+        user_id: &Uuid,
+        hashed_token: &[u8; 32],
+        name: &str,
+        scope: &DbApiTokenScope,
+        expires_at: Option<i64>,
+    ) -> Result<DbApiToken> {
+        let sql = "
+            INSERT INTO api_tokens (token_id, user_id, hashed_token, name, read_only,
+                folder_ids, entry_ids, expires_at, last_used_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NULL, $9)
+            RETURNING *;
+        ";
+        let query = sqlx::query(sql)
+            .bind(token_id)
+            .bind(user_id)
+            .bind(hashed_token)
+            .bind(name)
+            .bind(scope.read_only)
+            .bind(&scope.folder_ids)
+            .bind(&scope.entry_ids)
+            .bind(expires_at)
+            .bind(utils::get_current_timestamp());
+        let row = query.fetch_one(&self.pool).await?;
+        Ok(DbApiToken::from_row(&row))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_api_tokens(&self, user_id: &Uuid) -> Result<Vec<DbApiToken>> {
+        let sql = "SELECT * FROM api_tokens WHERE user_id = $1 ORDER BY created_at;";
+        let query = sqlx::query(sql).bind(user_id);
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(DbApiToken::from_row).collect())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn delete_api_token(&self, user_id: &Uuid, token_id: &Uuid) -> Result<()> {
+        let sql = "DELETE FROM api_tokens WHERE user_id = $1 AND token_id = $2;";
+        let result = sqlx::query(sql)
+            .bind(user_id)
+            .bind(token_id)
             .execute(&self.pool)
             .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(error::Error::NotFound);
+        }
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn validate_api_token(&self, hashed_token: &[u8; 32], now: i64) -> Result<DbApiToken> {
+        let sql = "
+            UPDATE api_tokens SET last_used_at = $2
+            WHERE hashed_token = $1 AND (expires_at IS NULL OR expires_at >= $2)
+            RETURNING *;
+        ";
+        let query = sqlx::query(sql).bind(hashed_token).bind(now);
+        let row = query.fetch_one(&self.pool).await?;
+        Ok(DbApiToken::from_row(&row))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_entry_folder(&self, user_id: &Uuid, entry_id: &Uuid) -> Result<Option<Uuid>> {
+        let sql = "
+            SELECT folder_id FROM passwords WHERE user_id = $1 AND password_id = $2
+            UNION ALL
+            SELECT folder_id FROM notes WHERE user_id = $1 AND note_id = $2;
+        ";
+        let query = sqlx::query_scalar(sql).bind(user_id).bind(entry_id);
+        let folder_id = query.fetch_one(&self.pool).await?;
+        Ok(folder_id)
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
     async fn create_user(
         &self,
         user_id: &Uuid,
         username: &String,
-        password: &String,
+        srp_salt: &[u8; 32],
+        verifier: &[u8],
         salt: &[u8; 32],
         created_at: i64,
-        connected_at: i64,
     ) -> Result<()> {
         let sql = "SELECT EXISTS (SELECT 1 FROM users WHERE users.username = $1);";
         let exists: bool = sqlx::query_scalar(sql)
@@ -206,25 +890,26 @@ impl Db for PostgreDb {
             .await?;
 
         if exists {
-            return Err(error::Error::DatabaseError);
+            return Err(error::Error::Conflict("Username already taken".to_string()));
         }
 
         let sql = "
-            INSERT INTO users (user_id, username, password, salt, created_at, connected_at)
-            VALUES ($1, $2, $3, $4, $5, $6);
+            INSERT INTO users (user_id, username, srp_salt, verifier, salt, created_at, connected_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $6);
         ";
         sqlx::query(sql)
             .bind(user_id)
             .bind(username.to_lowercase())
-            .bind(password)
+            .bind(srp_salt)
+            .bind(verifier)
             .bind(salt)
             .bind(created_at)
-            .bind(connected_at)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
     async fn get_user(&self, username: &String) -> Result<DbUser> {
         let sql = "SELECT * FROM users WHERE users.username = $1;";
         let query = sqlx::query(sql).bind(username.to_lowercase());
@@ -232,116 +917,1000 @@ impl Db for PostgreDb {
         Ok(DbUser::new(
             row.get("user_id"),
             row.get("username"),
-            row.get("password"),
+            row.get("srp_salt"),
+            row.get("verifier"),
             row.get("salt"),
             row.get("created_at"),
             row.get("connected_at"),
         ))
     }
 
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_user_by_id(&self, user_id: &Uuid) -> Result<DbUser> {
+        let sql = "SELECT * FROM users WHERE users.user_id = $1;";
+        let query = sqlx::query(sql).bind(user_id);
+        let row = query.fetch_one(&self.pool).await?;
+        Ok(DbUser::new(
+            row.get("user_id"),
+            row.get("username"),
+            row.get("srp_salt"),
+            row.get("verifier"),
+            row.get("salt"),
+            row.get("created_at"),
+            row.get("connected_at"),
+        ))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_legacy_password(&self, username: &str) -> Result<String> {
+        let sql = "
+            SELECT users.password FROM users
+            WHERE users.username = $1 AND users.verifier = ''::BYTEA AND users.password IS NOT NULL;
+        ";
+        let query = sqlx::query_scalar(sql).bind(username.to_lowercase());
+        Ok(query.fetch_one(&self.pool).await?)
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn upgrade_user(
+        &self,
+        username: &str,
+        srp_salt: &[u8; 32],
+        verifier: &[u8],
+    ) -> Result<()> {
+        // the old hash goes away with the upgrade, so it can only happen once
+        let sql = "
+            UPDATE users SET srp_salt = $2, verifier = $3, password = NULL
+            WHERE users.username = $1 AND users.verifier = ''::BYTEA;
+        ";
+        let result = sqlx::query(sql)
+            .bind(username.to_lowercase())
+            .bind(srp_salt)
+            .bind(verifier)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(error::Error::NotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
     async fn update_user_timestamp(&self, user_id: &Uuid, connected_at: i64) -> Result<()> {
-        let sql = "UPDATE users SET users.connected_at = $1 WHERE users.user_id = $2;";
+        let sql = "UPDATE users SET connected_at = $1 WHERE users.user_id = $2;";
+        sqlx::query(sql)
+            .bind(connected_at)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn set_totp_secret(&self, user_id: &Uuid, secret: &[u8]) -> Result<()> {
+        let sql = "
+            INSERT INTO user_totp (user_id, secret) VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET secret = $2, last_used_step = 0
+            WHERE user_totp.enabled = FALSE;
+        ";
+        let result = sqlx::query(sql)
+            .bind(user_id)
+            .bind(secret)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(error::Error::Conflict("TOTP already enabled".to_string()));
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_totp(&self, user_id: &Uuid) -> Result<DbTotp> {
+        let sql = "SELECT * FROM user_totp WHERE user_totp.user_id = $1;";
+        let query = sqlx::query(sql).bind(user_id);
+        let row = query.fetch_one(&self.pool).await?;
+        Ok(DbTotp::new(row.get("secret"), row.get("enabled")))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn enable_totp(
+        &self,
+        user_id: &Uuid,
+        step: i64,
+        recovery_codes: &[(Uuid, String)],
+    ) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+
+        let sql = "
+            UPDATE user_totp SET enabled = TRUE, last_used_step = $1
+            WHERE user_id = $2 AND enabled = FALSE;
+        ";
+        let result = sqlx::query(sql)
+            .bind(step)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(error::Error::Conflict("TOTP already enabled".to_string()));
+        }
+
+        let sql = "DELETE FROM recovery_codes WHERE user_id = $1;";
+        sqlx::query(sql)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let sql = "INSERT INTO recovery_codes (code_id, user_id, code) VALUES ($1, $2, $3);";
+        for (code_id, code) in recovery_codes {
+            sqlx::query(sql)
+                .bind(code_id)
+                .bind(user_id)
+                .bind(code)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn update_totp_step(&self, user_id: &Uuid, step: i64) -> Result<()> {
+        let sql = "
+            UPDATE user_totp SET last_used_step = $1
+            WHERE user_id = $2 AND last_used_step < $1;
+        ";
+        let result = sqlx::query(sql)
+            .bind(step)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(error::Error::Conflict("TOTP code already used".to_string()));
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn delete_totp(&self, user_id: &Uuid) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+
+        let sql = "DELETE FROM recovery_codes WHERE user_id = $1;";
+        sqlx::query(sql)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let sql = "DELETE FROM user_totp WHERE user_id = $1;";
+        sqlx::query(sql)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_recovery_codes(&self, user_id: &Uuid) -> Result<Vec<DbRecoveryCode>> {
+        let sql = "SELECT * FROM recovery_codes WHERE recovery_codes.user_id = $1;";
+        let query = sqlx::query(sql).bind(user_id);
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| DbRecoveryCode::new(row.get("code_id"), row.get("code")))
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn delete_recovery_code(&self, user_id: &Uuid, code_id: &Uuid) -> Result<()> {
+        let sql = "DELETE FROM recovery_codes WHERE user_id = $1 AND code_id = $2;";
+        let result = sqlx::query(sql)
+            .bind(user_id)
+            .bind(code_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(error::Error::NotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn create_login_challenge(
+        &self,
+        hashed_challenge_id: &[u8; 32],
+        user_id: &Uuid,
+        expires_at: i64,
+    ) -> Result<()> {
+        let sql = "
+            INSERT INTO login_challenges (challenge_id, user_id, expires_at)
+            VALUES ($1, $2, $3);
+        ";
+        sqlx::query(sql)
+            .bind(hashed_challenge_id)
+            .bind(user_id)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn take_login_challenge(&self, hashed_challenge_id: &[u8; 32], now: i64) -> Result<Uuid> {
+        let sql = "
+            DELETE FROM login_challenges WHERE challenge_id = $1
+            RETURNING user_id, expires_at;
+        ";
+        let query = sqlx::query(sql).bind(hashed_challenge_id);
+        let row = query.fetch_one(&self.pool).await?;
+
+        let expires_at: i64 = row.get("expires_at");
+        if expires_at < now {
+            return Err(error::Error::NotFound);
+        }
+        Ok(row.get("user_id"))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn create_srp_login(
+        &self,
+        hashed_login_id: &[u8; 32],
+        user_id: Option<&Uuid>,
+        username: &str,
+        client_proof: &[u8],
+        server_proof: &[u8],
+        expires_at: i64,
+    ) -> Result<()> {
+        // anyone can start a login, so expired ones are swept here instead of piling up
+        let sql = "DELETE FROM srp_logins WHERE expires_at < $1;";
+        sqlx::query(sql)
+            .bind(utils::get_current_timestamp())
+            .execute(&self.pool)
+            .await?;
+
+        let sql = "
+            INSERT INTO srp_logins (login_id, user_id, username, client_proof, server_proof, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6);
+        ";
+        sqlx::query(sql)
+            .bind(hashed_login_id)
+            .bind(user_id)
+            .bind(username.to_lowercase())
+            .bind(client_proof)
+            .bind(server_proof)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn take_srp_login(&self, hashed_login_id: &[u8; 32], now: i64) -> Result<DbSrpLogin> {
+        let sql = "
+            DELETE FROM srp_logins WHERE login_id = $1
+            RETURNING user_id, username, client_proof, server_proof, expires_at;
+        ";
+        let query = sqlx::query(sql).bind(hashed_login_id);
+        let row = query.fetch_one(&self.pool).await?;
+
+        let expires_at: i64 = row.get("expires_at");
+        if expires_at < now {
+            return Err(error::Error::NotFound);
+        }
+        Ok(DbSrpLogin::from_row(&row))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_login_challenge(&self, hashed_challenge_id: &[u8; 32], now: i64) -> Result<Uuid> {
+        let sql = "
+            SELECT user_id FROM login_challenges
+            WHERE challenge_id = $1 AND expires_at >= $2;
+        ";
+        let query = sqlx::query_scalar(sql).bind(hashed_challenge_id).bind(now);
+        let user_id = query.fetch_one(&self.pool).await?;
+        Ok(user_id)
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_login_lockout(&self, attempt_key: &str) -> Result<i64> {
+        let sql = "SELECT locked_until FROM login_attempts WHERE attempt_key = $1;";
+        let query = sqlx::query_scalar(sql).bind(attempt_key);
+        let locked_until = query.fetch_optional(&self.pool).await?;
+        Ok(locked_until.unwrap_or(0))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn record_login_failure(
+        &self,
+        attempt_key: &str,
+        now: i64,
+        reset_before: i64,
+    ) -> Result<i32> {
+        // failures older than the lockout window start a new count
+        let sql = "
+            INSERT INTO login_attempts (attempt_key, failures, locked_until, updated_at)
+            VALUES ($1, 1, 0, $2)
+            ON CONFLICT (attempt_key) DO UPDATE SET
+            failures = CASE
+                WHEN login_attempts.updated_at < $3 THEN 1
+                ELSE login_attempts.failures + 1
+            END,
+            updated_at = $2
+            RETURNING failures;
+        ";
+        let query = sqlx::query_scalar(sql)
+            .bind(attempt_key)
+            .bind(now)
+            .bind(reset_before);
+        let failures = query.fetch_one(&self.pool).await?;
+        Ok(failures)
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn set_login_lockout(&self, attempt_key: &str, locked_until: i64) -> Result<()> {
+        let sql = "UPDATE login_attempts SET locked_until = $1 WHERE attempt_key = $2;";
+        sqlx::query(sql)
+            .bind(locked_until)
+            .bind(attempt_key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn clear_login_failures(&self, attempt_key: &str) -> Result<()> {
+        let sql = "DELETE FROM login_attempts WHERE attempt_key = $1;";
+        sqlx::query(sql)
+            .bind(attempt_key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn set_webauthn_challenge(
+        &self,
+        user_id: &Uuid,
+        ceremony: &str,
+        challenge: &str,
+        expires_at: i64,
+    ) -> Result<()> {
+        let sql = "
+            INSERT INTO webauthn_challenges (user_id, ceremony, challenge, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id, ceremony) DO UPDATE SET challenge = $3, expires_at = $4;
+        ";
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(ceremony)
+            .bind(challenge)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn take_webauthn_challenge(
+        &self,
+        user_id: &Uuid,
+        ceremony: &str,
+        now: i64,
+    ) -> Result<String> {
+        let sql = "
+            DELETE FROM webauthn_challenges WHERE user_id = $1 AND ceremony = $2
+            RETURNING challenge, expires_at;
+        ";
+        let query = sqlx::query(sql).bind(user_id).bind(ceremony);
+        let row = query.fetch_one(&self.pool).await?;
+
+        let expires_at: i64 = row.get("expires_at");
+        if expires_at < now {
+            return Err(error::Error::NotFound);
+        }
+        Ok(row.get("challenge"))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn create_webauthn_credential(
+        &self,
+        user_id: &Uuid,
+        credential_id: &[u8],
+        name: &str,
+        public_key: &[u8],
+        sign_count: i64,
+    ) -> Result<DbWebauthnCredential> {
+        let sql = "
+            INSERT INTO webauthn_credentials
+            (credential_id, user_id, name, public_key, sign_count, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *;
+        ";
+        let query = sqlx::query(sql)
+            .bind(credential_id)
+            .bind(user_id)
+            .bind(name)
+            .bind(public_key)
+            .bind(sign_count)
+            .bind(utils::get_current_timestamp());
+        let row = query.fetch_one(&self.pool).await?;
+        Ok(DbWebauthnCredential::from_row(&row))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_webauthn_credentials(&self, user_id: &Uuid) -> Result<Vec<DbWebauthnCredential>> {
+        let sql = "
+            SELECT * FROM webauthn_credentials WHERE user_id = $1
+            ORDER BY created_at;
+        ";
+        let query = sqlx::query(sql).bind(user_id);
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(DbWebauthnCredential::from_row).collect())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn update_webauthn_sign_count(
+        &self,
+        user_id: &Uuid,
+        credential_id: &[u8],
+        sign_count: i64,
+    ) -> Result<()> {
+        // a concurrent login with the same counter value must not succeed twice
+        let sql = "
+            UPDATE webauthn_credentials SET sign_count = $1
+            WHERE user_id = $2 AND credential_id = $3 AND (sign_count < $1 OR $1 = 0);
+        ";
+        let result = sqlx::query(sql)
+            .bind(sign_count)
+            .bind(user_id)
+            .bind(credential_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(error::Error::Conflict(
+                "Credential already used".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn delete_webauthn_credential(&self, user_id: &Uuid, credential_id: &[u8]) -> Result<()> {
+        let sql = "DELETE FROM webauthn_credentials WHERE user_id = $1 AND credential_id = $2;";
+        let result = sqlx::query(sql)
+            .bind(user_id)
+            .bind(credential_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(error::Error::NotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn create_password(
+        &self,
+        password_id: &Uuid,
+        user_id: &Uuid,
+        domain_name: &String,
+        username: &String,
+        password: &Vec<u8>,
+        nonce: &[u8; 12],
+    ) -> Result<DbPassword> {
+        let sql = "
+            INSERT INTO passwords
+            (password_id, user_id, domain_name, username, password, nonce, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7);
+        ";
+        sqlx::query(sql)
+            .bind(password_id)
+            .bind(user_id)
+            .bind(domain_name)
+            .bind(username)
+            .bind(password)
+            .bind(nonce)
+            .bind(utils::get_current_timestamp())
+            .execute(&self.pool)
+            .await?;
+        self.get_password(&user_id, &password_id).await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_password(&self, user_id: &Uuid, password_id: &Uuid) -> Result<DbPassword> {
+        let sql = "
+            SELECT passwords.*, ARRAY(
+                SELECT tag_id FROM password_tags
+                WHERE password_tags.password_id = passwords.password_id
+            ) AS tag_ids FROM passwords WHERE
+            passwords.user_id = $1 AND passwords.password_id = $2;
+        ";
+        let query = sqlx::query(sql).bind(user_id).bind(password_id);
+        let row = query.fetch_one(&self.pool).await?;
+        Ok(DbPassword::from_row(&row))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_passwords(
+        &self,
+        user_id: &Uuid,
+        folder_id: Option<&Uuid>,
+        tag_id: Option<&Uuid>,
+        page: &DbPage,
+    ) -> Result<Vec<DbPassword>> {
+        let (after, order) = match page.order {
+            EntriesOrder::Id => ("passwords.password_id > $5", "passwords.password_id"),
+            EntriesOrder::UpdatedAt => (
+                "(passwords.updated_at, passwords.password_id) < ($4, $5)",
+                "passwords.updated_at DESC, passwords.password_id DESC",
+            ),
+        };
+        let sql = format!(
+            "
+            SELECT passwords.*, ARRAY(
+                SELECT tag_id FROM password_tags
+                WHERE password_tags.password_id = passwords.password_id
+            ) AS tag_ids FROM passwords WHERE passwords.user_id = $1
+            AND ($2::UUID IS NULL OR passwords.folder_id = $2)
+            AND ($3::UUID IS NULL OR EXISTS (
+                SELECT 1 FROM password_tags WHERE
+                password_tags.password_id = passwords.password_id AND password_tags.tag_id = $3
+            ))
+            AND ($5::UUID IS NULL OR {after})
+            ORDER BY {order} LIMIT $6;
+            "
+        );
+        let query = sqlx::query(&sql)
+            .bind(user_id)
+            .bind(folder_id)
+            .bind(tag_id)
+            .bind(page.after.as_ref().map(|after| after.updated_at))
+            .bind(page.after.as_ref().map(|after| after.id))
+            // One extra row tells whether there is a next page.
+            .bind(page.limit + 1);
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(DbPassword::from_row).collect())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn delete_password(&self, user_id: &Uuid, password_id: &Uuid) -> Result<()> {
+        let sql = "
+            DELETE from passwords WHERE passwords.user_id = $1 AND passwords.password_id = $2;
+        ";
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(password_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn patch_password(
+        &self,
+        password_id: &Uuid,
+        user_id: &Uuid,
+        domain_name: &String,
+        username: &String,
+        password: &Vec<u8>,
+        nonce: &[u8; 12],
+    ) -> Result<DbPassword> {
+        let mut transaction = self.pool.begin().await?;
+        let updated_at = utils::get_current_timestamp();
+
+        let sql = "
+            INSERT INTO password_history
+            (password_id, version, user_id, domain_name, username, password, nonce, replaced_at)
+            SELECT password_id,
+            COALESCE((SELECT MAX(version) FROM password_history WHERE password_id = $1), 0) + 1,
+            user_id, domain_name, username, password, nonce, $3
+            FROM passwords WHERE password_id = $1 AND user_id = $2;
+        ";
+        sqlx::query(sql)
+            .bind(password_id)
+            .bind(user_id)
+            .bind(updated_at)
+            .execute(&mut *transaction)
+            .await?;
+
+        let sql = "
+            UPDATE passwords SET
+            domain_name = $1, username = $2, password = $3, nonce = $4, updated_at = $5
+            WHERE password_id = $6 AND user_id = $7;
+        ";
+        sqlx::query(sql)
+            .bind(domain_name)
+            .bind(username)
+            .bind(password)
+            .bind(nonce)
+            .bind(updated_at)
+            .bind(password_id)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let sql = "
+            DELETE FROM password_history WHERE password_id = $1 AND version <=
+            (SELECT MAX(version) FROM password_history WHERE password_id = $1) - $2;
+        ";
+        sqlx::query(sql)
+            .bind(password_id)
+            .bind(self.password_history_depth)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+        self.get_password(user_id, password_id).await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn set_password_search_tokens(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+        tokens: &[[u8; 32]],
+    ) -> Result<()> {
+        self.get_password(user_id, password_id).await?;
+        let mut transaction = self.pool.begin().await?;
+
+        let sql = "DELETE FROM password_search_tokens WHERE password_id = $1;";
+        sqlx::query(sql)
+            .bind(password_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let sql = "
+            INSERT INTO password_search_tokens (password_id, user_id, token)
+            SELECT $1, $2, token FROM UNNEST($3::BYTEA[]) AS token
+            ON CONFLICT DO NOTHING;
+        ";
+        sqlx::query(sql)
+            .bind(password_id)
+            .bind(user_id)
+            .bind(
+                tokens
+                    .iter()
+                    .map(|token| token.to_vec())
+                    .collect::<Vec<_>>(),
+            )
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn search_passwords(&self, user_id: &Uuid, token: &[u8; 32]) -> Result<Vec<DbPassword>> {
+        let sql = "
+            SELECT passwords.*, ARRAY(
+                SELECT tag_id FROM password_tags
+                WHERE password_tags.password_id = passwords.password_id
+            ) AS tag_ids FROM passwords
+            JOIN password_search_tokens
+            ON password_search_tokens.password_id = passwords.password_id
+            WHERE password_search_tokens.user_id = $1 AND password_search_tokens.token = $2;
+        ";
+        let query = sqlx::query(sql).bind(user_id).bind(token);
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(DbPassword::from_row).collect())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_password_history(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+    ) -> Result<Vec<DbPasswordHistory>> {
+        self.get_password(user_id, password_id).await?;
+        let sql = "
+            SELECT * FROM password_history WHERE
+            password_history.user_id = $1 AND password_history.password_id = $2
+            ORDER BY password_history.version DESC;
+        ";
+        let query = sqlx::query(sql).bind(user_id).bind(password_id);
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                DbPasswordHistory::new(
+                    row.get("password_id"),
+                    row.get("version"),
+                    row.get("domain_name"),
+                    row.get("username"),
+                    row.get("password"),
+                    row.get("nonce"),
+                    row.get("replaced_at"),
+                )
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn restore_password(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+        version: i32,
+    ) -> Result<DbPassword> {
+        let sql = "
+            SELECT * FROM password_history WHERE
+            password_history.user_id = $1 AND password_history.password_id = $2
+            AND password_history.version = $3;
+        ";
+        let query = sqlx::query(sql)
+            .bind(user_id)
+            .bind(password_id)
+            .bind(version);
+        let row = query.fetch_one(&self.pool).await?;
+        self.patch_password(
+            password_id,
+            user_id,
+            &row.get("domain_name"),
+            &row.get("username"),
+            &row.get("password"),
+            &row.get("nonce"),
+        )
+        .await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn create_note(
+        &self,
+        note_id: &Uuid,
+        user_id: &Uuid,
+        title: &Vec<u8>,
+        title_nonce: &[u8; 12],
+        content: &Vec<u8>,
+        content_nonce: &[u8; 12],
+    ) -> Result<DbNote> {
+        let sql = "
+            INSERT INTO notes
+            (note_id, user_id, title, title_nonce, content, content_nonce, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7);
+        ";
         sqlx::query(sql)
-            .bind(connected_at)
+            .bind(note_id)
             .bind(user_id)
+            .bind(title)
+            .bind(title_nonce)
+            .bind(content)
+            .bind(content_nonce)
+            .bind(utils::get_current_timestamp())
             .execute(&self.pool)
             .await?;
-        Ok(())
+        self.get_note(user_id, note_id).await
     }
 
-    async fn create_password(
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_note(&self, user_id: &Uuid, note_id: &Uuid) -> Result<DbNote> {
+        let sql = "
+            SELECT notes.*, ARRAY(
+                SELECT tag_id FROM note_tags WHERE note_tags.note_id = notes.note_id
+            ) AS tag_ids FROM notes WHERE
+            notes.user_id = $1 AND notes.note_id = $2;
+        ";
+        let query = sqlx::query(sql).bind(user_id).bind(note_id);
+        let row = query.fetch_one(&self.pool).await?;
+        Ok(DbNote::from_row(&row))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_notes(
         &self,
-        password_id: &Uuid,
         user_id: &Uuid,
-        domain_name: &String,
-        username: &String,
-        password: &Vec<u8>,
-        nonce: &[u8; 12],
-    ) -> Result<DbPassword> {
+        folder_id: Option<&Uuid>,
+        tag_id: Option<&Uuid>,
+        page: &DbPage,
+    ) -> Result<Vec<DbNote>> {
+        let (after, order) = match page.order {
+            EntriesOrder::Id => ("notes.note_id > $5", "notes.note_id"),
+            EntriesOrder::UpdatedAt => (
+                "(notes.updated_at, notes.note_id) < ($4, $5)",
+                "notes.updated_at DESC, notes.note_id DESC",
+            ),
+        };
+        let sql = format!(
+            "
+            SELECT notes.*, ARRAY(
+                SELECT tag_id FROM note_tags WHERE note_tags.note_id = notes.note_id
+            ) AS tag_ids FROM notes WHERE notes.user_id = $1
+            AND ($2::UUID IS NULL OR notes.folder_id = $2)
+            AND ($3::UUID IS NULL OR EXISTS (
+                SELECT 1 FROM note_tags WHERE
+                note_tags.note_id = notes.note_id AND note_tags.tag_id = $3
+            ))
+            AND ($5::UUID IS NULL OR {after})
+            ORDER BY {order} LIMIT $6;
+            "
+        );
+        let query = sqlx::query(&sql)
+            .bind(user_id)
+            .bind(folder_id)
+            .bind(tag_id)
+            .bind(page.after.as_ref().map(|after| after.updated_at))
+            .bind(page.after.as_ref().map(|after| after.id))
+            // One extra row tells whether there is a next page.
+            .bind(page.limit + 1);
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(DbNote::from_row).collect())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn delete_note(&self, user_id: &Uuid, note_id: &Uuid) -> Result<()> {
         let sql = "
-            INSERT INTO passwords
-            (password_id, user_id, domain_name, username, password, nonce)
-            VALUES ($1, $2, $3, $4, $5, $6);
+            DELETE from notes WHERE notes.user_id = $1 AND notes.note_id = $2;
         ";
         sqlx::query(sql)
-            .bind(password_id)
             .bind(user_id)
-            .bind(domain_name)
-            .bind(username)
-            .bind(password)
-            .bind(nonce)
+            .bind(note_id)
             .execute(&self.pool)
             .await?;
-        self.get_password(&user_id, &password_id).await
+        Ok(())
     }
 
-    async fn get_password(&self, user_id: &Uuid, password_id: &Uuid) -> Result<DbPassword> {
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn patch_note(
+        &self,
+        note_id: &Uuid,
+        user_id: &Uuid,
+        title: &Vec<u8>,
+        title_nonce: &[u8; 12],
+        content: &Vec<u8>,
+        content_nonce: &[u8; 12],
+    ) -> Result<DbNote> {
+        let mut transaction = self.pool.begin().await?;
+        let updated_at = utils::get_current_timestamp();
+
         let sql = "
-            SELECT * FROM passwords WHERE
-            passwords.user_id = $1 AND passwords.password_id = $2;
+            INSERT INTO note_revisions
+            (note_id, revision, user_id, title, title_nonce, content, content_nonce, replaced_at)
+            SELECT note_id,
+            COALESCE((SELECT MAX(revision) FROM note_revisions WHERE note_id = $1), 0) + 1,
+            user_id, title, title_nonce, content, content_nonce, $3
+            FROM notes WHERE note_id = $1 AND user_id = $2;
         ";
-        let query = sqlx::query(sql).bind(user_id).bind(password_id);
-        let row = query.fetch_one(&self.pool).await?;
-        Ok(DbPassword::new(
-            row.get("password_id"),
-            row.get("user_id"),
-            row.get("domain_name"),
-            row.get("username"),
-            row.get("password"),
-            row.get("nonce"),
-        ))
+        sqlx::query(sql)
+            .bind(note_id)
+            .bind(user_id)
+            .bind(updated_at)
+            .execute(&mut *transaction)
+            .await?;
+
+        let sql = "
+            UPDATE notes SET
+            title = $1, title_nonce = $2, content = $3, content_nonce = $4, updated_at = $5
+            WHERE note_id = $6 AND user_id = $7;
+        ";
+        sqlx::query(sql)
+            .bind(title)
+            .bind(title_nonce)
+            .bind(content)
+            .bind(content_nonce)
+            .bind(updated_at)
+            .bind(note_id)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let query = match self.note_revision_policy {
+            RevisionPolicy::KeepLast(count) => {
+                let sql = "
+                    DELETE FROM note_revisions WHERE note_id = $1 AND revision <=
+                    (SELECT MAX(revision) FROM note_revisions WHERE note_id = $1) - $2;
+                ";
+                sqlx::query(sql).bind(note_id).bind(count)
+            }
+            RevisionPolicy::KeepDays(days) => {
+                let sql = "DELETE FROM note_revisions WHERE note_id = $1 AND replaced_at < $2;";
+                sqlx::query(sql)
+                    .bind(note_id)
+                    .bind(updated_at - days * 24 * 60 * 60)
+            }
+        };
+        query.execute(&mut *transaction).await?;
+
+        transaction.commit().await?;
+        self.get_note(user_id, note_id).await
     }
 
-    async fn get_passwords(&self, user_id: &Uuid) -> Result<Vec<DbPassword>> {
-        let sql = "SELECT * FROM passwords WHERE passwords.user_id = $1;";
-        let query = sqlx::query(sql).bind(user_id);
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_note_revisions(
+        &self,
+        user_id: &Uuid,
+        note_id: &Uuid,
+    ) -> Result<Vec<DbNoteRevision>> {
+        self.get_note(user_id, note_id).await?;
+        let sql = "
+            SELECT * FROM note_revisions WHERE
+            note_revisions.user_id = $1 AND note_revisions.note_id = $2
+            ORDER BY note_revisions.revision DESC;
+        ";
+        let query = sqlx::query(sql).bind(user_id).bind(note_id);
         let rows = query.fetch_all(&self.pool).await?;
         Ok(rows
             .into_iter()
             .map(|row| {
-                DbPassword::new(
-                    row.get("password_id"),
-                    row.get("user_id"),
-                    row.get("domain_name"),
-                    row.get("username"),
-                    row.get("password"),
-                    row.get("nonce"),
+                DbNoteRevision::new(
+                    row.get("note_id"),
+                    row.get("revision"),
+                    row.get("title"),
+                    row.get("title_nonce"),
+                    row.get("content"),
+                    row.get("content_nonce"),
+                    row.get("replaced_at"),
                 )
             })
             .collect())
     }
 
-    async fn delete_password(&self, user_id: &Uuid, password_id: &Uuid) -> Result<()> {
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn restore_note(&self, user_id: &Uuid, note_id: &Uuid, revision: i32) -> Result<DbNote> {
         let sql = "
-            DELETE from passwords WHERE passwords.user_id = $1 AND passwords.password_id = $2;
+            SELECT * FROM note_revisions WHERE
+            note_revisions.user_id = $1 AND note_revisions.note_id = $2
+            AND note_revisions.revision = $3;
+        ";
+        let query = sqlx::query(sql).bind(user_id).bind(note_id).bind(revision);
+        let row = query.fetch_one(&self.pool).await?;
+        self.patch_note(
+            note_id,
+            user_id,
+            &row.get("title"),
+            &row.get("title_nonce"),
+            &row.get("content"),
+            &row.get("content_nonce"),
+        )
+        .await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn set_password_folder(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+        folder_id: Option<&Uuid>,
+    ) -> Result<DbPassword> {
+        if let Some(folder_id) = folder_id {
+            self.get_folder(user_id, folder_id).await?;
+        }
+        let sql = "
+            UPDATE passwords SET folder_id = $1
+            WHERE password_id = $2 AND user_id = $3;
         ";
         sqlx::query(sql)
-            .bind(user_id)
+            .bind(folder_id)
             .bind(password_id)
+            .bind(user_id)
             .execute(&self.pool)
             .await?;
-        Ok(())
+        self.get_password(user_id, password_id).await
     }
 
-    async fn patch_password(
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn set_password_totp(
         &self,
-        password_id: &Uuid,
         user_id: &Uuid,
-        domain_name: &String,
-        username: &String,
-        password: &Vec<u8>,
-        nonce: &[u8; 12],
+        password_id: &Uuid,
+        totp: Option<&[u8]>,
+        totp_nonce: Option<&[u8; 12]>,
     ) -> Result<DbPassword> {
         let sql = "
-            UPDATE passwords SET
-            domain_name = $1, username = $2, password = $3, nonce = $4
-            WHERE password_id = $5 AND user_id = $6;
+            UPDATE passwords SET totp = $1, totp_nonce = $2, updated_at = $3
+            WHERE password_id = $4 AND user_id = $5;
         ";
         sqlx::query(sql)
-            .bind(domain_name)
-            .bind(username)
-            .bind(password)
-            .bind(nonce)
+            .bind(totp)
+            .bind(totp_nonce)
+            .bind(utils::get_current_timestamp())
             .bind(password_id)
             .bind(user_id)
             .execute(&self.pool)
@@ -349,103 +1918,290 @@ impl Db for PostgreDb {
         self.get_password(user_id, password_id).await
     }
 
-    async fn create_note(
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn add_password_tag(
         &self,
-        note_id: &Uuid,
         user_id: &Uuid,
-        title: &Vec<u8>,
-        title_nonce: &[u8; 12],
-        content: &Vec<u8>,
-        content_nonce: &[u8; 12],
-    ) -> Result<DbNote> {
+        password_id: &Uuid,
+        tag_id: &Uuid,
+    ) -> Result<DbPassword> {
+        self.get_tag(user_id, tag_id).await?;
+        self.get_password(user_id, password_id).await?;
         let sql = "
-            INSERT INTO notes
-            (note_id, user_id, title, title_nonce, content, content_nonce)
-            VALUES ($1, $2, $3, $4, $5, $6);
+            INSERT INTO password_tags (password_id, tag_id) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING;
         ";
         sqlx::query(sql)
+            .bind(password_id)
+            .bind(tag_id)
+            .execute(&self.pool)
+            .await?;
+        self.get_password(user_id, password_id).await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn remove_password_tag(
+        &self,
+        user_id: &Uuid,
+        password_id: &Uuid,
+        tag_id: &Uuid,
+    ) -> Result<DbPassword> {
+        self.get_password(user_id, password_id).await?;
+        let sql = "DELETE FROM password_tags WHERE password_id = $1 AND tag_id = $2;";
+        sqlx::query(sql)
+            .bind(password_id)
+            .bind(tag_id)
+            .execute(&self.pool)
+            .await?;
+        self.get_password(user_id, password_id).await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn set_note_folder(
+        &self,
+        user_id: &Uuid,
+        note_id: &Uuid,
+        folder_id: Option<&Uuid>,
+    ) -> Result<DbNote> {
+        if let Some(folder_id) = folder_id {
+            self.get_folder(user_id, folder_id).await?;
+        }
+        let sql = "UPDATE notes SET folder_id = $1 WHERE note_id = $2 AND user_id = $3;";
+        sqlx::query(sql)
+            .bind(folder_id)
             .bind(note_id)
             .bind(user_id)
-            .bind(title)
-            .bind(title_nonce)
-            .bind(content)
-            .bind(content_nonce)
             .execute(&self.pool)
             .await?;
         self.get_note(user_id, note_id).await
     }
 
-    async fn get_note(&self, user_id: &Uuid, note_id: &Uuid) -> Result<DbNote> {
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn add_note_tag(&self, user_id: &Uuid, note_id: &Uuid, tag_id: &Uuid) -> Result<DbNote> {
+        self.get_tag(user_id, tag_id).await?;
+        self.get_note(user_id, note_id).await?;
         let sql = "
-            SELECT * FROM notes WHERE
-            notes.user_id = $1 AND notes.note_id = $2;
+            INSERT INTO note_tags (note_id, tag_id) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING;
         ";
-        let query = sqlx::query(sql).bind(user_id).bind(note_id);
+        sqlx::query(sql)
+            .bind(note_id)
+            .bind(tag_id)
+            .execute(&self.pool)
+            .await?;
+        self.get_note(user_id, note_id).await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn remove_note_tag(
+        &self,
+        user_id: &Uuid,
+        note_id: &Uuid,
+        tag_id: &Uuid,
+    ) -> Result<DbNote> {
+        self.get_note(user_id, note_id).await?;
+        let sql = "DELETE FROM note_tags WHERE note_id = $1 AND tag_id = $2;";
+        sqlx::query(sql)
+            .bind(note_id)
+            .bind(tag_id)
+            .execute(&self.pool)
+            .await?;
+        self.get_note(user_id, note_id).await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn create_folder(
+        &self,
+        folder_id: &Uuid,
+        user_id: &Uuid,
+        parent_id: Option<&Uuid>,
+        name: &[u8],
+        name_nonce: &[u8; 12],
+    ) -> Result<DbFolder> {
+        if let Some(parent_id) = parent_id {
+            self.get_folder(user_id, parent_id).await?;
+        }
+        let sql = "
+            INSERT INTO folders (folder_id, user_id, parent_id, name, name_nonce)
+            VALUES ($1, $2, $3, $4, $5);
+        ";
+        sqlx::query(sql)
+            .bind(folder_id)
+            .bind(user_id)
+            .bind(parent_id)
+            .bind(name)
+            .bind(name_nonce)
+            .execute(&self.pool)
+            .await?;
+        self.get_folder(user_id, folder_id).await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_folder(&self, user_id: &Uuid, folder_id: &Uuid) -> Result<DbFolder> {
+        let sql = "
+            SELECT * FROM folders WHERE
+            folders.user_id = $1 AND folders.folder_id = $2;
+        ";
+        let query = sqlx::query(sql).bind(user_id).bind(folder_id);
         let row = query.fetch_one(&self.pool).await?;
-        Ok(DbNote::new(
-            row.get("note_id"),
-            row.get("user_id"),
-            row.get("title"),
-            row.get("title_nonce"),
-            row.get("content"),
-            row.get("content_nonce"),
+        Ok(DbFolder::new(
+            row.get("folder_id"),
+            row.get("parent_id"),
+            row.get("name"),
+            row.get("name_nonce"),
         ))
     }
 
-    async fn get_notes(&self, user_id: &Uuid) -> Result<Vec<DbNote>> {
-        let sql = "SELECT * FROM notes WHERE notes.user_id = $1;";
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_folders(&self, user_id: &Uuid) -> Result<Vec<DbFolder>> {
+        let sql = "SELECT * FROM folders WHERE folders.user_id = $1;";
         let query = sqlx::query(sql).bind(user_id);
         let rows = query.fetch_all(&self.pool).await?;
         Ok(rows
             .into_iter()
             .map(|row| {
-                DbNote::new(
-                    row.get("note_id"),
-                    row.get("user_id"),
-                    row.get("title"),
-                    row.get("title_nonce"),
-                    row.get("content"),
-                    row.get("content_nonce"),
+                DbFolder::new(
+                    row.get("folder_id"),
+                    row.get("parent_id"),
+                    row.get("name"),
+                    row.get("name_nonce"),
                 )
             })
             .collect())
     }
 
-    async fn delete_note(&self, user_id: &Uuid, note_id: &Uuid) -> Result<()> {
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn delete_folder(&self, user_id: &Uuid, folder_id: &Uuid) -> Result<()> {
+        let sql = "DELETE FROM folders WHERE folders.user_id = $1 AND folders.folder_id = $2;";
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(folder_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn patch_folder(
+        &self,
+        folder_id: &Uuid,
+        user_id: &Uuid,
+        parent_id: Option<&Uuid>,
+        name: &[u8],
+        name_nonce: &[u8; 12],
+    ) -> Result<DbFolder> {
+        if let Some(parent_id) = parent_id {
+            self.get_folder(user_id, parent_id).await?;
+
+            let sql = "
+                WITH RECURSIVE ancestors AS (
+                    SELECT folder_id, parent_id FROM folders WHERE folder_id = $1
+                    UNION
+                    SELECT folders.folder_id, folders.parent_id FROM folders
+                    JOIN ancestors ON folders.folder_id = ancestors.parent_id
+                )
+                SELECT EXISTS (SELECT 1 FROM ancestors WHERE ancestors.folder_id = $2);
+            ";
+            let creates_cycle: bool = sqlx::query_scalar(sql)
+                .bind(parent_id)
+                .bind(folder_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+            if creates_cycle {
+                return Err(error::Error::Validation(
+                    "Folder cannot be moved into itself".to_string(),
+                ));
+            }
+        }
+
         let sql = "
-            DELETE from notes WHERE notes.user_id = $1 AND notes.note_id = $2;
+            UPDATE folders SET parent_id = $1, name = $2, name_nonce = $3
+            WHERE folder_id = $4 AND user_id = $5;
         ";
         sqlx::query(sql)
+            .bind(parent_id)
+            .bind(name)
+            .bind(name_nonce)
+            .bind(folder_id)
             .bind(user_id)
-            .bind(note_id)
+            .execute(&self.pool)
+            .await?;
+        self.get_folder(user_id, folder_id).await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn create_tag(
+        &self,
+        tag_id: &Uuid,
+        user_id: &Uuid,
+        name: &[u8],
+        name_nonce: &[u8; 12],
+    ) -> Result<DbTag> {
+        let sql = "INSERT INTO tags (tag_id, user_id, name, name_nonce) VALUES ($1, $2, $3, $4);";
+        sqlx::query(sql)
+            .bind(tag_id)
+            .bind(user_id)
+            .bind(name)
+            .bind(name_nonce)
+            .execute(&self.pool)
+            .await?;
+        self.get_tag(user_id, tag_id).await
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_tag(&self, user_id: &Uuid, tag_id: &Uuid) -> Result<DbTag> {
+        let sql = "SELECT * FROM tags WHERE tags.user_id = $1 AND tags.tag_id = $2;";
+        let query = sqlx::query(sql).bind(user_id).bind(tag_id);
+        let row = query.fetch_one(&self.pool).await?;
+        Ok(DbTag::new(
+            row.get("tag_id"),
+            row.get("name"),
+            row.get("name_nonce"),
+        ))
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn get_tags(&self, user_id: &Uuid) -> Result<Vec<DbTag>> {
+        let sql = "SELECT * FROM tags WHERE tags.user_id = $1;";
+        let query = sqlx::query(sql).bind(user_id);
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| DbTag::new(row.get("tag_id"), row.get("name"), row.get("name_nonce")))
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn delete_tag(&self, user_id: &Uuid, tag_id: &Uuid) -> Result<()> {
+        let sql = "DELETE FROM tags WHERE tags.user_id = $1 AND tags.tag_id = $2;";
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(tag_id)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
-    async fn patch_note(
+    #[tracing::instrument(skip_all, err(Debug, level = "debug"))]
+    async fn patch_tag(
         &self,
-        note_id: &Uuid,
+        tag_id: &Uuid,
         user_id: &Uuid,
-        title: &Vec<u8>,
-        title_nonce: &[u8; 12],
-        content: &Vec<u8>,
-        content_nonce: &[u8; 12],
-    ) -> Result<DbNote> {
+        name: &[u8],
+        name_nonce: &[u8; 12],
+    ) -> Result<DbTag> {
         let sql = "
-            UPDATE notes SET
-            title = $1, title_nonce = $2, content = $3, content_nonce = $4
-            WHERE note_id = $5 AND user_id = $6;
+            UPDATE tags SET name = $1, name_nonce = $2
+            WHERE tag_id = $3 AND user_id = $4;
         ";
         sqlx::query(sql)
-            .bind(title)
-            .bind(title_nonce)
-            .bind(content)
-            .bind(content_nonce)
-            .bind(note_id)
+            .bind(name)
+            .bind(name_nonce)
+            .bind(tag_id)
             .bind(user_id)
             .execute(&self.pool)
             .await?;
-        self.get_note(user_id, note_id).await
+        self.get_tag(user_id, tag_id).await
     }
 }
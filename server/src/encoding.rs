@@ -0,0 +1,182 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+
+pub const CONTENT_TYPE_CBOR: &str = "application/cbor";
+
+/// How responses of the current request are written.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    /// JSON with bytes as arrays of numbers, kept for clients predating base64.
+    LegacyJson,
+    /// JSON with bytes as base64 strings.
+    Json,
+    /// CBOR with bytes as byte strings.
+    Cbor,
+}
+
+tokio::task_local! {
+    static FORMAT: Format;
+}
+
+/// Format negotiated for the request being handled, legacy JSON outside of a request.
+pub fn current() -> Format {
+    FORMAT
+        .try_with(|format| *format)
+        .unwrap_or(Format::LegacyJson)
+}
+
+/// Picks the response format from the `Accept` header, see [`Format`].
+pub async fn negotiate(request: Request, next: Next) -> Response {
+    let format = format_from_headers(request.headers());
+    FORMAT.scope(format, next.run(request)).await
+}
+
+fn format_from_headers(headers: &HeaderMap) -> Format {
+    let accept = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for media_range in accept {
+        let mut parts = media_range.split(';').map(str::trim);
+        match parts.next() {
+            Some(CONTENT_TYPE_CBOR) => return Format::Cbor,
+            Some("application/json")
+                if parts.any(|parameter| parameter.replace(' ', "") == "encoding=base64") =>
+            {
+                return Format::Json
+            }
+            _ => {}
+        }
+    }
+    Format::LegacyJson
+}
+
+/// Serde helpers for byte fields, used with `#[serde(with = "encoding::bytes")]`.
+///
+/// Bytes are read from base64 strings as well as from arrays of numbers, and written in the
+/// negotiated format.
+pub mod bytes {
+    use super::{current, Format};
+    use data_encoding::BASE64;
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+    use std::fmt;
+
+    pub trait Bytes: Sized {
+        fn serialize_bytes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+        fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+    }
+
+    pub fn serialize<T: Bytes, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        value.serialize_bytes(serializer)
+    }
+
+    pub fn deserialize<'de, T: Bytes, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        T::deserialize_bytes(deserializer)
+    }
+
+    struct Wrapper<T>(T);
+
+    impl<T: Bytes> Serialize for Wrapper<&T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize_bytes(serializer)
+        }
+    }
+
+    impl<'de, T: Bytes> Deserialize<'de> for Wrapper<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            T::deserialize_bytes(deserializer).map(Wrapper)
+        }
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a base64 string or an array of bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            BASE64
+                .decode(value.as_bytes())
+                .map_err(|_| E::custom("invalid base64"))
+        }
+
+        fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+            Ok(value.to_vec())
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    impl Bytes for Vec<u8> {
+        fn serialize_bytes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if !serializer.is_human_readable() {
+                return serializer.serialize_bytes(self);
+            }
+            match current() {
+                Format::LegacyJson => serializer.collect_seq(self),
+                Format::Json | Format::Cbor => serializer.serialize_str(&BASE64.encode(self)),
+            }
+        }
+
+        fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(BytesVisitor)
+        }
+    }
+
+    impl<const N: usize> Bytes for [u8; N] {
+        fn serialize_bytes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.to_vec().serialize_bytes(serializer)
+        }
+
+        fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let bytes = Vec::<u8>::deserialize_bytes(deserializer)?;
+            let length = bytes.len();
+            bytes
+                .try_into()
+                .map_err(|_| de::Error::invalid_length(length, &format!("{N} bytes").as_str()))
+        }
+    }
+
+    impl<T: Bytes> Bytes for Option<T> {
+        fn serialize_bytes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                Some(value) => serializer.serialize_some(&Wrapper(value)),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Option::<Wrapper<T>>::deserialize(deserializer).map(|value| value.map(|value| value.0))
+        }
+    }
+
+    impl<const N: usize> Bytes for Vec<[u8; N]> {
+        fn serialize_bytes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter().map(Wrapper))
+        }
+
+        fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let values = Vec::<Wrapper<[u8; N]>>::deserialize(deserializer)?;
+            Ok(values.into_iter().map(|value| value.0).collect())
+        }
+    }
+}
@@ -1,16 +1,52 @@
+use crate::model::ErrorResponse;
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
 use sqlx;
 
+// postgres error codes, see https://www.postgresql.org/docs/current/errcodes-appendix.html
+const UNIQUE_VIOLATION: &str = "23505";
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+const CHECK_VIOLATION: &str = "23514";
+
 #[derive(Debug)]
 pub enum Error {
-    DatabaseError,
+    NotFound,
+    Conflict(String),
+    Validation(String),
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    TooManyRequests(String, i64),
+    PayloadTooLarge,
+    RequestTimeout,
+    ShuttingDown,
+    Internal,
     SqlxError(sqlx::Error),
     SqlxMigrateError(sqlx::migrate::MigrateError),
     HeadersError,
+    DecodingError,
 }
 
 impl From<sqlx::Error> for Error {
     fn from(value: sqlx::Error) -> Self {
-        Error::SqlxError(value)
+        if let sqlx::Error::RowNotFound = value {
+            return Error::NotFound;
+        }
+        let code = value
+            .as_database_error()
+            .and_then(|err| err.code())
+            .map(|code| code.to_string());
+        match code.as_deref() {
+            Some(UNIQUE_VIOLATION) => Error::Conflict("Resource already exists".to_string()),
+            Some(FOREIGN_KEY_VIOLATION) => {
+                Error::Validation("Referenced resource does not exist".to_string())
+            }
+            Some(CHECK_VIOLATION) => Error::Validation("Invalid value".to_string()),
+            _ => Error::SqlxError(value),
+        }
     }
 }
 
@@ -19,3 +55,95 @@ impl From<sqlx::migrate::MigrateError> for Error {
         Error::SqlxMigrateError(value)
     }
 }
+
+impl From<JsonRejection> for Error {
+    fn from(value: JsonRejection) -> Self {
+        // well formed json with missing or mistyped fields is a validation error
+        match value {
+            JsonRejection::JsonDataError(_) => Error::Validation(value.body_text()),
+            _ if value.status() == StatusCode::PAYLOAD_TOO_LARGE => Error::PayloadTooLarge,
+            _ => Error::BadRequest(value.body_text()),
+        }
+    }
+}
+
+impl From<PathRejection> for Error {
+    fn from(value: PathRejection) -> Self {
+        Error::BadRequest(value.body_text())
+    }
+}
+
+impl From<QueryRejection> for Error {
+    fn from(value: QueryRejection) -> Self {
+        Error::BadRequest(value.body_text())
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        match &self {
+            Error::Internal | Error::SqlxError(_) | Error::SqlxMigrateError(_) => {
+                tracing::error!(error = ?self, "request failed")
+            }
+            _ => tracing::debug!(error = ?self, "request rejected"),
+        }
+        let (status_code, code, message) = match self {
+            Error::NotFound => (
+                StatusCode::NOT_FOUND,
+                "not_found",
+                "Resource not found".to_string(),
+            ),
+            Error::Conflict(message) => (StatusCode::CONFLICT, "conflict", message),
+            Error::Validation(message) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation_failed",
+                message,
+            ),
+            Error::BadRequest(message) => (StatusCode::BAD_REQUEST, "bad_request", message),
+            Error::Unauthorized(message) => (StatusCode::UNAUTHORIZED, "unauthorized", message),
+            Error::Forbidden(message) => (StatusCode::FORBIDDEN, "forbidden", message),
+            Error::TooManyRequests(message, retry_after) => {
+                return ErrorResponse::too_many_requests(message, retry_after)
+            }
+            Error::PayloadTooLarge => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "payload_too_large",
+                "Request body too large".to_string(),
+            ),
+            Error::RequestTimeout => (
+                StatusCode::REQUEST_TIMEOUT,
+                "request_timeout",
+                "Request timed out".to_string(),
+            ),
+            Error::ShuttingDown => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "shutting_down",
+                "Server is shutting down".to_string(),
+            ),
+            Error::HeadersError => (
+                StatusCode::BAD_REQUEST,
+                "bad_request",
+                "Invalid headers".to_string(),
+            ),
+            Error::DecodingError => (
+                StatusCode::BAD_REQUEST,
+                "bad_request",
+                "Invalid encoding".to_string(),
+            ),
+            // clients should retry later, unlike for errors caused by the request itself
+            Error::SqlxError(
+                sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_),
+            ) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "database_unavailable",
+                "Database unavailable".to_string(),
+            ),
+            Error::Internal | Error::SqlxError(_) | Error::SqlxMigrateError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+                "Internal server error".to_string(),
+            ),
+        };
+        ErrorResponse::new(status_code, code, message).into_response()
+    }
+}
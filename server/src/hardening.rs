@@ -0,0 +1,92 @@
+use crate::config::Config;
+use crate::error::Error;
+use crate::telemetry::REQUEST_ID_HEADER;
+use crate::versioning::{DEPRECATION_HEADER, SUNSET_HEADER};
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::DefaultBodyLimit,
+    http::{header, HeaderName, HeaderValue, Method},
+    BoxError, Router,
+};
+use std::time::Duration;
+use tower::{limit::GlobalConcurrencyLimitLayer, timeout::TimeoutLayer, ServiceBuilder};
+use tower_http::{
+    cors::{AllowOrigin, CorsLayer},
+    set_header::SetResponseHeaderLayer,
+};
+
+const CORS_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+// the api only serves json, nothing of it should be rendered, framed or cached
+const SECURITY_HEADERS: [(HeaderName, &str); 5] = [
+    (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+    (header::X_FRAME_OPTIONS, "DENY"),
+    (header::REFERRER_POLICY, "no-referrer"),
+    (
+        header::CONTENT_SECURITY_POLICY,
+        "default-src 'none'; frame-ancestors 'none'",
+    ),
+    (header::CACHE_CONTROL, "no-store"),
+];
+
+/// Limits body size, duration and concurrency of requests, and adds cors and security headers.
+pub fn layer(router: Router, config: &Config) -> Router {
+    let mut router = router
+        .layer(DefaultBodyLimit::max(config.max_body_bytes))
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(handle_error))
+                .layer(TimeoutLayer::new(Duration::from_secs(
+                    config.request_timeout_seconds,
+                )))
+                // shared by every route, unlike the per service limit
+                .layer(GlobalConcurrencyLimitLayer::new(
+                    config.max_concurrent_requests,
+                )),
+        );
+    for (name, value) in SECURITY_HEADERS {
+        router = router.layer(SetResponseHeaderLayer::if_not_present(
+            name,
+            HeaderValue::from_static(value),
+        ));
+    }
+    router.layer(cors(config))
+}
+
+fn cors(config: &Config) -> CorsLayer {
+    let origins = config
+        .cors_allowed_origins
+        .iter()
+        .map(|origin| HeaderValue::from_str(origin).expect("Invalid CORS_ALLOWED_ORIGINS value"))
+        .collect::<Vec<HeaderValue>>();
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            REQUEST_ID_HEADER,
+        ])
+        .expose_headers([
+            REQUEST_ID_HEADER,
+            header::RETRY_AFTER,
+            DEPRECATION_HEADER,
+            SUNSET_HEADER,
+            header::LINK,
+        ])
+        .max_age(CORS_MAX_AGE)
+}
+
+async fn handle_error(err: BoxError) -> Error {
+    if err.is::<tower::timeout::error::Elapsed>() {
+        Error::RequestTimeout
+    } else {
+        tracing::error!(error = ?err, "middleware failed");
+        Error::Internal
+    }
+}
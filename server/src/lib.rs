@@ -1,7 +1,15 @@
+mod config;
 mod database;
+mod encoding;
 mod error;
+mod hardening;
 mod middleware;
 mod model;
+mod monitoring;
 pub mod routers;
 mod routes;
+pub mod shutdown;
+pub mod telemetry;
+pub mod tls;
 mod utils;
+mod versioning;
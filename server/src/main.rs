@@ -1,19 +1,82 @@
-use axum::Router;
-use server::routers;
+use server::{routers, shutdown, telemetry, tls};
+use std::{path::PathBuf, time::Duration};
 use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() {
+    telemetry::init();
+    if std::env::var("FAKE_SALT_SECRET").is_err() {
+        tracing::warn!(
+            "FAKE_SALT_SECRET not set, unknown usernames can be told apart across restarts and replicas"
+        );
+    }
+
     let address = std::env::var("SERVER_URL").expect("SERVER_URL not set");
     let listener = TcpListener::bind(address).await.unwrap();
 
-    let users_router = routers::users_router().await;
-    let passwords_router = routers::passwords_router().await;
-    let notes_router = routers::notes_router().await;
-    let app = Router::new()
-        .nest("/users", users_router)
-        .nest("/passwords", passwords_router)
-        .nest("/notes", notes_router);
+    let app_state = routers::AppState::build().await;
+    let drain_timeout = Duration::from_secs(app_state.config.shutdown_drain_seconds);
+    let shutdown_token = CancellationToken::new();
+    tokio::spawn(shutdown::cancel_on_signal(shutdown_token.clone()));
+    let purge = shutdown::spawn_purge(app_state.clone(), shutdown_token.clone());
+
+    let mut app = routers::api(app_state.clone());
+    let (tls_config, reload) = match (
+        &app_state.config.tls_cert_path,
+        &app_state.config.tls_key_path,
+    ) {
+        (Some(cert_path), Some(key_path)) => {
+            let (cert_path, key_path) = (PathBuf::from(cert_path), PathBuf::from(key_path));
+            let tls_config = tls::load(&cert_path, &key_path)
+                .await
+                .expect("Invalid TLS certificate or key");
+            let reload = tls::spawn_reload(
+                tls_config.clone(),
+                cert_path,
+                key_path,
+                Duration::from_secs(app_state.config.tls_reload_seconds),
+                shutdown_token.clone(),
+            );
+            app = tls::layer(app, app_state.config.hsts_max_age_seconds);
+            (Some(tls_config), Some(reload))
+        }
+        (None, None) => (None, None),
+        _ => panic!("TLS_CERT_PATH and TLS_KEY_PATH must be set together"),
+    };
+
+    // metrics stay off the public listener, they are only served when an admin address is set
+    let admin = match std::env::var("ADMIN_URL") {
+        Ok(admin_address) => {
+            let admin_listener = TcpListener::bind(admin_address).await.unwrap();
+            tracing::info!(address = %admin_listener.local_addr().unwrap(), "admin listening");
+            Some(tokio::spawn(shutdown::serve(
+                admin_listener,
+                routers::admin_router(app_state.clone()),
+                None,
+                shutdown_token.clone(),
+                drain_timeout,
+            )))
+        }
+        Err(_) => None,
+    };
+
+    tracing::info!(
+        address = %listener.local_addr().unwrap(),
+        tls = tls_config.is_some(),
+        "listening"
+    );
+    shutdown::serve(listener, app, tls_config, shutdown_token, drain_timeout)
+        .await
+        .unwrap();
 
-    axum::serve(listener, app).await.unwrap();
+    if let Some(admin) = admin {
+        admin.await.unwrap().unwrap();
+    }
+    if let Some(reload) = reload {
+        reload.await.unwrap();
+    }
+    purge.await.unwrap();
+    app_state.database.close().await;
+    tracing::info!("shut down");
 }
@@ -1,29 +1,298 @@
-use crate::{database::Db, model::MessageResponse, routers::AppState, utils};
+use crate::{
+    database::{Db, DbApiToken},
+    error::Error,
+    model::EntriesFilter,
+    routers::AppState,
+    utils, versioning,
+};
 use axum::{
-    extract::{Request, State},
-    http::HeaderValue,
+    async_trait,
+    body::{self, Body},
+    extract::{ConnectInfo, FromRequestParts, MatchedPath, Query, RawPathParams, Request, State},
+    http::{request::Parts, Method, StatusCode},
     middleware::Next,
     response::Response,
+    RequestExt,
 };
+use serde::Deserialize;
+use sqlx::types::Uuid;
+use std::{net::SocketAddr, str::FromStr};
+
+const LOGIN_BODY_LIMIT: usize = 64 * 1024;
+const ENTRY_FOLDER_BODY_LIMIT: usize = 4 * 1024;
+
+#[derive(Deserialize)]
+struct LoginAttempt {
+    username: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EntryFolder {
+    folder_id: Option<Uuid>,
+}
+
+/// User authenticated by `validate_session`, available to handlers behind it.
+#[derive(Clone, Copy)]
+pub struct AuthUser(pub Uuid);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<AuthUser>() {
+            Some(auth_user) => Ok(*auth_user),
+            None => Err(unauthorized()),
+        }
+    }
+}
+
+fn unauthorized() -> Error {
+    Error::Unauthorized("Unauthorized access".to_string())
+}
+
+// an unknown token is the client's fault, a failing database is not and must show up as such
+fn unauthorized_if_missing(err: Error) -> Error {
+    match err {
+        Error::NotFound => unauthorized(),
+        err => err,
+    }
+}
 
 pub async fn validate_session(
     State(state): State<AppState<'_>>,
     mut request: Request,
     next: Next,
-) -> Response {
-    let session_id = match utils::get_headers_value(request.headers(), "session_id") {
-        Ok(session_id) => session_id,
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
+) -> Result<Response, Error> {
+    let access_token = utils::get_bearer_token(request.headers()).map_err(|_| unauthorized())?;
+    let hashed_access_token = crypto::hash_with_sha3(&access_token);
+    let now = utils::get_current_timestamp();
+
+    if access_token.starts_with(utils::API_TOKEN_PREFIX) {
+        let dbtoken = state
+            .database
+            .validate_api_token(&hashed_access_token, now)
+            .await
+            .map_err(unauthorized_if_missing)?;
+        if !api_token_allows(&state, &mut request, &dbtoken).await {
+            return Err(Error::Forbidden(
+                "Token scope does not allow this request".to_string(),
+            ));
+        }
+        request.extensions_mut().insert(AuthUser(dbtoken.user_id));
+        return Ok(next.run(request).await);
+    }
+
+    let user_id = state
+        .database
+        .validate_session(&hashed_access_token, now)
+        .await
+        .map_err(unauthorized_if_missing)?;
+    request.extensions_mut().insert(AuthUser(user_id));
+    Ok(next.run(request).await)
+}
+
+async fn api_token_allows(
+    state: &AppState<'_>,
+    request: &mut Request,
+    dbtoken: &DbApiToken,
+) -> bool {
+    let matched_path = match request.extensions().get::<MatchedPath>() {
+        Some(matched_path) => versioning::unversioned_path(matched_path.as_str()).to_string(),
+        None => return false,
     };
-    let hashed_session_id = crypto::hash_with_sha3(&session_id);
-    let user_id = match state.database.validate_session(&hashed_session_id).await {
-        Ok(user_id) => user_id,
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
+    // tokens cannot manage the account, which includes minting more tokens
+    if matched_path.starts_with("/users") {
+        return false;
+    }
+    let scope = &dbtoken.scope;
+    if scope.read_only && request.method() != Method::GET {
+        return false;
+    }
+    if scope.folder_ids.is_empty() && scope.entry_ids.is_empty() {
+        return true;
+    }
+
+    let params = match request.extract_parts::<RawPathParams>().await {
+        Ok(params) => params,
+        Err(_) => return false,
     };
-    let user_id: HeaderValue = match user_id.to_string().parse() {
-        Ok(header_value) => header_value,
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
+    let entry_id = params
+        .iter()
+        .find(|(name, _)| *name == "password_id" || *name == "note_id")
+        .and_then(|(_, value)| Uuid::from_str(value).ok());
+    match entry_id {
+        Some(entry_id) => {
+            if scope.entry_ids.contains(&entry_id) {
+                return true;
+            }
+            let in_scope = match state
+                .database
+                .get_entry_folder(&dbtoken.user_id, &entry_id)
+                .await
+            {
+                Ok(Some(folder_id)) => scope.folder_ids.contains(&folder_id),
+                _ => false,
+            };
+            // an entry reached through its folder may only be moved to another folder in scope
+            if in_scope && matched_path.ends_with("/folder") {
+                return body_folder_in_scope(request, &scope.folder_ids).await;
+            }
+            in_scope
+        }
+        // listing entries is fine once filtered down to one of the folders, the query does not
+        // decide where new entries go so creating them is not
+        None if request.method() == Method::GET
+            && (matched_path == "/passwords" || matched_path == "/notes") =>
+        {
+            match Query::<EntriesFilter>::try_from_uri(request.uri()) {
+                Ok(Query(filter)) => filter
+                    .folder_id
+                    .is_some_and(|folder_id| scope.folder_ids.contains(&folder_id)),
+                Err(_) => false,
+            }
+        }
+        None => false,
+    }
+}
+
+async fn body_folder_in_scope(request: &mut Request, folder_ids: &[Uuid]) -> bool {
+    // the folder is read from the body, which then has to be put back for the handler
+    let body = std::mem::take(request.body_mut());
+    let bytes = match body::to_bytes(body, ENTRY_FOLDER_BODY_LIMIT).await {
+        Ok(bytes) => bytes,
+        Err(_) => return false,
     };
-    request.headers_mut().insert("user_id", user_id);
-    next.run(request).await
+    let folder_id = serde_json::from_slice::<EntryFolder>(&bytes)
+        .ok()
+        .and_then(|entry_folder| entry_folder.folder_id);
+    *request.body_mut() = Body::from(bytes);
+    folder_id.is_some_and(|folder_id| folder_ids.contains(&folder_id))
+}
+
+/// Throttles the first SRP step per address, every call costs a modular exponentiation and a
+/// row whether the username exists or not.
+pub async fn limit_login_start(
+    State(state): State<AppState<'_>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Error> {
+    let attempt_key = format!("start:{}", client_ip(&request));
+
+    let now = utils::get_current_timestamp();
+    let locked_until = state.database.get_login_lockout(&attempt_key).await?;
+    if locked_until > now {
+        return Err(Error::TooManyRequests(
+            "Too many login attempts".to_string(),
+            locked_until - now,
+        ));
+    }
+
+    // attempts are counted until the address stays quiet for a backoff period
+    let reset_before = now - state.config.login_backoff_seconds;
+    let attempts = state
+        .database
+        .record_login_failure(&attempt_key, now, reset_before)
+        .await?;
+    if attempts >= state.config.login_start_ip_max_attempts {
+        metrics::counter!("login_lockouts_total").increment(1);
+        state
+            .database
+            .set_login_lockout(&attempt_key, now + state.config.login_backoff_seconds)
+            .await?;
+    }
+    Ok(next.run(request).await)
+}
+
+pub async fn limit_login(
+    State(state): State<AppState<'_>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Error> {
+    let ip = client_ip(&request);
+
+    // the username is read from the body, which then has to be put back for the handler
+    let (parts, body) = request.into_parts();
+    let bytes = body::to_bytes(body, LOGIN_BODY_LIMIT)
+        .await
+        .map_err(|_| Error::PayloadTooLarge)?;
+    let username = serde_json::from_slice::<LoginAttempt>(&bytes)
+        .ok()
+        .and_then(|attempt| attempt.username);
+    let request = Request::from_parts(parts, Body::from(bytes));
+
+    let mut attempt_keys = vec![(format!("ip:{ip}"), state.config.login_ip_max_failures)];
+    // usernames are matched case insensitively, so are their failures
+    if let Some(username) = username {
+        attempt_keys.push((
+            format!("user:{}", username.to_lowercase()),
+            state.config.login_user_max_failures,
+        ));
+    }
+
+    let now = utils::get_current_timestamp();
+    for (attempt_key, _) in &attempt_keys {
+        let locked_until = state.database.get_login_lockout(attempt_key).await?;
+        if locked_until > now {
+            return Err(Error::TooManyRequests(
+                "Too many failed login attempts".to_string(),
+                locked_until - now,
+            ));
+        }
+    }
+
+    let response = next.run(request).await;
+
+    let status = response.status();
+    if status == StatusCode::OK {
+        // the address keeps its count until its own window ends, or anyone holding an account
+        // could wipe it between guesses against other usernames
+        let user_keys = attempt_keys
+            .iter()
+            .filter(|(attempt_key, _)| attempt_key.starts_with("user:"));
+        for (attempt_key, _) in user_keys {
+            if let Err(err) = state.database.clear_login_failures(attempt_key).await {
+                tracing::warn!(error = ?err, "failed to clear login failures");
+            }
+        }
+    } else if status == StatusCode::BAD_REQUEST
+        || status == StatusCode::UNAUTHORIZED
+        || status == StatusCode::UNPROCESSABLE_ENTITY
+    {
+        metrics::counter!("login_failures_total").increment(1);
+        let now = utils::get_current_timestamp();
+        let reset_before = now - state.config.login_max_lockout_seconds;
+        for (attempt_key, max_failures) in &attempt_keys {
+            let failures = match state
+                .database
+                .record_login_failure(attempt_key, now, reset_before)
+                .await
+            {
+                Ok(failures) => failures,
+                Err(err) => {
+                    tracing::warn!(error = ?err, "failed to record a login failure");
+                    continue;
+                }
+            };
+            let lockout = state.config.login_lockout(failures, *max_failures);
+            if lockout > 0 {
+                metrics::counter!("login_lockouts_total").increment(1);
+                if let Err(err) = state
+                    .database
+                    .set_login_lockout(attempt_key, now + lockout)
+                    .await
+                {
+                    tracing::warn!(error = ?err, "failed to lock out login attempts");
+                }
+            }
+        }
+    }
+    Ok(response)
+}
+
+fn client_ip(request: &Request) -> String {
+    match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(address)) => address.ip().to_string(),
+        None => "unknown".to_string(),
+    }
 }
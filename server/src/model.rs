@@ -1,11 +1,112 @@
+use crate::config::Config;
+use crate::database::{DbCursor, DbPage, EntriesOrder};
+use crate::encoding::{self, Format};
+use crate::error::Error;
+use crate::routes::{notes::NoteOut, passwords::PasswordOut};
 use axum::{
     extract::Json,
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
 
 const CONTENT_TYPE_JSON: [(&str, &str); 1] = [("Content-Type", "application/json")];
+const CONTENT_TYPE_CBOR: [(&str, &str); 1] = [("Content-Type", encoding::CONTENT_TYPE_CBOR)];
+
+// bodies are written in the format negotiated by `encoding::negotiate`
+fn body<T: Serialize>(status_code: StatusCode, value: T) -> Response {
+    if encoding::current() != Format::Cbor {
+        return (status_code, CONTENT_TYPE_JSON, Json(value)).into_response();
+    }
+    let mut bytes = Vec::new();
+    match ciborium::into_writer(&value, &mut bytes) {
+        Ok(()) => (status_code, CONTENT_TYPE_CBOR, bytes).into_response(),
+        Err(err) => {
+            tracing::error!(error = ?err, "failed to encode cbor");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct EntriesFilter {
+    pub folder_id: Option<Uuid>,
+    pub tag_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub after: Option<String>,
+    #[serde(default)]
+    pub order: EntriesOrder,
+}
+
+impl EntriesFilter {
+    pub fn page(&self, config: &Config) -> Result<DbPage, Error> {
+        let after = match &self.after {
+            Some(after) => Some(
+                after
+                    .parse()
+                    .map_err(|_| Error::BadRequest("Invalid cursor".to_string()))?,
+            ),
+            None => None,
+        };
+        Ok(DbPage {
+            order: self.order,
+            limit: self
+                .limit
+                .unwrap_or(config.default_page_limit)
+                .clamp(1, config.max_page_limit),
+            after,
+        })
+    }
+}
+
+impl FromStr for DbCursor {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (updated_at, id) = value.split_once('_').unwrap_or(("0", value));
+        Ok(Self {
+            updated_at: updated_at.parse().map_err(|_| ())?,
+            id: id.parse().map_err(|_| ())?,
+        })
+    }
+}
+
+impl fmt::Display for DbCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.updated_at, self.id)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[aliases(
+    PasswordPageOut = PageOut<PasswordOut>,
+    NotePageOut = PageOut<NoteOut>
+)]
+pub struct PageOut<T: Serialize> {
+    entries: Vec<T>,
+    next_cursor: Option<String>,
+}
+
+impl<T: Serialize> PageOut<T> {
+    pub fn new<D>(mut rows: Vec<D>, page: &DbPage, cursor: fn(&D) -> DbCursor) -> Self
+    where
+        T: From<D>,
+    {
+        let next_cursor = if rows.len() as i64 > page.limit {
+            rows.truncate(page.limit as usize);
+            rows.last().map(|row| cursor(row).to_string())
+        } else {
+            None
+        };
+        Self {
+            entries: rows.into_iter().map(T::from).collect(),
+            next_cursor,
+        }
+    }
+}
 
 #[derive(Serialize)]
 pub struct DataResponse<T: Serialize> {
@@ -35,15 +136,31 @@ impl<T: Serialize> DataResponse<T> {
         }
         .into_response()
     }
+
+    pub fn accepted(data: T) -> Response {
+        Self {
+            status_code: StatusCode::ACCEPTED,
+            data,
+        }
+        .into_response()
+    }
+
+    pub fn service_unavailable(data: T) -> Response {
+        Self {
+            status_code: StatusCode::SERVICE_UNAVAILABLE,
+            data,
+        }
+        .into_response()
+    }
 }
 
 impl<T: Serialize> IntoResponse for DataResponse<T> {
     fn into_response(self) -> Response {
-        (self.status_code, CONTENT_TYPE_JSON, Json(self.data)).into_response()
+        body(self.status_code, self.data)
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct MessageResponse {
     #[serde(skip)]
     status_code: StatusCode,
@@ -74,26 +191,43 @@ impl MessageResponse {
         }
         .into_response()
     }
+}
 
-    pub fn bad_request(message: String) -> Response {
-        Self {
-            status_code: StatusCode::BAD_REQUEST,
-            message,
-        }
-        .into_response()
+impl IntoResponse for MessageResponse {
+    fn into_response(self) -> Response {
+        body(self.status_code, self)
     }
+}
 
-    pub fn unauthorized(message: String) -> Response {
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    #[serde(skip)]
+    status_code: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl ErrorResponse {
+    pub fn new(status_code: StatusCode, code: &'static str, message: String) -> Self {
         Self {
-            status_code: StatusCode::UNAUTHORIZED,
+            status_code,
+            code,
             message,
         }
-        .into_response()
+    }
+
+    pub fn too_many_requests(message: String, retry_after: i64) -> Response {
+        let mut response =
+            Self::new(StatusCode::TOO_MANY_REQUESTS, "too_many_requests", message).into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        response
     }
 }
 
-impl IntoResponse for MessageResponse {
+impl IntoResponse for ErrorResponse {
     fn into_response(self) -> Response {
-        (self.status_code, CONTENT_TYPE_JSON, Json(self)).into_response()
+        body(self.status_code, self)
     }
 }
@@ -0,0 +1,83 @@
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use std::{sync::OnceLock, time::Instant};
+use tracing::{span, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+const DATABASE_TARGET: &str = "server::database";
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Installs the global recorder on first use, metrics recorded before are lost.
+pub fn handle() -> &'static PrometheusHandle {
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .set_buckets(LATENCY_BUCKETS)
+            .expect("Invalid metrics buckets")
+            .install_recorder()
+            .expect("Metrics recorder already installed")
+    })
+}
+
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    // the matched route keeps ids out of the labels
+    let route = match request.extensions().get::<MatchedPath>() {
+        Some(matched_path) => matched_path.as_str().to_string(),
+        None => "unknown".to_string(),
+    };
+    let method = request.method().to_string();
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    metrics::counter!("http_requests_total", &labels).increment(1);
+    metrics::histogram!("http_request_duration_seconds", &labels)
+        .record(start.elapsed().as_secs_f64());
+    response
+}
+
+pub fn time<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    metrics::histogram!(name).record(start.elapsed().as_secs_f64());
+    result
+}
+
+struct SpanStart(Instant);
+
+/// Records the lifetime of every `Db` method span as the latency of that query.
+pub struct DatabaseMetricsLayer;
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for DatabaseMetricsLayer {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if attrs.metadata().target() != DATABASE_TARGET {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStart(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let start = span.extensions().get::<SpanStart>().map(|start| start.0);
+        if let Some(start) = start {
+            metrics::histogram!("db_query_duration_seconds", "query" => span.name())
+                .record(start.elapsed().as_secs_f64());
+        }
+    }
+}
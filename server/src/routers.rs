@@ -1,8 +1,15 @@
+use crate::config::Config;
 use crate::database::PostgreDb;
+use crate::encoding;
+use crate::hardening;
 use crate::middleware;
-use crate::routes::{notes, passwords, users};
+use crate::monitoring;
+use crate::routes::{admin, folders, health, notes, openapi, passwords, tags, users, version};
+use crate::telemetry;
+use crate::versioning;
 use axum::{
-    routing::{delete, get, patch, post},
+    extract::DefaultBodyLimit,
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use crypto::Argon2Hasher;
@@ -12,58 +19,232 @@ use std::env;
 pub struct AppState<'a> {
     pub hasher: Argon2Hasher<'a>,
     pub database: PostgreDb,
+    pub config: Config,
 }
 
-pub async fn users_router() -> Router {
-    let app_state = AppState {
-        hasher: Argon2Hasher::new(),
-        database: PostgreDb::build(env::var("DATABASE_URL").expect("DATABASE_URL not set"))
+impl AppState<'static> {
+    /// Connects to `DATABASE_URL`, every router built from the state shares its pool.
+    pub async fn build() -> Self {
+        let config = Config::from_env();
+        Self {
+            hasher: Argon2Hasher::new(),
+            database: PostgreDb::build(
+                env::var("DATABASE_URL").expect("DATABASE_URL not set"),
+                &config,
+            )
             .await
             .expect("Invalid database configuration"),
-    };
+            config,
+        }
+    }
+}
+
+pub async fn app() -> Router {
+    api(AppState::build().await)
+}
+
+pub fn api(app_state: AppState<'static>) -> Router {
+    monitoring::handle();
+    let router = Router::new()
+        .nest("/v1", v1_router(app_state.clone()))
+        // kept until the sunset so clients predating /v1 keep working
+        .merge(versioning::unversioned(
+            v1_router(app_state.clone()),
+            &app_state.config,
+        ))
+        .nest("/health", health_router(app_state.clone()))
+        .layer(axum::middleware::from_fn(encoding::negotiate));
+    telemetry::layer(hardening::layer(router, &app_state.config))
+}
+
+pub fn v1_router(app_state: AppState<'static>) -> Router {
     Router::new()
-        .route("/register", post(users::post_users_register))
+        .nest("/users", users_router(app_state.clone()))
+        .nest("/passwords", passwords_router(app_state.clone()))
+        .nest(
+            "/notes",
+            notes_router(app_state.clone())
+                .layer(DefaultBodyLimit::max(app_state.config.max_note_body_bytes)),
+        )
+        .nest("/folders", folders_router(app_state.clone()))
+        .nest("/tags", tags_router(app_state))
+        .route("/version", get(version::get_version))
+        .route("/openapi.json", get(openapi::get_openapi))
+}
+
+pub fn users_router(app_state: AppState<'static>) -> Router {
+    let login_router = Router::new()
+        .route("/upgrade", post(users::post_users_upgrade))
         .route("/login", post(users::post_users_login))
+        .route("/login/totp", post(users::post_users_login_totp))
+        .route(
+            "/webauthn/login/finish",
+            post(users::post_users_webauthn_login_finish),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::limit_login,
+        ));
+    let login_start_router = Router::new()
+        .route("/login/start", post(users::post_users_login_start))
+        .route_layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::limit_login_start,
+        ));
+    Router::new()
+        .route("/totp/enroll", post(users::post_users_totp_enroll))
+        .route("/totp/confirm", post(users::post_users_totp_confirm))
+        .route("/totp/disable", post(users::post_users_totp_disable))
+        .route(
+            "/webauthn/register/start",
+            post(users::post_users_webauthn_register_start),
+        )
+        .route(
+            "/webauthn/register/finish",
+            post(users::post_users_webauthn_register_finish),
+        )
+        .route(
+            "/webauthn/credentials",
+            get(users::get_users_webauthn_credentials),
+        )
+        .route(
+            "/webauthn/credentials/:credential_id",
+            delete(users::delete_users_webauthn_credentials_id),
+        )
+        .route("/tokens", post(users::post_users_tokens))
+        .route("/tokens", get(users::get_users_tokens))
+        .route("/tokens/:token_id", delete(users::delete_users_tokens_id))
+        .route_layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::validate_session,
+        ))
+        .merge(login_router)
+        .merge(login_start_router)
+        .route("/register", post(users::post_users_register))
+        .route("/prelogin", post(users::post_users_prelogin))
+        .route(
+            "/webauthn/login/start",
+            post(users::post_users_webauthn_login_start),
+        )
+        .route("/refresh", post(users::post_users_refresh))
         .route("/logout", post(users::post_users_logout))
+        .route_layer(axum::middleware::from_fn(monitoring::track_requests))
         .with_state(app_state)
 }
 
-pub async fn passwords_router() -> Router {
-    let app_state = AppState {
-        hasher: Argon2Hasher::new(),
-        database: PostgreDb::build(env::var("DATABASE_URL").expect("DATABASE_URL not set"))
-            .await
-            .expect("Invalid database configuration"),
-    };
+pub fn passwords_router(app_state: AppState<'static>) -> Router {
     Router::new()
         .route("/", post(passwords::post_passwords))
         .route("/", get(passwords::get_passwords))
+        .route("/search", get(passwords::get_passwords_search))
         .route("/:password_id", get(passwords::get_passwords_id))
         .route("/:password_id", delete(passwords::delete_passwords_id))
         .route("/:password_id", patch(passwords::patch_passwords_id))
+        .route(
+            "/:password_id/history",
+            get(passwords::get_passwords_id_history),
+        )
+        .route(
+            "/:password_id/history/:version/restore",
+            post(passwords::post_passwords_id_history_restore),
+        )
+        .route(
+            "/:password_id/search_tokens",
+            put(passwords::put_passwords_id_search_tokens),
+        )
+        .route(
+            "/:password_id/folder",
+            put(passwords::put_passwords_id_folder),
+        )
+        .route("/:password_id/totp", put(passwords::put_passwords_id_totp))
+        .route(
+            "/:password_id/tags/:tag_id",
+            put(passwords::put_passwords_id_tags_id),
+        )
+        .route(
+            "/:password_id/tags/:tag_id",
+            delete(passwords::delete_passwords_id_tags_id),
+        )
         .route_layer(axum::middleware::from_fn_with_state(
             app_state.clone(),
             middleware::validate_session,
         ))
+        .route_layer(axum::middleware::from_fn(monitoring::track_requests))
         .with_state(app_state)
 }
 
-pub async fn notes_router() -> Router {
-    let app_state = AppState {
-        hasher: Argon2Hasher::new(),
-        database: PostgreDb::build(env::var("DATABASE_URL").expect("DATABASE_URL not set"))
-            .await
-            .expect("Invalid database configuration"),
-    };
+pub fn notes_router(app_state: AppState<'static>) -> Router {
     Router::new()
         .route("/", post(notes::post_notes))
         .route("/", get(notes::get_notes))
         .route("/:note_id", get(notes::get_notes_id))
         .route("/:note_id", delete(notes::delete_notes_id))
         .route("/:note_id", patch(notes::patch_notes_id))
+        .route("/:note_id/revisions", get(notes::get_notes_id_revisions))
+        .route(
+            "/:note_id/revisions/:revision/restore",
+            post(notes::post_notes_id_revisions_restore),
+        )
+        .route("/:note_id/folder", put(notes::put_notes_id_folder))
+        .route("/:note_id/tags/:tag_id", put(notes::put_notes_id_tags_id))
+        .route(
+            "/:note_id/tags/:tag_id",
+            delete(notes::delete_notes_id_tags_id),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::validate_session,
+        ))
+        .route_layer(axum::middleware::from_fn(monitoring::track_requests))
+        .with_state(app_state)
+}
+
+pub fn folders_router(app_state: AppState<'static>) -> Router {
+    Router::new()
+        .route("/", post(folders::post_folders))
+        .route("/", get(folders::get_folders))
+        .route("/:folder_id", get(folders::get_folders_id))
+        .route("/:folder_id", delete(folders::delete_folders_id))
+        .route("/:folder_id", patch(folders::patch_folders_id))
         .route_layer(axum::middleware::from_fn_with_state(
             app_state.clone(),
             middleware::validate_session,
         ))
+        .route_layer(axum::middleware::from_fn(monitoring::track_requests))
+        .with_state(app_state)
+}
+
+pub fn tags_router(app_state: AppState<'static>) -> Router {
+    Router::new()
+        .route("/", post(tags::post_tags))
+        .route("/", get(tags::get_tags))
+        .route("/:tag_id", get(tags::get_tags_id))
+        .route("/:tag_id", delete(tags::delete_tags_id))
+        .route("/:tag_id", patch(tags::patch_tags_id))
+        .route_layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::validate_session,
+        ))
+        .route_layer(axum::middleware::from_fn(monitoring::track_requests))
+        .with_state(app_state)
+}
+
+pub fn health_router(app_state: AppState<'static>) -> Router {
+    Router::new()
+        .route("/live", get(health::get_health_live))
+        .route("/ready", get(health::get_health_ready))
+        .route_layer(axum::middleware::from_fn(monitoring::track_requests))
+        .with_state(app_state)
+}
+
+pub async fn admin() -> Router {
+    admin_router(AppState::build().await)
+}
+
+/// Operator endpoints, served on their own listener and never exposed with the API.
+pub fn admin_router(app_state: AppState<'static>) -> Router {
+    monitoring::handle();
+    Router::new()
+        .route("/metrics", get(admin::get_metrics))
         .with_state(app_state)
 }
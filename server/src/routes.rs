@@ -1,3 +1,9 @@
+pub mod admin;
+pub mod folders;
+pub mod health;
 pub mod notes;
+pub mod openapi;
 pub mod passwords;
+pub mod tags;
 pub mod users;
+pub mod version;
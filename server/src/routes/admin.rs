@@ -0,0 +1,31 @@
+use crate::database::Db;
+use crate::error::Error;
+use crate::monitoring;
+use crate::routers::AppState;
+use crate::utils;
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+
+const CONTENT_TYPE_PROMETHEUS: &str = "text/plain; version=0.0.4";
+
+pub async fn get_metrics(State(state): State<AppState<'_>>) -> Result<Response, Error> {
+    // gauges are sampled when scraped
+    let (size, idle) = state.database.pool_usage();
+    metrics::gauge!("db_pool_connections", "state" => "idle").set(idle as f64);
+    metrics::gauge!("db_pool_connections", "state" => "active")
+        .set((size as usize).saturating_sub(idle) as f64);
+    let sessions = state
+        .database
+        .count_active_sessions(utils::get_current_timestamp())
+        .await?;
+    metrics::gauge!("active_sessions").set(sessions as f64);
+
+    Ok((
+        [(header::CONTENT_TYPE, CONTENT_TYPE_PROMETHEUS)],
+        monitoring::handle().render(),
+    )
+        .into_response())
+}
@@ -0,0 +1,180 @@
+use crate::database::{Db, DbFolder};
+use crate::encoding;
+use crate::error::Error;
+use crate::middleware::AuthUser;
+use crate::model::{DataResponse, MessageResponse};
+use crate::routers::AppState;
+use crate::utils;
+use axum::{
+    extract::{
+        rejection::{JsonRejection, PathRejection},
+        Json, Path, State,
+    },
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+pub struct EntryFolderIn {
+    pub folder_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct FolderIn {
+    pub parent_id: Option<Uuid>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    pub name: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    pub name_nonce: [u8; 12],
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FolderOut {
+    folder_id: String,
+    parent_id: Option<String>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    name: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    name_nonce: [u8; 12],
+}
+
+impl From<DbFolder> for FolderOut {
+    fn from(dbfolder: DbFolder) -> Self {
+        Self {
+            folder_id: dbfolder.folder_id.to_string(),
+            parent_id: dbfolder.parent_id.map(|parent_id| parent_id.to_string()),
+            name: dbfolder.name,
+            name_nonce: dbfolder.name_nonce,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/folders",
+    tag = "folders",
+    request_body = FolderIn,
+    responses(
+        (status = 201, description = "Folder created", body = FolderOut),
+    ),
+)]
+pub async fn post_folders(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    folderin: Result<Json<FolderIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let folderin = folderin?.0;
+
+    let folder_id = utils::create_uuid_v4();
+    let dbfolder = state
+        .database
+        .create_folder(
+            &folder_id,
+            &user_id,
+            folderin.parent_id.as_ref(),
+            &folderin.name,
+            &folderin.name_nonce,
+        )
+        .await?;
+    Ok(DataResponse::created(FolderOut::from(dbfolder)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/folders",
+    tag = "folders",
+    responses(
+        (status = 200, description = "Folders", body = Vec<FolderOut>),
+    ),
+)]
+pub async fn get_folders(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+) -> Result<Response, Error> {
+    let dbfolders = state.database.get_folders(&user_id).await?;
+
+    Ok(DataResponse::ok(
+        dbfolders
+            .into_iter()
+            .map(FolderOut::from)
+            .collect::<Vec<FolderOut>>(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/folders/{folder_id}",
+    tag = "folders",
+    params(("folder_id" = Uuid, Path, description = "Folder id")),
+    responses(
+        (status = 200, description = "Folder", body = FolderOut),
+    ),
+)]
+pub async fn get_folders_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    folder_id: Result<Path<Uuid>, PathRejection>,
+) -> Result<Response, Error> {
+    let folder_id = folder_id?.0;
+
+    let dbfolder = state.database.get_folder(&user_id, &folder_id).await?;
+    Ok(DataResponse::ok(FolderOut::from(dbfolder)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/folders/{folder_id}",
+    tag = "folders",
+    params(("folder_id" = Uuid, Path, description = "Folder id")),
+    responses(
+        (status = 200, description = "Folder deleted", body = MessageResponse),
+    ),
+)]
+pub async fn delete_folders_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    folder_id: Result<Path<Uuid>, PathRejection>,
+) -> Result<Response, Error> {
+    let folder_id = folder_id?.0;
+
+    state.database.delete_folder(&user_id, &folder_id).await?;
+    Ok(MessageResponse::ok("Folder deleted".to_string()))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v1/folders/{folder_id}",
+    tag = "folders",
+    request_body = FolderIn,
+    params(("folder_id" = Uuid, Path, description = "Folder id")),
+    responses(
+        (status = 200, description = "Folder renamed", body = FolderOut),
+    ),
+)]
+pub async fn patch_folders_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    folder_id: Result<Path<Uuid>, PathRejection>,
+    folderin: Result<Json<FolderIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let folderin = folderin?.0;
+    let folder_id = folder_id?.0;
+
+    let dbfolder = state
+        .database
+        .patch_folder(
+            &folder_id,
+            &user_id,
+            folderin.parent_id.as_ref(),
+            &folderin.name,
+            &folderin.name_nonce,
+        )
+        .await?;
+    Ok(DataResponse::ok(FolderOut::from(dbfolder)))
+}
@@ -0,0 +1,116 @@
+use crate::database::Db;
+use crate::error::Error;
+use crate::model::{DataResponse, MessageResponse};
+use crate::routers::AppState;
+use axum::{extract::State, response::Response};
+use serde::Serialize;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+// probes are retried by the orchestrator, a hanging pool must not hang them
+const READY_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, ToSchema)]
+pub struct ComponentOut {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl ComponentOut {
+    fn up() -> Self {
+        Self {
+            status: "up",
+            message: None,
+        }
+    }
+
+    fn down(message: String) -> Self {
+        Self {
+            status: "down",
+            message: Some(message),
+        }
+    }
+
+    fn is_up(&self) -> bool {
+        self.message.is_none()
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ComponentsOut {
+    database: ComponentOut,
+    migrations: ComponentOut,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReadyOut {
+    status: &'static str,
+    components: ComponentsOut,
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    tag = "health",
+    responses(
+        (status = 200, description = "Process is serving requests", body = MessageResponse),
+    ),
+    security(()),
+)]
+pub async fn get_health_live() -> Result<Response, Error> {
+    Ok(MessageResponse::ok("Alive".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready for traffic", body = ReadyOut),
+        (status = 503, description = "Database unreachable or migrations pending", body = ReadyOut),
+    ),
+    security(()),
+)]
+pub async fn get_health_ready(State(state): State<AppState<'_>>) -> Result<Response, Error> {
+    let database = match tokio::time::timeout(READY_CHECK_TIMEOUT, state.database.ping()).await {
+        Ok(Ok(())) => ComponentOut::up(),
+        Ok(Err(err)) => {
+            tracing::warn!(error = ?err, "database not ready");
+            ComponentOut::down("Database unreachable".to_string())
+        }
+        Err(_) => ComponentOut::down("Database timed out".to_string()),
+    };
+    let migrations = if !database.is_up() {
+        ComponentOut::down("Database unreachable".to_string())
+    } else {
+        match tokio::time::timeout(
+            READY_CHECK_TIMEOUT,
+            state.database.count_pending_migrations(),
+        )
+        .await
+        {
+            Ok(Ok(0)) => ComponentOut::up(),
+            Ok(Ok(pending)) => ComponentOut::down(format!("{pending} migrations pending")),
+            Ok(Err(err)) => {
+                tracing::warn!(error = ?err, "migrations not ready");
+                ComponentOut::down("Migration state unavailable".to_string())
+            }
+            Err(_) => ComponentOut::down("Database timed out".to_string()),
+        }
+    };
+
+    let ready = database.is_up() && migrations.is_up();
+    let readyout = ReadyOut {
+        status: if ready { "ready" } else { "unavailable" },
+        components: ComponentsOut {
+            database,
+            migrations,
+        },
+    };
+    if ready {
+        Ok(DataResponse::ok(readyout))
+    } else {
+        Ok(DataResponse::service_unavailable(readyout))
+    }
+}
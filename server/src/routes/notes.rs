@@ -1,34 +1,56 @@
-use crate::database::{Db, DbNote};
-use crate::model::{DataResponse, MessageResponse};
+use crate::database::{Db, DbCursor, DbNote, DbNoteRevision};
+use crate::encoding;
+use crate::error::Error;
+use crate::middleware::AuthUser;
+use crate::model::{DataResponse, EntriesFilter, MessageResponse, PageOut};
 use crate::routers::AppState;
+use crate::routes::folders::EntryFolderIn;
 use crate::utils;
 use axum::{
     extract::{
-        rejection::{JsonRejection, PathRejection},
-        Json, Path, State,
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        Json, Path, Query, State,
     },
-    http::HeaderMap,
     response::Response,
 };
 use serde::{Deserialize, Serialize};
 use sqlx::types::Uuid;
-use std::str::FromStr;
+use utoipa::ToSchema;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct NoteIn {
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     pub title: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     pub title_nonce: [u8; 12],
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     pub content: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     pub content_nonce: [u8; 12],
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct NoteOut {
     note_id: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     title: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     title_nonce: [u8; 12],
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     content: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     content_nonce: [u8; 12],
+    folder_id: Option<String>,
+    tag_ids: Vec<String>,
+    updated_at: i64,
 }
 
 impl From<DbNote> for NoteOut {
@@ -39,29 +61,68 @@ impl From<DbNote> for NoteOut {
             title_nonce: dbnote.title_nonce,
             content: dbnote.content,
             content_nonce: dbnote.content_nonce,
+            folder_id: dbnote.folder_id.map(|folder_id| folder_id.to_string()),
+            tag_ids: dbnote
+                .tag_ids
+                .into_iter()
+                .map(|tag_id| tag_id.to_string())
+                .collect(),
+            updated_at: dbnote.updated_at,
         }
     }
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct NoteRevisionOut {
+    note_id: String,
+    revision: i32,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    title: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    title_nonce: [u8; 12],
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    content: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    content_nonce: [u8; 12],
+    replaced_at: i64,
+}
+
+impl From<DbNoteRevision> for NoteRevisionOut {
+    fn from(dbrevision: DbNoteRevision) -> Self {
+        Self {
+            note_id: dbrevision.note_id.to_string(),
+            revision: dbrevision.revision,
+            title: dbrevision.title,
+            title_nonce: dbrevision.title_nonce,
+            content: dbrevision.content,
+            content_nonce: dbrevision.content_nonce,
+            replaced_at: dbrevision.replaced_at,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/notes",
+    tag = "notes",
+    request_body = NoteIn,
+    responses(
+        (status = 201, description = "Note created", body = NoteOut),
+    ),
+)]
 pub async fn post_notes(
-    headers: HeaderMap,
+    AuthUser(user_id): AuthUser,
     State(state): State<AppState<'_>>,
     notein: Result<Json<NoteIn>, JsonRejection>,
-) -> Response {
-    let notein = match notein {
-        Ok(notein) => notein.0,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
-
-    let user_id = match utils::get_headers_value(&headers, "user_id") {
-        Ok(user_id) => match Uuid::from_str(&user_id) {
-            Ok(user_id) => user_id,
-            Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-        },
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
+) -> Result<Response, Error> {
+    let notein = notein?.0;
+
     let note_id = utils::create_uuid_v4();
-    match state
+    let dbnote = state
         .database
         .create_note(
             &note_id,
@@ -71,114 +132,111 @@ pub async fn post_notes(
             &notein.content,
             &notein.content_nonce,
         )
-        .await
-    {
-        Ok(dbnote) => DataResponse::created(NoteOut::from(dbnote)),
-        Err(_) => MessageResponse::bad_request("Failed to add a new note".to_string()),
-    }
+        .await?;
+    Ok(DataResponse::created(NoteOut::from(dbnote)))
 }
 
-pub async fn get_notes(headers: HeaderMap, State(state): State<AppState<'_>>) -> Response {
-    let user_id = match utils::get_headers_value(&headers, "user_id") {
-        Ok(user_id) => match Uuid::from_str(&user_id) {
-            Ok(user_id) => user_id,
-            Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-        },
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
+#[utoipa::path(
+    get,
+    path = "/v1/notes",
+    tag = "notes",
+    params(EntriesFilter),
+    responses(
+        (status = 200, description = "Page of notes", body = NotePageOut),
+    ),
+)]
+pub async fn get_notes(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    filter: Result<Query<EntriesFilter>, QueryRejection>,
+) -> Result<Response, Error> {
+    let filter = filter?.0;
 
-    let dbnotes = match state.database.get_notes(&user_id).await {
-        Ok(dbnotes) => dbnotes,
-        Err(_) => return MessageResponse::bad_request("Failed to get notes".to_string()),
-    };
+    let page = filter.page(&state.config)?;
 
-    DataResponse::ok(
-        dbnotes
-            .into_iter()
-            .map(|dbnote| NoteOut::from(dbnote))
-            .collect::<Vec<NoteOut>>(),
-    )
+    let dbnotes = state
+        .database
+        .get_notes(
+            &user_id,
+            filter.folder_id.as_ref(),
+            filter.tag_id.as_ref(),
+            &page,
+        )
+        .await?;
+
+    Ok(DataResponse::ok(PageOut::<NoteOut>::new(
+        dbnotes,
+        &page,
+        |dbnote| DbCursor {
+            updated_at: dbnote.updated_at,
+            id: dbnote.note_id,
+        },
+    )))
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/notes/{note_id}",
+    tag = "notes",
+    params(("note_id" = Uuid, Path, description = "Note id")),
+    responses(
+        (status = 200, description = "Note", body = NoteOut),
+    ),
+)]
 pub async fn get_notes_id(
-    headers: HeaderMap,
+    AuthUser(user_id): AuthUser,
     State(state): State<AppState<'_>>,
     note_id: Result<Path<Uuid>, PathRejection>,
-) -> Response {
-    let note_id = match note_id {
-        Ok(note_id) => note_id.0,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
-
-    let user_id = match utils::get_headers_value(&headers, "user_id") {
-        Ok(user_id) => match Uuid::from_str(&user_id) {
-            Ok(user_id) => user_id,
-            Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-        },
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
-
-    match state.database.get_note(&user_id, &note_id).await {
-        Ok(dbnote) => {
-            if dbnote.user_id == user_id {
-                return DataResponse::ok(NoteOut::from(dbnote));
-            } else {
-                return MessageResponse::unauthorized("Unauthorized access".to_string());
-            }
-        }
-        Err(_) => return MessageResponse::bad_request("Failed to get a note".to_string()),
+) -> Result<Response, Error> {
+    let note_id = note_id?.0;
+
+    let dbnote = state.database.get_note(&user_id, &note_id).await?;
+    if dbnote.user_id != user_id {
+        return Err(Error::Unauthorized("Unauthorized access".to_string()));
     }
+    Ok(DataResponse::ok(NoteOut::from(dbnote)))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/v1/notes/{note_id}",
+    tag = "notes",
+    params(("note_id" = Uuid, Path, description = "Note id")),
+    responses(
+        (status = 200, description = "Note deleted", body = MessageResponse),
+    ),
+)]
 pub async fn delete_notes_id(
-    headers: HeaderMap,
+    AuthUser(user_id): AuthUser,
     State(state): State<AppState<'_>>,
     note_id: Result<Path<Uuid>, PathRejection>,
-) -> Response {
-    let note_id = match note_id {
-        Ok(note_id) => note_id,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
-
-    let user_id = match utils::get_headers_value(&headers, "user_id") {
-        Ok(user_id) => match Uuid::from_str(&user_id) {
-            Ok(user_id) => user_id,
-            Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-        },
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
+) -> Result<Response, Error> {
+    let note_id = note_id?.0;
 
-    match state.database.delete_note(&user_id, &note_id).await {
-        Ok(_) => MessageResponse::ok("Note deleted".to_string()),
-        Err(_) => MessageResponse::bad_request("Failed to delete a note".to_string()),
-    }
+    state.database.delete_note(&user_id, &note_id).await?;
+    Ok(MessageResponse::ok("Note deleted".to_string()))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/v1/notes/{note_id}",
+    tag = "notes",
+    request_body = NoteIn,
+    params(("note_id" = Uuid, Path, description = "Note id")),
+    responses(
+        (status = 201, description = "Note updated", body = NoteOut),
+    ),
+)]
 pub async fn patch_notes_id(
-    headers: HeaderMap,
+    AuthUser(user_id): AuthUser,
     State(state): State<AppState<'_>>,
     note_id: Result<Path<Uuid>, PathRejection>,
     notein: Result<Json<NoteIn>, JsonRejection>,
-) -> Response {
-    let notein = match notein {
-        Ok(notein) => notein.0,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
-
-    let note_id = match note_id {
-        Ok(note_id) => note_id.0,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
-
-    let user_id = match utils::get_headers_value(&headers, "user_id") {
-        Ok(user_id) => match Uuid::from_str(&user_id) {
-            Ok(user_id) => user_id,
-            Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-        },
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
+) -> Result<Response, Error> {
+    let notein = notein?.0;
+    let note_id = note_id?.0;
 
-    match state
+    let dbnote = state
         .database
         .patch_note(
             &note_id,
@@ -188,9 +246,130 @@ pub async fn patch_notes_id(
             &notein.content,
             &notein.content_nonce,
         )
-        .await
-    {
-        Ok(dbnote) => DataResponse::created(NoteOut::from(dbnote)),
-        Err(_) => MessageResponse::bad_request("Failed to edit a note".to_string()),
-    }
+        .await?;
+    Ok(DataResponse::created(NoteOut::from(dbnote)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/notes/{note_id}/revisions",
+    tag = "notes",
+    params(("note_id" = Uuid, Path, description = "Note id")),
+    responses(
+        (status = 200, description = "Previous revisions", body = Vec<NoteRevisionOut>),
+    ),
+)]
+pub async fn get_notes_id_revisions(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    note_id: Result<Path<Uuid>, PathRejection>,
+) -> Result<Response, Error> {
+    let note_id = note_id?.0;
+
+    let dbrevisions = state
+        .database
+        .get_note_revisions(&user_id, &note_id)
+        .await?;
+
+    Ok(DataResponse::ok(
+        dbrevisions
+            .into_iter()
+            .map(NoteRevisionOut::from)
+            .collect::<Vec<NoteRevisionOut>>(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/notes/{note_id}/revisions/{revision}/restore",
+    tag = "notes",
+    params(("note_id" = Uuid, Path, description = "Note id"), ("revision" = i32, Path, description = "Note revision")),
+    responses(
+        (status = 200, description = "Note restored", body = NoteOut),
+    ),
+)]
+pub async fn post_notes_id_revisions_restore(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    path: Result<Path<(Uuid, i32)>, PathRejection>,
+) -> Result<Response, Error> {
+    let (note_id, revision) = path?.0;
+
+    let dbnote = state
+        .database
+        .restore_note(&user_id, &note_id, revision)
+        .await?;
+    Ok(DataResponse::ok(NoteOut::from(dbnote)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/notes/{note_id}/folder",
+    tag = "notes",
+    request_body = EntryFolderIn,
+    params(("note_id" = Uuid, Path, description = "Note id")),
+    responses(
+        (status = 200, description = "Note moved", body = NoteOut),
+    ),
+)]
+pub async fn put_notes_id_folder(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    note_id: Result<Path<Uuid>, PathRejection>,
+    folder: Result<Json<EntryFolderIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let folder = folder?.0;
+    let note_id = note_id?.0;
+
+    let dbnote = state
+        .database
+        .set_note_folder(&user_id, &note_id, folder.folder_id.as_ref())
+        .await?;
+    Ok(DataResponse::ok(NoteOut::from(dbnote)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/notes/{note_id}/tags/{tag_id}",
+    tag = "notes",
+    params(("note_id" = Uuid, Path, description = "Note id"), ("tag_id" = Uuid, Path, description = "Tag id")),
+    responses(
+        (status = 200, description = "Tag added", body = NoteOut),
+    ),
+)]
+pub async fn put_notes_id_tags_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    path: Result<Path<(Uuid, Uuid)>, PathRejection>,
+) -> Result<Response, Error> {
+    let (note_id, tag_id) = path?.0;
+
+    let dbnote = state
+        .database
+        .add_note_tag(&user_id, &note_id, &tag_id)
+        .await?;
+    Ok(DataResponse::ok(NoteOut::from(dbnote)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/notes/{note_id}/tags/{tag_id}",
+    tag = "notes",
+    params(("note_id" = Uuid, Path, description = "Note id"), ("tag_id" = Uuid, Path, description = "Tag id")),
+    responses(
+        (status = 200, description = "Tag removed", body = NoteOut),
+    ),
+)]
+pub async fn delete_notes_id_tags_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    path: Result<Path<(Uuid, Uuid)>, PathRejection>,
+) -> Result<Response, Error> {
+    let (note_id, tag_id) = path?.0;
+
+    let dbnote = state
+        .database
+        .remove_note_tag(&user_id, &note_id, &tag_id)
+        .await?;
+    Ok(DataResponse::ok(NoteOut::from(dbnote)))
 }
@@ -0,0 +1,182 @@
+use crate::database::EntriesOrder;
+use crate::error::Error;
+use crate::model::{ErrorResponse, MessageResponse, NotePageOut, PasswordPageOut};
+use crate::routes::{folders, health, notes, passwords, tags, users, version};
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use utoipa::{
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        ContentBuilder, Ref, ResponseBuilder,
+    },
+    Modify, OpenApi,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Vault API"),
+    paths(
+        users::post_users_register,
+        users::post_users_upgrade,
+        users::post_users_prelogin,
+        users::post_users_login_start,
+        users::post_users_login,
+        users::post_users_login_totp,
+        users::post_users_totp_enroll,
+        users::post_users_totp_confirm,
+        users::post_users_totp_disable,
+        users::post_users_webauthn_register_start,
+        users::post_users_webauthn_register_finish,
+        users::get_users_webauthn_credentials,
+        users::delete_users_webauthn_credentials_id,
+        users::post_users_tokens,
+        users::get_users_tokens,
+        users::delete_users_tokens_id,
+        users::post_users_webauthn_login_start,
+        users::post_users_webauthn_login_finish,
+        users::post_users_refresh,
+        users::post_users_logout,
+        passwords::post_passwords,
+        passwords::get_passwords,
+        passwords::get_passwords_search,
+        passwords::get_passwords_id,
+        passwords::delete_passwords_id,
+        passwords::patch_passwords_id,
+        passwords::get_passwords_id_history,
+        passwords::post_passwords_id_history_restore,
+        passwords::put_passwords_id_search_tokens,
+        passwords::put_passwords_id_folder,
+        passwords::put_passwords_id_totp,
+        passwords::put_passwords_id_tags_id,
+        passwords::delete_passwords_id_tags_id,
+        notes::post_notes,
+        notes::get_notes,
+        notes::get_notes_id,
+        notes::delete_notes_id,
+        notes::patch_notes_id,
+        notes::get_notes_id_revisions,
+        notes::post_notes_id_revisions_restore,
+        notes::put_notes_id_folder,
+        notes::put_notes_id_tags_id,
+        notes::delete_notes_id_tags_id,
+        folders::post_folders,
+        folders::get_folders,
+        folders::get_folders_id,
+        folders::delete_folders_id,
+        folders::patch_folders_id,
+        tags::post_tags,
+        tags::get_tags,
+        tags::get_tags_id,
+        tags::delete_tags_id,
+        tags::patch_tags_id,
+        health::get_health_live,
+        health::get_health_ready,
+        version::get_version,
+    ),
+    components(schemas(
+        ErrorResponse,
+        MessageResponse,
+        EntriesOrder,
+        PasswordPageOut,
+        NotePageOut,
+        users::UserIn,
+        users::UpgradeIn,
+        users::LoginStartIn,
+        users::LoginStartOut,
+        users::LoginIn,
+        users::PreloginIn,
+        users::PreloginOut,
+        users::UserOut,
+        users::RefreshIn,
+        users::TokensOut,
+        users::SessionOut,
+        users::ApiTokenIn,
+        users::ApiTokenOut,
+        users::SecondFactorIn,
+        users::LoginSecondFactorIn,
+        users::LoginChallengeOut,
+        users::TotpEnrollmentOut,
+        users::RecoveryCodesOut,
+        users::WebauthnRegistrationIn,
+        users::WebauthnLoginStartIn,
+        users::WebauthnLoginIn,
+        users::WebauthnRelyingPartyOut,
+        users::WebauthnUserOut,
+        users::WebauthnParametersOut,
+        users::WebauthnDescriptorOut,
+        users::WebauthnCreationOut,
+        users::WebauthnRequestOut,
+        users::WebauthnCredentialOut,
+        passwords::PasswordIn,
+        passwords::PasswordTotpIn,
+        passwords::SearchTokensIn,
+        passwords::PasswordOut,
+        passwords::PasswordHistoryOut,
+        notes::NoteIn,
+        notes::NoteOut,
+        notes::NoteRevisionOut,
+        folders::EntryFolderIn,
+        folders::FolderIn,
+        folders::FolderOut,
+        tags::TagIn,
+        tags::TagOut,
+        health::ComponentOut,
+        health::ComponentsOut,
+        health::ReadyOut,
+        version::VersionOut,
+    )),
+    modifiers(&Bearer, &ErrorResponses),
+    security(("bearer" = [])),
+)]
+pub struct ApiDoc;
+
+struct Bearer;
+
+impl Modify for Bearer {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("Access token from login or a personal access token"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+// every operation can fail with the same error body
+struct ErrorResponses;
+
+impl Modify for ErrorResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for path_item in openapi.paths.paths.values_mut() {
+            for operation in path_item.operations.values_mut() {
+                for (status, description) in [("4XX", "Request rejected"), ("5XX", "Server failed")]
+                {
+                    let response = ResponseBuilder::new()
+                        .description(description)
+                        .content(
+                            "application/json",
+                            ContentBuilder::new()
+                                .schema(Ref::from_schema_name("ErrorResponse"))
+                                .build(),
+                        )
+                        .build();
+                    operation
+                        .responses
+                        .responses
+                        .insert(status.to_string(), response.into());
+                }
+            }
+        }
+    }
+}
+
+pub async fn get_openapi() -> Result<Response, Error> {
+    Ok(Json(ApiDoc::openapi()).into_response())
+}
@@ -1,34 +1,76 @@
-use crate::database::{Db, DbPassword};
-use crate::model::{DataResponse, MessageResponse};
+use crate::database::{Db, DbCursor, DbPassword, DbPasswordHistory};
+use crate::encoding;
+use crate::error::Error;
+use crate::middleware::AuthUser;
+use crate::model::{DataResponse, EntriesFilter, MessageResponse, PageOut};
 use crate::routers::AppState;
+use crate::routes::folders::EntryFolderIn;
 use crate::utils;
 use axum::{
     extract::{
-        rejection::{JsonRejection, PathRejection},
-        Json, Path, State,
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        Json, Path, Query, State,
     },
-    http::HeaderMap,
     response::Response,
 };
 use serde::{Deserialize, Serialize};
 use sqlx::types::Uuid;
-use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, ToSchema)]
 pub struct PasswordIn {
     pub domain_name: String,
     pub username: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     pub password: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     pub nonce: [u8; 12],
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, ToSchema)]
+pub struct PasswordTotpIn {
+    #[serde(default, with = "encoding::bytes")]
+    #[schema(value_type = Option<String>, format = Byte)]
+    pub totp: Option<Vec<u8>>,
+    #[serde(default, with = "encoding::bytes")]
+    #[schema(value_type = Option<String>, format = Byte)]
+    pub totp_nonce: Option<[u8; 12]>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SearchTokensIn {
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = Vec<String>)]
+    pub tokens: Vec<[u8; 32]>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SearchQuery {
+    pub token: String,
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct PasswordOut {
     password_id: String,
     domain_name: String,
     username: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     password: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     nonce: [u8; 12],
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = Option<String>, format = Byte)]
+    totp: Option<Vec<u8>>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = Option<String>, format = Byte)]
+    totp_nonce: Option<[u8; 12]>,
+    folder_id: Option<String>,
+    tag_ids: Vec<String>,
+    updated_at: i64,
 }
 
 impl From<DbPassword> for PasswordOut {
@@ -39,30 +81,66 @@ impl From<DbPassword> for PasswordOut {
             username: dbpassword.username,
             password: dbpassword.password,
             nonce: dbpassword.nonce,
+            totp: dbpassword.totp,
+            totp_nonce: dbpassword.totp_nonce,
+            folder_id: dbpassword.folder_id.map(|folder_id| folder_id.to_string()),
+            tag_ids: dbpassword
+                .tag_ids
+                .into_iter()
+                .map(|tag_id| tag_id.to_string())
+                .collect(),
+            updated_at: dbpassword.updated_at,
         }
     }
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct PasswordHistoryOut {
+    password_id: String,
+    version: i32,
+    domain_name: String,
+    username: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    password: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    nonce: [u8; 12],
+    replaced_at: i64,
+}
+
+impl From<DbPasswordHistory> for PasswordHistoryOut {
+    fn from(dbhistory: DbPasswordHistory) -> Self {
+        Self {
+            password_id: dbhistory.password_id.to_string(),
+            version: dbhistory.version,
+            domain_name: dbhistory.domain_name,
+            username: dbhistory.username,
+            password: dbhistory.password,
+            nonce: dbhistory.nonce,
+            replaced_at: dbhistory.replaced_at,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/passwords",
+    tag = "passwords",
+    request_body = PasswordIn,
+    responses(
+        (status = 201, description = "Password created", body = PasswordOut),
+    ),
+)]
 pub async fn post_passwords(
-    headers: HeaderMap,
+    AuthUser(user_id): AuthUser,
     State(state): State<AppState<'_>>,
     password: Result<Json<PasswordIn>, JsonRejection>,
-) -> Response {
-    let password = match password {
-        Ok(password) => password.0,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
-
-    let user_id = match utils::get_headers_value(&headers, "user_id") {
-        Ok(user_id) => match Uuid::from_str(&user_id) {
-            Ok(user_id) => user_id,
-            Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-        },
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
+) -> Result<Response, Error> {
+    let password = password?.0;
 
     let password_id = utils::create_uuid_v4();
-    match state
+    let dbpassword = state
         .database
         .create_password(
             &password_id,
@@ -72,114 +150,114 @@ pub async fn post_passwords(
             &password.password,
             &password.nonce,
         )
-        .await
-    {
-        Ok(dbpassword) => DataResponse::created(PasswordOut::from(dbpassword)),
-        Err(_) => MessageResponse::bad_request("Failed to add a new password".to_string()),
-    }
+        .await?;
+    Ok(DataResponse::created(PasswordOut::from(dbpassword)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/passwords/{password_id}",
+    tag = "passwords",
+    params(("password_id" = Uuid, Path, description = "Password id")),
+    responses(
+        (status = 200, description = "Password", body = PasswordOut),
+    ),
+)]
 pub async fn get_passwords_id(
-    headers: HeaderMap,
+    AuthUser(user_id): AuthUser,
     State(state): State<AppState<'_>>,
     password_id: Result<Path<Uuid>, PathRejection>,
-) -> Response {
-    let password_id = match password_id {
-        Ok(password_id) => password_id.0,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
-
-    let user_id = match utils::get_headers_value(&headers, "user_id") {
-        Ok(user_id) => match Uuid::from_str(&user_id) {
-            Ok(user_id) => user_id,
-            Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-        },
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
-
-    match state.database.get_password(&user_id, &password_id).await {
-        Ok(dbpassword) => {
-            if dbpassword.user_id == user_id {
-                return DataResponse::ok(PasswordOut::from(dbpassword));
-            } else {
-                return MessageResponse::unauthorized("Unauthorized access".to_string());
-            }
-        }
-        Err(_) => return MessageResponse::bad_request("Failed to get a password".to_string()),
+) -> Result<Response, Error> {
+    let password_id = password_id?.0;
+
+    let dbpassword = state.database.get_password(&user_id, &password_id).await?;
+    if dbpassword.user_id != user_id {
+        return Err(Error::Unauthorized("Unauthorized access".to_string()));
     }
+    Ok(DataResponse::ok(PasswordOut::from(dbpassword)))
 }
 
-pub async fn get_passwords(headers: HeaderMap, State(state): State<AppState<'_>>) -> Response {
-    let user_id = match utils::get_headers_value(&headers, "user_id") {
-        Ok(user_id) => match Uuid::from_str(&user_id) {
-            Ok(user_id) => user_id,
-            Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-        },
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
+#[utoipa::path(
+    get,
+    path = "/v1/passwords",
+    tag = "passwords",
+    params(EntriesFilter),
+    responses(
+        (status = 200, description = "Page of passwords", body = PasswordPageOut),
+    ),
+)]
+pub async fn get_passwords(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    filter: Result<Query<EntriesFilter>, QueryRejection>,
+) -> Result<Response, Error> {
+    let filter = filter?.0;
 
-    let dbpasswords = match state.database.get_passwords(&user_id).await {
-        Ok(dbpasswords) => dbpasswords,
-        Err(_) => return MessageResponse::bad_request("Failed to get passwords".to_string()),
-    };
+    let page = filter.page(&state.config)?;
 
-    DataResponse::ok(
-        dbpasswords
-            .into_iter()
-            .map(|dbpassword| PasswordOut::from(dbpassword))
-            .collect::<Vec<PasswordOut>>(),
-    )
+    let dbpasswords = state
+        .database
+        .get_passwords(
+            &user_id,
+            filter.folder_id.as_ref(),
+            filter.tag_id.as_ref(),
+            &page,
+        )
+        .await?;
+
+    Ok(DataResponse::ok(PageOut::<PasswordOut>::new(
+        dbpasswords,
+        &page,
+        |dbpassword| DbCursor {
+            updated_at: dbpassword.updated_at,
+            id: dbpassword.password_id,
+        },
+    )))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/v1/passwords/{password_id}",
+    tag = "passwords",
+    params(("password_id" = Uuid, Path, description = "Password id")),
+    responses(
+        (status = 200, description = "Password deleted", body = MessageResponse),
+    ),
+)]
 pub async fn delete_passwords_id(
-    headers: HeaderMap,
+    AuthUser(user_id): AuthUser,
     State(state): State<AppState<'_>>,
     password_id: Result<Path<Uuid>, PathRejection>,
-) -> Response {
-    let password_id = match password_id {
-        Ok(password_id) => password_id,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
-
-    let user_id = match utils::get_headers_value(&headers, "user_id") {
-        Ok(user_id) => match Uuid::from_str(&user_id) {
-            Ok(user_id) => user_id,
-            Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-        },
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
+) -> Result<Response, Error> {
+    let password_id = password_id?.0;
 
-    match state.database.delete_password(&user_id, &password_id).await {
-        Ok(_) => MessageResponse::ok("Password deleted".to_string()),
-        Err(_) => MessageResponse::bad_request("Failed to delete a password".to_string()),
-    }
+    state
+        .database
+        .delete_password(&user_id, &password_id)
+        .await?;
+    Ok(MessageResponse::ok("Password deleted".to_string()))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/v1/passwords/{password_id}",
+    tag = "passwords",
+    request_body = PasswordIn,
+    params(("password_id" = Uuid, Path, description = "Password id")),
+    responses(
+        (status = 201, description = "Password updated", body = PasswordOut),
+    ),
+)]
 pub async fn patch_passwords_id(
-    headers: HeaderMap,
+    AuthUser(user_id): AuthUser,
     State(state): State<AppState<'_>>,
     password_id: Result<Path<Uuid>, PathRejection>,
     password: Result<Json<PasswordIn>, JsonRejection>,
-) -> Response {
-    let password = match password {
-        Ok(password) => password.0,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
-
-    let password_id = match password_id {
-        Ok(password_id) => password_id.0,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
-
-    let user_id = match utils::get_headers_value(&headers, "user_id") {
-        Ok(user_id) => match Uuid::from_str(&user_id) {
-            Ok(user_id) => user_id,
-            Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-        },
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
+) -> Result<Response, Error> {
+    let password = password?.0;
+    let password_id = password_id?.0;
 
-    match state
+    let dbpassword = state
         .database
         .patch_password(
             &password_id,
@@ -189,9 +267,223 @@ pub async fn patch_passwords_id(
             &password.password,
             &password.nonce,
         )
-        .await
-    {
-        Ok(dbpassword) => DataResponse::created(PasswordOut::from(dbpassword)),
-        Err(_) => MessageResponse::bad_request("Failed to edit a password".to_string()),
+        .await?;
+    Ok(DataResponse::created(PasswordOut::from(dbpassword)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/passwords/{password_id}/history",
+    tag = "passwords",
+    params(("password_id" = Uuid, Path, description = "Password id")),
+    responses(
+        (status = 200, description = "Previous versions", body = Vec<PasswordHistoryOut>),
+    ),
+)]
+pub async fn get_passwords_id_history(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    password_id: Result<Path<Uuid>, PathRejection>,
+) -> Result<Response, Error> {
+    let password_id = password_id?.0;
+
+    let dbhistory = state
+        .database
+        .get_password_history(&user_id, &password_id)
+        .await?;
+
+    Ok(DataResponse::ok(
+        dbhistory
+            .into_iter()
+            .map(PasswordHistoryOut::from)
+            .collect::<Vec<PasswordHistoryOut>>(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/passwords/{password_id}/history/{version}/restore",
+    tag = "passwords",
+    params(("password_id" = Uuid, Path, description = "Password id"), ("version" = i32, Path, description = "Password version")),
+    responses(
+        (status = 200, description = "Password restored", body = PasswordOut),
+    ),
+)]
+pub async fn post_passwords_id_history_restore(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    path: Result<Path<(Uuid, i32)>, PathRejection>,
+) -> Result<Response, Error> {
+    let (password_id, version) = path?.0;
+
+    let dbpassword = state
+        .database
+        .restore_password(&user_id, &password_id, version)
+        .await?;
+    Ok(DataResponse::ok(PasswordOut::from(dbpassword)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/passwords/{password_id}/folder",
+    tag = "passwords",
+    request_body = EntryFolderIn,
+    params(("password_id" = Uuid, Path, description = "Password id")),
+    responses(
+        (status = 200, description = "Password moved", body = PasswordOut),
+    ),
+)]
+pub async fn put_passwords_id_folder(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    password_id: Result<Path<Uuid>, PathRejection>,
+    folder: Result<Json<EntryFolderIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let folder = folder?.0;
+    let password_id = password_id?.0;
+
+    let dbpassword = state
+        .database
+        .set_password_folder(&user_id, &password_id, folder.folder_id.as_ref())
+        .await?;
+    Ok(DataResponse::ok(PasswordOut::from(dbpassword)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/passwords/{password_id}/totp",
+    tag = "passwords",
+    request_body = PasswordTotpIn,
+    params(("password_id" = Uuid, Path, description = "Password id")),
+    responses(
+        (status = 200, description = "TOTP secret updated", body = PasswordOut),
+    ),
+)]
+pub async fn put_passwords_id_totp(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    password_id: Result<Path<Uuid>, PathRejection>,
+    totp: Result<Json<PasswordTotpIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let totp = totp?.0;
+
+    if totp.totp.is_some() != totp.totp_nonce.is_some() {
+        return Err(Error::Validation(
+            "TOTP secret requires a nonce".to_string(),
+        ));
     }
+
+    let password_id = password_id?.0;
+
+    let dbpassword = state
+        .database
+        .set_password_totp(
+            &user_id,
+            &password_id,
+            totp.totp.as_deref(),
+            totp.totp_nonce.as_ref(),
+        )
+        .await?;
+    Ok(DataResponse::ok(PasswordOut::from(dbpassword)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/passwords/{password_id}/tags/{tag_id}",
+    tag = "passwords",
+    params(("password_id" = Uuid, Path, description = "Password id"), ("tag_id" = Uuid, Path, description = "Tag id")),
+    responses(
+        (status = 200, description = "Tag added", body = PasswordOut),
+    ),
+)]
+pub async fn put_passwords_id_tags_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    path: Result<Path<(Uuid, Uuid)>, PathRejection>,
+) -> Result<Response, Error> {
+    let (password_id, tag_id) = path?.0;
+
+    let dbpassword = state
+        .database
+        .add_password_tag(&user_id, &password_id, &tag_id)
+        .await?;
+    Ok(DataResponse::ok(PasswordOut::from(dbpassword)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/passwords/{password_id}/tags/{tag_id}",
+    tag = "passwords",
+    params(("password_id" = Uuid, Path, description = "Password id"), ("tag_id" = Uuid, Path, description = "Tag id")),
+    responses(
+        (status = 200, description = "Tag removed", body = PasswordOut),
+    ),
+)]
+pub async fn delete_passwords_id_tags_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    path: Result<Path<(Uuid, Uuid)>, PathRejection>,
+) -> Result<Response, Error> {
+    let (password_id, tag_id) = path?.0;
+
+    let dbpassword = state
+        .database
+        .remove_password_tag(&user_id, &password_id, &tag_id)
+        .await?;
+    Ok(DataResponse::ok(PasswordOut::from(dbpassword)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/passwords/{password_id}/search_tokens",
+    tag = "passwords",
+    request_body = SearchTokensIn,
+    params(("password_id" = Uuid, Path, description = "Password id")),
+    responses(
+        (status = 200, description = "Search tokens replaced", body = MessageResponse),
+    ),
+)]
+pub async fn put_passwords_id_search_tokens(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    password_id: Result<Path<Uuid>, PathRejection>,
+    tokens: Result<Json<SearchTokensIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let tokens = tokens?.0;
+    let password_id = password_id?.0;
+
+    state
+        .database
+        .set_password_search_tokens(&user_id, &password_id, &tokens.tokens)
+        .await?;
+    Ok(MessageResponse::ok("Search tokens updated".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/passwords/search",
+    tag = "passwords",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Passwords with the search token", body = Vec<PasswordOut>),
+    ),
+)]
+pub async fn get_passwords_search(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    search: Result<Query<SearchQuery>, QueryRejection>,
+) -> Result<Response, Error> {
+    let search = search?.0;
+
+    let mut token = [0u8; 32];
+    hex::decode_to_slice(&search.token, &mut token)
+        .map_err(|err| Error::BadRequest(err.to_string()))?;
+
+    let dbpasswords = state.database.search_passwords(&user_id, &token).await?;
+    Ok(DataResponse::ok(
+        dbpasswords
+            .into_iter()
+            .map(PasswordOut::from)
+            .collect::<Vec<PasswordOut>>(),
+    ))
 }
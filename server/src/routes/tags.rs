@@ -0,0 +1,160 @@
+use crate::database::{Db, DbTag};
+use crate::encoding;
+use crate::error::Error;
+use crate::middleware::AuthUser;
+use crate::model::{DataResponse, MessageResponse};
+use crate::routers::AppState;
+use crate::utils;
+use axum::{
+    extract::{
+        rejection::{JsonRejection, PathRejection},
+        Json, Path, State,
+    },
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+pub struct TagIn {
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    pub name: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    pub name_nonce: [u8; 12],
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TagOut {
+    tag_id: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    name: Vec<u8>,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    name_nonce: [u8; 12],
+}
+
+impl From<DbTag> for TagOut {
+    fn from(dbtag: DbTag) -> Self {
+        Self {
+            tag_id: dbtag.tag_id.to_string(),
+            name: dbtag.name,
+            name_nonce: dbtag.name_nonce,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/tags",
+    tag = "tags",
+    request_body = TagIn,
+    responses(
+        (status = 201, description = "Tag created", body = TagOut),
+    ),
+)]
+pub async fn post_tags(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    tagin: Result<Json<TagIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let tagin = tagin?.0;
+
+    let tag_id = utils::create_uuid_v4();
+    let dbtag = state
+        .database
+        .create_tag(&tag_id, &user_id, &tagin.name, &tagin.name_nonce)
+        .await?;
+    Ok(DataResponse::created(TagOut::from(dbtag)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/tags",
+    tag = "tags",
+    responses(
+        (status = 200, description = "Tags", body = Vec<TagOut>),
+    ),
+)]
+pub async fn get_tags(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+) -> Result<Response, Error> {
+    let dbtags = state.database.get_tags(&user_id).await?;
+
+    Ok(DataResponse::ok(
+        dbtags
+            .into_iter()
+            .map(TagOut::from)
+            .collect::<Vec<TagOut>>(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/tags/{tag_id}",
+    tag = "tags",
+    params(("tag_id" = Uuid, Path, description = "Tag id")),
+    responses(
+        (status = 200, description = "Tag", body = TagOut),
+    ),
+)]
+pub async fn get_tags_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    tag_id: Result<Path<Uuid>, PathRejection>,
+) -> Result<Response, Error> {
+    let tag_id = tag_id?.0;
+
+    let dbtag = state.database.get_tag(&user_id, &tag_id).await?;
+    Ok(DataResponse::ok(TagOut::from(dbtag)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/tags/{tag_id}",
+    tag = "tags",
+    params(("tag_id" = Uuid, Path, description = "Tag id")),
+    responses(
+        (status = 200, description = "Tag deleted", body = MessageResponse),
+    ),
+)]
+pub async fn delete_tags_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    tag_id: Result<Path<Uuid>, PathRejection>,
+) -> Result<Response, Error> {
+    let tag_id = tag_id?.0;
+
+    state.database.delete_tag(&user_id, &tag_id).await?;
+    Ok(MessageResponse::ok("Tag deleted".to_string()))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v1/tags/{tag_id}",
+    tag = "tags",
+    request_body = TagIn,
+    params(("tag_id" = Uuid, Path, description = "Tag id")),
+    responses(
+        (status = 200, description = "Tag renamed", body = TagOut),
+    ),
+)]
+pub async fn patch_tags_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    tag_id: Result<Path<Uuid>, PathRejection>,
+    tagin: Result<Json<TagIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let tagin = tagin?.0;
+    let tag_id = tag_id?.0;
+
+    let dbtag = state
+        .database
+        .patch_tag(&tag_id, &user_id, &tagin.name, &tagin.name_nonce)
+        .await?;
+    Ok(DataResponse::ok(TagOut::from(dbtag)))
+}
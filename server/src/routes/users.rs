@@ -1,26 +1,118 @@
-use crate::database::{Db, DbUser};
-use crate::model::MessageResponse;
+use crate::database::{Db, DbApiToken, DbApiTokenScope, DbUser, DbWebauthnCredential};
+use crate::encoding;
+use crate::error::Error;
+use crate::middleware::AuthUser;
+use crate::model::{DataResponse, MessageResponse};
+use crate::monitoring;
 use crate::routers::AppState;
 use crate::utils;
 use axum::{
-    extract::{rejection::JsonRejection, Json, State},
-    http::{HeaderMap, StatusCode},
-    response::{IntoResponse, Response},
+    extract::{
+        rejection::{JsonRejection, PathRejection},
+        Json, Path, State,
+    },
+    http::HeaderMap,
+    response::Response,
+};
+use crypto::{
+    Hasher, KdfParams, SrpServer, SrpServerSession, Totp, Webauthn, WebauthnAssertion,
+    WebauthnCredential,
 };
-use crypto::Hasher;
 use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
+use std::sync::OnceLock;
+use utoipa::ToSchema;
+
+const LOGIN_CHALLENGE_LIFETIME: i64 = 5 * 60;
+const SRP_VERIFIER_LENGTH: usize = 256;
+const USERNAME_LENGTH: usize = 16;
+const TOTP_SKEW: u64 = 1;
+const RECOVERY_CODES_COUNT: usize = 10;
+const WEBAUTHN_CHALLENGE_LIFETIME: i64 = 5 * 60;
+const WEBAUTHN_REGISTER: &str = "register";
+const WEBAUTHN_LOGIN: &str = "login";
+const WEBAUTHN_ES256: i32 = -7;
+const API_TOKEN_NAME_LENGTH: usize = 64;
+const ARGON2_DURATION: &str = "argon2_hash_duration_seconds";
+
+static DUMMY_PASSWORD_HASH: OnceLock<String> = OnceLock::new();
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UserIn {
+    pub username: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    pub srp_salt: [u8; 32],
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    pub verifier: Vec<u8>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpgradeIn {
     pub username: String,
     pub password: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    pub srp_salt: [u8; 32],
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    pub verifier: Vec<u8>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct LoginStartIn {
+    pub username: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    pub client_public_key: Vec<u8>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+pub struct LoginStartOut {
+    login_id: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    srp_salt: [u8; 32],
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    server_public_key: Vec<u8>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct LoginIn {
+    pub username: String,
+    pub login_id: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    pub client_proof: Vec<u8>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct PreloginIn {
+    pub username: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PreloginOut {
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
+    salt: [u8; 32],
+    kdf: String,
+    #[schema(value_type = Object)]
+    kdf_params: KdfParams,
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct UserOut {
     user_id: String,
     username: String,
+    #[serde(with = "encoding::bytes")]
+    #[schema(value_type = String, format = Byte)]
     salt: [u8; 32],
+    #[serde(skip_serializing_if = "Option::is_none", with = "encoding::bytes")]
+    #[schema(value_type = Option<String>, format = Byte)]
+    server_proof: Option<[u8; 32]>,
 }
 
 impl From<DbUser> for UserOut {
@@ -29,102 +121,1177 @@ impl From<DbUser> for UserOut {
             user_id: dbuser.user_id.to_string(),
             username: dbuser.username,
             salt: dbuser.salt,
+            server_proof: None,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RefreshIn {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TokensOut {
+    access_token: String,
+    token_type: String,
+    expires_in: i64,
+    refresh_token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SessionOut {
+    #[serde(flatten)]
+    user: UserOut,
+    #[serde(flatten)]
+    tokens: TokensOut,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ApiTokenIn {
+    pub name: String,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub folder_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub entry_ids: Vec<Uuid>,
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiTokenOut {
+    token_id: String,
+    name: String,
+    read_only: bool,
+    folder_ids: Vec<String>,
+    entry_ids: Vec<String>,
+    expires_at: Option<i64>,
+    last_used_at: Option<i64>,
+    created_at: i64,
+    // only returned once, when the token is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+impl From<DbApiToken> for ApiTokenOut {
+    fn from(dbtoken: DbApiToken) -> Self {
+        Self {
+            token_id: dbtoken.token_id.to_string(),
+            name: dbtoken.name,
+            read_only: dbtoken.scope.read_only,
+            folder_ids: dbtoken
+                .scope
+                .folder_ids
+                .iter()
+                .map(Uuid::to_string)
+                .collect(),
+            entry_ids: dbtoken
+                .scope
+                .entry_ids
+                .iter()
+                .map(Uuid::to_string)
+                .collect(),
+            expires_at: dbtoken.expires_at,
+            last_used_at: dbtoken.last_used_at,
+            created_at: dbtoken.created_at,
+            token: None,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SecondFactorIn {
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct LoginSecondFactorIn {
+    pub challenge_id: String,
+    #[serde(flatten)]
+    pub second_factor: SecondFactorIn,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct LoginChallengeOut {
+    challenge_id: String,
+    second_factors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "encoding::bytes")]
+    #[schema(value_type = Option<String>, format = Byte)]
+    server_proof: Option<[u8; 32]>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TotpEnrollmentOut {
+    secret: String,
+    otpauth_uri: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RecoveryCodesOut {
+    recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct WebauthnRegistrationIn {
+    pub name: String,
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct WebauthnLoginStartIn {
+    pub challenge_id: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct WebauthnLoginIn {
+    pub challenge_id: String,
+    pub credential_id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+// The options below keep the WebAuthn field names so clients can pass them to
+// navigator.credentials after decoding the base64url values.
+#[derive(Serialize, ToSchema)]
+pub struct WebauthnRelyingPartyOut {
+    id: String,
+    name: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebauthnUserOut {
+    id: String,
+    name: String,
+    display_name: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct WebauthnParametersOut {
+    #[serde(rename = "type")]
+    credential_type: String,
+    alg: i32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct WebauthnDescriptorOut {
+    #[serde(rename = "type")]
+    credential_type: String,
+    id: String,
+}
+
+impl From<&DbWebauthnCredential> for WebauthnDescriptorOut {
+    fn from(dbcredential: &DbWebauthnCredential) -> Self {
+        Self {
+            credential_type: "public-key".to_string(),
+            id: utils::encode_base64url(&dbcredential.credential_id),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebauthnCreationOut {
+    challenge: String,
+    rp: WebauthnRelyingPartyOut,
+    user: WebauthnUserOut,
+    pub_key_cred_params: Vec<WebauthnParametersOut>,
+    timeout: i64,
+    attestation: String,
+    exclude_credentials: Vec<WebauthnDescriptorOut>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebauthnRequestOut {
+    challenge: String,
+    rp_id: String,
+    allow_credentials: Vec<WebauthnDescriptorOut>,
+    timeout: i64,
+    user_verification: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct WebauthnCredentialOut {
+    credential_id: String,
+    name: String,
+    created_at: i64,
+}
+
+impl From<DbWebauthnCredential> for WebauthnCredentialOut {
+    fn from(dbcredential: DbWebauthnCredential) -> Self {
+        Self {
+            credential_id: utils::encode_base64url(&dbcredential.credential_id),
+            name: dbcredential.name,
+            created_at: dbcredential.created_at,
         }
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/users/register",
+    tag = "users",
+    request_body = UserIn,
+    responses(
+        (status = 201, description = "Account created, also answered for taken usernames", body = MessageResponse),
+        (status = 409, description = "Account created before SRP, it has to be upgraded", body = MessageResponse),
+    ),
+    security(()),
+)]
 pub async fn post_users_register(
     State(state): State<AppState<'_>>,
     user: Result<Json<UserIn>, JsonRejection>,
-) -> Response {
-    let user = match user {
-        Ok(user) => user.0,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
-    };
+) -> Result<Response, Error> {
+    let user = user?.0;
 
-    let hasher = &state.hasher;
-    let hashed_password = match hasher.hash_data(&user.password) {
-        Ok(pwd) => pwd,
-        Err(_) => {
-            return MessageResponse::bad_request("Failed to register a new account".to_string())
+    validate_username(&user.username)?;
+    if user.verifier.len() != SRP_VERIFIER_LENGTH {
+        return Err(Error::Validation("Invalid verifier".to_string()));
+    }
+    // accounts created before SRP keep their username until their owner upgrades them
+    if let Ok(dbuser) = state.database.get_user(&user.username).await {
+        if dbuser.verifier.is_empty() {
+            return Err(Error::Conflict("Account has to be upgraded".to_string()));
         }
-    };
+    }
     let user_id = utils::create_uuid_v4();
     let salt = utils::create_salt();
     let timestamp = utils::get_current_timestamp();
 
+    // a taken username gets the same answer so registration does not reveal existing accounts
     match state
         .database
         .create_user(
             &user_id,
             &user.username,
-            &hashed_password,
+            &user.srp_salt,
+            &user.verifier,
             &salt,
             timestamp,
-            timestamp,
         )
         .await
     {
-        Ok(_) => MessageResponse::created("Account created".to_string()),
-        Err(_) => MessageResponse::bad_request("Failed to register a new account".to_string()),
+        Ok(_) | Err(Error::Conflict(_)) => {
+            Ok(MessageResponse::created("Account created".to_string()))
+        }
+        Err(err) => Err(err),
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/users/upgrade",
+    tag = "users",
+    request_body = UpgradeIn,
+    responses(
+        (status = 200, description = "Account upgraded to SRP", body = MessageResponse),
+    ),
+    security(()),
+)]
+pub async fn post_users_upgrade(
+    State(state): State<AppState<'_>>,
+    upgrade: Result<Json<UpgradeIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let upgrade = upgrade?.0;
+
+    validate_username(&upgrade.username)?;
+    if upgrade.verifier.len() != SRP_VERIFIER_LENGTH {
+        return Err(Error::Validation("Invalid verifier".to_string()));
+    }
+
+    // unknown and already upgraded accounts are compared against a dummy hash so they take as
+    // long to fail as a wrong password
+    let hashed = match state.database.get_legacy_password(&upgrade.username).await {
+        Ok(hashed) => Some(hashed),
+        Err(Error::NotFound) => None,
+        Err(err) => return Err(err),
+    };
+    let dummy_hash = DUMMY_PASSWORD_HASH.get_or_init(|| {
+        state
+            .hasher
+            .hash_data(&utils::create_session_id())
+            .unwrap_or_default()
+    });
+    let matches = monitoring::time(ARGON2_DURATION, || {
+        state
+            .hasher
+            .cmp_data(&upgrade.password, hashed.as_ref().unwrap_or(dummy_hash))
+    });
+    if hashed.is_none() || !matches.unwrap_or(false) {
+        return Err(login_failed());
+    }
+
+    state
+        .database
+        .upgrade_user(&upgrade.username, &upgrade.srp_salt, &upgrade.verifier)
+        .await
+        .map_err(|_| login_failed())?;
+    Ok(MessageResponse::ok("Account upgraded".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/prelogin",
+    tag = "users",
+    request_body = PreloginIn,
+    responses(
+        (status = 200, description = "Key derivation parameters", body = PreloginOut),
+    ),
+    security(()),
+)]
+pub async fn post_users_prelogin(
+    State(state): State<AppState<'_>>,
+    prelogin: Result<Json<PreloginIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let prelogin = prelogin?.0;
+    validate_username(&prelogin.username)?;
+
+    // unknown usernames get a stable fake salt so the answer does not reveal existing accounts
+    let salt = match state.database.get_user(&prelogin.username).await {
+        Ok(dbuser) => dbuser.salt,
+        Err(_) => crypto::fake_salt(
+            state.config.fake_salt_secret.as_bytes(),
+            &prelogin.username.to_lowercase(),
+        ),
+    };
+
+    Ok(DataResponse::ok(PreloginOut {
+        salt,
+        kdf: KdfParams::ALGORITHM.to_string(),
+        kdf_params: KdfParams::default(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/login/start",
+    tag = "users",
+    request_body = LoginStartIn,
+    responses(
+        (status = 200, description = "First SRP step", body = LoginStartOut),
+    ),
+    security(()),
+)]
+pub async fn post_users_login_start(
+    State(state): State<AppState<'_>>,
+    login: Result<Json<LoginStartIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let login = login?.0;
+    validate_username(&login.username)?;
+
+    // unknown usernames go through the same steps with a fake verifier so the answer does not
+    // reveal existing accounts, their login then fails at the proof check
+    let dbuser = state.database.get_user(&login.username).await.ok();
+    let (user_id, srp_salt, verifier) = match dbuser {
+        Some(dbuser) if dbuser.verifier.len() == SRP_VERIFIER_LENGTH => {
+            match <[u8; 32]>::try_from(dbuser.srp_salt) {
+                Ok(srp_salt) => (Some(dbuser.user_id), srp_salt, dbuser.verifier),
+                Err(_) => fake_srp_credentials(&state, &login.username),
+            }
+        }
+        _ => fake_srp_credentials(&state, &login.username),
+    };
+
+    let server = SrpServer::new(&verifier);
+    let session = server
+        .process(&login.username, &srp_salt, &login.client_public_key)
+        .map_err(|_| Error::Validation("Invalid client public key".to_string()))?;
+
+    let login_id = utils::create_session_id();
+    let hashed_login_id = crypto::hash_with_sha3(&login_id);
+    let expires_at = utils::get_current_timestamp() + LOGIN_CHALLENGE_LIFETIME;
+    state
+        .database
+        .create_srp_login(
+            &hashed_login_id,
+            user_id.as_ref(),
+            &login.username,
+            session.client_proof(),
+            session.server_proof(),
+            expires_at,
+        )
+        .await?;
+    Ok(DataResponse::ok(LoginStartOut {
+        login_id,
+        srp_salt,
+        server_public_key: server.public_key(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/login",
+    tag = "users",
+    request_body = LoginIn,
+    responses(
+        (status = 200, description = "Logged in", body = SessionOut),
+        (status = 202, description = "Second factor required", body = LoginChallengeOut),
+    ),
+    security(()),
+)]
 pub async fn post_users_login(
     State(state): State<AppState<'_>>,
-    user: Result<Json<UserIn>, JsonRejection>,
-) -> Response {
-    let user = match user {
-        Ok(user) => user.0,
-        Err(err) => return MessageResponse::bad_request(err.to_string()),
+    login: Result<Json<LoginIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let login = login?.0;
+
+    let hashed_login_id = crypto::hash_with_sha3(&login.login_id);
+    let dblogin = state
+        .database
+        .take_srp_login(&hashed_login_id, utils::get_current_timestamp())
+        .await
+        .map_err(|_| login_failed())?;
+    // the rate limiter counts failures against the username sent here, so it has to match
+    if dblogin.username != login.username.to_lowercase() {
+        return Err(login_failed());
+    }
+
+    let session = match (
+        <[u8; 32]>::try_from(dblogin.client_proof),
+        <[u8; 32]>::try_from(dblogin.server_proof),
+    ) {
+        (Ok(client_proof), Ok(server_proof)) => SrpServerSession::new(client_proof, server_proof),
+        _ => return Err(login_failed()),
+    };
+    let server_proof = session
+        .verify_client(&login.client_proof)
+        .map_err(|_| login_failed())?;
+    let dbuser = match dblogin.user_id {
+        Some(user_id) => state.database.get_user_by_id(&user_id).await?,
+        None => return Err(login_failed()),
+    };
+
+    let mut second_factors = Vec::new();
+    if let Ok(dbtotp) = state.database.get_totp(&dbuser.user_id).await {
+        if dbtotp.enabled {
+            second_factors.push("totp".to_string());
+        }
+    }
+    if let Ok(dbcredentials) = state
+        .database
+        .get_webauthn_credentials(&dbuser.user_id)
+        .await
+    {
+        if !dbcredentials.is_empty() {
+            second_factors.push("webauthn".to_string());
+        }
+    }
+    if !second_factors.is_empty() {
+        return create_login_challenge(&state, &dbuser.user_id, second_factors, Some(server_proof))
+            .await;
+    }
+    create_session(&state, dbuser, Some(server_proof)).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/login/totp",
+    tag = "users",
+    request_body = LoginSecondFactorIn,
+    responses(
+        (status = 200, description = "Logged in", body = SessionOut),
+    ),
+    security(()),
+)]
+pub async fn post_users_login_totp(
+    State(state): State<AppState<'_>>,
+    login: Result<Json<LoginSecondFactorIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let login = login?.0;
+
+    let hashed_challenge_id = crypto::hash_with_sha3(&login.challenge_id);
+    let user_id = state
+        .database
+        .take_login_challenge(&hashed_challenge_id, utils::get_current_timestamp())
+        .await
+        .map_err(|_| invalid_login_challenge())?;
+
+    if !verify_second_factor(&state, &user_id, &login.second_factor).await {
+        return Err(invalid_second_factor());
+    }
+
+    let dbuser = state.database.get_user_by_id(&user_id).await?;
+    create_session(&state, dbuser, None).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/totp/enroll",
+    tag = "users",
+    responses(
+        (status = 201, description = "TOTP secret to confirm", body = TotpEnrollmentOut),
+    ),
+)]
+pub async fn post_users_totp_enroll(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+) -> Result<Response, Error> {
+    let dbuser = state.database.get_user_by_id(&user_id).await?;
+
+    let totp = Totp::generate();
+    state
+        .database
+        .set_totp_secret(&user_id, totp.secret())
+        .await?;
+    Ok(DataResponse::created(TotpEnrollmentOut {
+        secret: totp.secret_base32(),
+        otpauth_uri: totp.otpauth_uri(&state.config.totp_issuer, &dbuser.username),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/totp/confirm",
+    tag = "users",
+    request_body = SecondFactorIn,
+    responses(
+        (status = 200, description = "TOTP enabled", body = RecoveryCodesOut),
+    ),
+)]
+pub async fn post_users_totp_confirm(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    second_factor: Result<Json<SecondFactorIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let second_factor = second_factor?.0;
+
+    let dbtotp = match state.database.get_totp(&user_id).await {
+        Ok(dbtotp) if !dbtotp.enabled => dbtotp,
+        _ => return Err(Error::Conflict("TOTP enrollment not started".to_string())),
     };
 
-    let dbuser = match state.database.get_user(&user.username).await {
-        Ok(dbuser) => dbuser,
-        Err(_) => return MessageResponse::bad_request("Failed to login".to_string()),
+    let code = second_factor.code.unwrap_or_default();
+    let timestamp = utils::get_current_timestamp() as u64;
+    let step = Totp::new(dbtotp.secret)
+        .verify(&code, timestamp, TOTP_SKEW)
+        .ok_or_else(|| Error::Validation("Invalid TOTP code".to_string()))?;
+
+    let mut recovery_codes = Vec::with_capacity(RECOVERY_CODES_COUNT);
+    let mut hashed_recovery_codes = Vec::with_capacity(RECOVERY_CODES_COUNT);
+    for _ in 0..RECOVERY_CODES_COUNT {
+        let recovery_code = utils::create_recovery_code();
+        let hashed = monitoring::time(ARGON2_DURATION, || state.hasher.hash_data(&recovery_code))
+            .map_err(|_| Error::Internal)?;
+        hashed_recovery_codes.push((utils::create_uuid_v4(), hashed));
+        recovery_codes.push(recovery_code);
+    }
+
+    state
+        .database
+        .enable_totp(&user_id, step as i64, &hashed_recovery_codes)
+        .await?;
+    Ok(DataResponse::ok(RecoveryCodesOut { recovery_codes }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/totp/disable",
+    tag = "users",
+    request_body = SecondFactorIn,
+    responses(
+        (status = 200, description = "TOTP disabled", body = MessageResponse),
+    ),
+)]
+pub async fn post_users_totp_disable(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    second_factor: Result<Json<SecondFactorIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let second_factor = second_factor?.0;
+
+    if !verify_second_factor(&state, &user_id, &second_factor).await {
+        return Err(invalid_second_factor());
+    }
+
+    state.database.delete_totp(&user_id).await?;
+    Ok(MessageResponse::ok("TOTP disabled".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/webauthn/register/start",
+    tag = "users",
+    responses(
+        (status = 200, description = "Credential creation options", body = WebauthnCreationOut),
+    ),
+)]
+pub async fn post_users_webauthn_register_start(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+) -> Result<Response, Error> {
+    let dbuser = state.database.get_user_by_id(&user_id).await?;
+    let dbcredentials = state.database.get_webauthn_credentials(&user_id).await?;
+
+    let challenge = Webauthn::create_challenge();
+    let expires_at = utils::get_current_timestamp() + WEBAUTHN_CHALLENGE_LIFETIME;
+    state
+        .database
+        .set_webauthn_challenge(&user_id, WEBAUTHN_REGISTER, &challenge, expires_at)
+        .await?;
+
+    Ok(DataResponse::ok(WebauthnCreationOut {
+        challenge,
+        rp: WebauthnRelyingPartyOut {
+            id: state.config.webauthn_rp_id.clone(),
+            name: state.config.webauthn_rp_id.clone(),
+        },
+        user: WebauthnUserOut {
+            id: utils::encode_base64url(user_id.as_bytes()),
+            name: dbuser.username.clone(),
+            display_name: dbuser.username,
+        },
+        pub_key_cred_params: vec![WebauthnParametersOut {
+            credential_type: "public-key".to_string(),
+            alg: WEBAUTHN_ES256,
+        }],
+        timeout: WEBAUTHN_CHALLENGE_LIFETIME * 1000,
+        attestation: "none".to_string(),
+        exclude_credentials: dbcredentials
+            .iter()
+            .map(WebauthnDescriptorOut::from)
+            .collect(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/webauthn/register/finish",
+    tag = "users",
+    request_body = WebauthnRegistrationIn,
+    responses(
+        (status = 201, description = "Security key registered", body = WebauthnCredentialOut),
+    ),
+)]
+pub async fn post_users_webauthn_register_finish(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    registration: Result<Json<WebauthnRegistrationIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let registration = registration?.0;
+
+    let (client_data_json, attestation_object) = match (
+        utils::decode_base64url(&registration.client_data_json),
+        utils::decode_base64url(&registration.attestation_object),
+    ) {
+        (Ok(client_data_json), Ok(attestation_object)) => (client_data_json, attestation_object),
+        _ => return Err(Error::DecodingError),
     };
 
-    if let Ok(result) = state.hasher.cmp_data(&user.password, &dbuser.password) {
-        if result {
-            let session_id = utils::create_session_id();
-            let hashed_session_id = crypto::hash_with_sha3(&session_id);
-            if let Err(_) = state
-                .database
-                .create_session(&hashed_session_id, &dbuser.user_id)
-                .await
-            {
-                return MessageResponse::bad_request("Failed to create session".to_string());
-            };
+    let challenge = state
+        .database
+        .take_webauthn_challenge(&user_id, WEBAUTHN_REGISTER, utils::get_current_timestamp())
+        .await?;
+
+    let credential = match webauthn(&state).verify_registration(
+        &challenge,
+        &client_data_json,
+        &attestation_object,
+    ) {
+        Ok(credential) => credential,
+        Err(_) => return Err(Error::BadRequest("Invalid registration".to_string())),
+    };
+
+    let dbcredential = state
+        .database
+        .create_webauthn_credential(
+            &user_id,
+            &credential.credential_id,
+            &registration.name,
+            &credential.public_key,
+            credential.sign_count as i64,
+        )
+        .await?;
+    Ok(DataResponse::created(WebauthnCredentialOut::from(
+        dbcredential,
+    )))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/users/webauthn/credentials",
+    tag = "users",
+    responses(
+        (status = 200, description = "Registered security keys", body = Vec<WebauthnCredentialOut>),
+    ),
+)]
+pub async fn get_users_webauthn_credentials(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+) -> Result<Response, Error> {
+    let dbcredentials = state.database.get_webauthn_credentials(&user_id).await?;
+    Ok(DataResponse::ok(
+        dbcredentials
+            .into_iter()
+            .map(WebauthnCredentialOut::from)
+            .collect::<Vec<WebauthnCredentialOut>>(),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/users/webauthn/credentials/{credential_id}",
+    tag = "users",
+    params(("credential_id" = String, Path, description = "Base64url credential id")),
+    responses(
+        (status = 200, description = "Security key deleted", body = MessageResponse),
+    ),
+)]
+pub async fn delete_users_webauthn_credentials_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    credential_id: Result<Path<String>, PathRejection>,
+) -> Result<Response, Error> {
+    let credential_id = utils::decode_base64url(&credential_id?.0)?;
+
+    state
+        .database
+        .delete_webauthn_credential(&user_id, &credential_id)
+        .await?;
+    Ok(MessageResponse::ok("Credential deleted".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/tokens",
+    tag = "users",
+    request_body = ApiTokenIn,
+    responses(
+        (status = 201, description = "Token created, the only time it is returned", body = ApiTokenOut),
+    ),
+)]
+pub async fn post_users_tokens(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    tokenin: Result<Json<ApiTokenIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let tokenin = tokenin?.0;
+    if tokenin.name.is_empty() || tokenin.name.chars().count() > API_TOKEN_NAME_LENGTH {
+        return Err(Error::Validation("Invalid token name".to_string()));
+    }
+    if let Some(expires_at) = tokenin.expires_at {
+        if expires_at <= utils::get_current_timestamp() {
+            return Err(Error::Validation("Expiry date is in the past".to_string()));
+        }
+    }
+
+    let token_id = utils::create_uuid_v4();
+    let token = utils::create_api_token();
+    let hashed_token = crypto::hash_with_sha3(&token);
+    let scope = DbApiTokenScope {
+        read_only: tokenin.read_only,
+        folder_ids: tokenin.folder_ids,
+        entry_ids: tokenin.entry_ids,
+    };
+    let dbtoken = state
+        .database
+        .create_api_token(
+            &token_id,
+            &user_id,
+            &hashed_token,
+            &tokenin.name,
+            &scope,
+            tokenin.expires_at,
+        )
+        .await?;
+    Ok(DataResponse::created(ApiTokenOut {
+        token: Some(token),
+        ..ApiTokenOut::from(dbtoken)
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/users/tokens",
+    tag = "users",
+    responses(
+        (status = 200, description = "Personal access tokens", body = Vec<ApiTokenOut>),
+    ),
+)]
+pub async fn get_users_tokens(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+) -> Result<Response, Error> {
+    let dbtokens = state.database.get_api_tokens(&user_id).await?;
+    Ok(DataResponse::ok(
+        dbtokens
+            .into_iter()
+            .map(ApiTokenOut::from)
+            .collect::<Vec<ApiTokenOut>>(),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/users/tokens/{token_id}",
+    tag = "users",
+    params(("token_id" = Uuid, Path, description = "Personal access token id")),
+    responses(
+        (status = 200, description = "Token revoked", body = MessageResponse),
+    ),
+)]
+pub async fn delete_users_tokens_id(
+    AuthUser(user_id): AuthUser,
+    State(state): State<AppState<'_>>,
+    token_id: Result<Path<Uuid>, PathRejection>,
+) -> Result<Response, Error> {
+    let token_id = token_id?.0;
+
+    state.database.delete_api_token(&user_id, &token_id).await?;
+    Ok(MessageResponse::ok("Token deleted".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/webauthn/login/start",
+    tag = "users",
+    request_body = WebauthnLoginStartIn,
+    responses(
+        (status = 200, description = "Credential request options", body = WebauthnRequestOut),
+    ),
+    security(()),
+)]
+pub async fn post_users_webauthn_login_start(
+    State(state): State<AppState<'_>>,
+    login: Result<Json<WebauthnLoginStartIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let login = login?.0;
+
+    let hashed_challenge_id = crypto::hash_with_sha3(&login.challenge_id);
+    let user_id = state
+        .database
+        .get_login_challenge(&hashed_challenge_id, utils::get_current_timestamp())
+        .await
+        .map_err(|_| invalid_login_challenge())?;
+
+    let dbcredentials = match state.database.get_webauthn_credentials(&user_id).await {
+        Ok(dbcredentials) if !dbcredentials.is_empty() => dbcredentials,
+        _ => return Err(Error::Conflict("No credentials registered".to_string())),
+    };
+
+    let challenge = Webauthn::create_challenge();
+    let expires_at = utils::get_current_timestamp() + WEBAUTHN_CHALLENGE_LIFETIME;
+    state
+        .database
+        .set_webauthn_challenge(&user_id, WEBAUTHN_LOGIN, &challenge, expires_at)
+        .await?;
+
+    Ok(DataResponse::ok(WebauthnRequestOut {
+        challenge,
+        rp_id: state.config.webauthn_rp_id.clone(),
+        allow_credentials: dbcredentials
+            .iter()
+            .map(WebauthnDescriptorOut::from)
+            .collect(),
+        timeout: WEBAUTHN_CHALLENGE_LIFETIME * 1000,
+        user_verification: "discouraged".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/webauthn/login/finish",
+    tag = "users",
+    request_body = WebauthnLoginIn,
+    responses(
+        (status = 200, description = "Logged in", body = SessionOut),
+    ),
+    security(()),
+)]
+pub async fn post_users_webauthn_login_finish(
+    State(state): State<AppState<'_>>,
+    login: Result<Json<WebauthnLoginIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let login = login?.0;
+
+    let (credential_id, client_data_json, authenticator_data, signature) = match (
+        utils::decode_base64url(&login.credential_id),
+        utils::decode_base64url(&login.client_data_json),
+        utils::decode_base64url(&login.authenticator_data),
+        utils::decode_base64url(&login.signature),
+    ) {
+        (Ok(credential_id), Ok(client_data_json), Ok(authenticator_data), Ok(signature)) => (
+            credential_id,
+            client_data_json,
+            authenticator_data,
+            signature,
+        ),
+        _ => return Err(Error::DecodingError),
+    };
+
+    let hashed_challenge_id = crypto::hash_with_sha3(&login.challenge_id);
+    let now = utils::get_current_timestamp();
+    let user_id = state
+        .database
+        .take_login_challenge(&hashed_challenge_id, now)
+        .await
+        .map_err(|_| invalid_login_challenge())?;
+
+    let challenge = state
+        .database
+        .take_webauthn_challenge(&user_id, WEBAUTHN_LOGIN, now)
+        .await
+        .map_err(|_| invalid_second_factor())?;
+
+    let dbcredential = match state.database.get_webauthn_credentials(&user_id).await {
+        Ok(dbcredentials) => dbcredentials
+            .into_iter()
+            .find(|dbcredential| dbcredential.credential_id == credential_id),
+        Err(_) => None,
+    };
+    let dbcredential = dbcredential.ok_or_else(invalid_second_factor)?;
+
+    let credential = WebauthnCredential {
+        credential_id: dbcredential.credential_id,
+        public_key: dbcredential.public_key,
+        sign_count: dbcredential.sign_count as u32,
+    };
+    let assertion = WebauthnAssertion {
+        client_data_json: &client_data_json,
+        authenticator_data: &authenticator_data,
+        signature: &signature,
+    };
+    let sign_count = webauthn(&state)
+        .verify_assertion(&challenge, &credential, &assertion)
+        .map_err(|_| invalid_second_factor())?;
+    state
+        .database
+        .update_webauthn_sign_count(&user_id, &credential.credential_id, sign_count as i64)
+        .await
+        .map_err(|_| invalid_second_factor())?;
+
+    let dbuser = state.database.get_user_by_id(&user_id).await?;
+    create_session(&state, dbuser, None).await
+}
+
+fn login_failed() -> Error {
+    Error::Unauthorized("Failed to login".to_string())
+}
+
+fn invalid_login_challenge() -> Error {
+    Error::Unauthorized("Invalid login challenge".to_string())
+}
+
+fn invalid_second_factor() -> Error {
+    Error::Unauthorized("Invalid second factor".to_string())
+}
+
+fn invalid_refresh_token() -> Error {
+    Error::Unauthorized("Invalid refresh token".to_string())
+}
+
+fn webauthn(state: &AppState<'_>) -> Webauthn {
+    Webauthn::new(
+        state.config.webauthn_rp_id.clone(),
+        state.config.webauthn_origin.clone(),
+    )
+}
+
+fn validate_username(username: &str) -> Result<(), Error> {
+    if username.to_lowercase().chars().count() > USERNAME_LENGTH {
+        return Err(Error::Validation("Invalid username".to_string()));
+    }
+    Ok(())
+}
 
-            let connected_at = utils::get_current_timestamp();
-            state
+fn fake_srp_credentials(state: &AppState<'_>, username: &str) -> (Option<Uuid>, [u8; 32], Vec<u8>) {
+    // usernames are looked up case insensitively, fake answers have to match that
+    let username = username.to_lowercase();
+    let secret = state.config.fake_salt_secret.as_bytes();
+    let srp_salt = crypto::fake_salt(secret, &format!("srp:{username}"));
+    let verifier = crypto::fake_salt(secret, &format!("verifier:{username}"));
+    (None, srp_salt, verifier.to_vec())
+}
+
+async fn create_session(
+    state: &AppState<'_>,
+    dbuser: DbUser,
+    server_proof: Option<[u8; 32]>,
+) -> Result<Response, Error> {
+    // every login starts a new token family, refreshing keeps it and logging out ends it
+    let family_id = utils::create_uuid_v4();
+    let tokens = create_tokens(state, &dbuser.user_id, &family_id).await?;
+
+    let connected_at = utils::get_current_timestamp();
+    if let Err(err) = state
+        .database
+        .update_user_timestamp(&dbuser.user_id, connected_at)
+        .await
+    {
+        tracing::warn!(error = ?err, "failed to update the connection time");
+    }
+
+    Ok(DataResponse::ok(SessionOut {
+        user: UserOut {
+            server_proof,
+            ..UserOut::from(dbuser)
+        },
+        tokens,
+    }))
+}
+
+async fn create_tokens(
+    state: &AppState<'_>,
+    user_id: &Uuid,
+    family_id: &Uuid,
+) -> Result<TokensOut, Error> {
+    let now = utils::get_current_timestamp();
+    let access_token = utils::create_session_id();
+    let hashed_access_token = crypto::hash_with_sha3(&access_token);
+    state
+        .database
+        .create_session(
+            &hashed_access_token,
+            user_id,
+            family_id,
+            now + state.config.access_token_lifetime_seconds,
+        )
+        .await?;
+
+    let refresh_token = utils::create_session_id();
+    let hashed_refresh_token = crypto::hash_with_sha3(&refresh_token);
+    state
+        .database
+        .create_refresh_token(
+            &hashed_refresh_token,
+            user_id,
+            family_id,
+            now + state.config.refresh_token_lifetime_seconds,
+        )
+        .await?;
+
+    Ok(TokensOut {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: state.config.access_token_lifetime_seconds,
+        refresh_token,
+    })
+}
+
+async fn create_login_challenge(
+    state: &AppState<'_>,
+    user_id: &Uuid,
+    second_factors: Vec<String>,
+    server_proof: Option<[u8; 32]>,
+) -> Result<Response, Error> {
+    let challenge_id = utils::create_session_id();
+    let hashed_challenge_id = crypto::hash_with_sha3(&challenge_id);
+    let expires_at = utils::get_current_timestamp() + LOGIN_CHALLENGE_LIFETIME;
+    state
+        .database
+        .create_login_challenge(&hashed_challenge_id, user_id, expires_at)
+        .await?;
+    Ok(DataResponse::accepted(LoginChallengeOut {
+        challenge_id,
+        second_factors,
+        server_proof,
+    }))
+}
+
+async fn verify_second_factor(
+    state: &AppState<'_>,
+    user_id: &Uuid,
+    second_factor: &SecondFactorIn,
+) -> bool {
+    if let Some(code) = &second_factor.code {
+        let dbtotp = match state.database.get_totp(user_id).await {
+            Ok(dbtotp) if dbtotp.enabled => dbtotp,
+            _ => return false,
+        };
+        let timestamp = utils::get_current_timestamp() as u64;
+        return match Totp::new(dbtotp.secret).verify(code, timestamp, TOTP_SKEW) {
+            Some(step) => state
                 .database
-                .update_user_timestamp(&dbuser.user_id, connected_at)
+                .update_totp_step(user_id, step as i64)
                 .await
-                .unwrap_or(());
-
-            return (
-                StatusCode::OK,
-                [
-                    ("Content-Type", "application/json"),
-                    ("session_id", &session_id),
-                ],
-                Json(UserOut::from(dbuser)),
-            )
-                .into_response();
+                .is_ok(),
+            None => false,
+        };
+    }
+
+    if let Some(recovery_code) = &second_factor.recovery_code {
+        let dbcodes = match state.database.get_recovery_codes(user_id).await {
+            Ok(dbcodes) => dbcodes,
+            Err(_) => return false,
+        };
+        let recovery_code = recovery_code.trim().to_lowercase();
+        for dbcode in dbcodes {
+            let matches = monitoring::time(ARGON2_DURATION, || {
+                state.hasher.cmp_data(&recovery_code, &dbcode.code)
+            });
+            if let Ok(true) = matches {
+                return state
+                    .database
+                    .delete_recovery_code(user_id, &dbcode.code_id)
+                    .await
+                    .is_ok();
+            }
         }
     }
-    MessageResponse::bad_request("Failed to login".to_string())
+    false
 }
 
-pub async fn post_users_logout(headers: HeaderMap, State(state): State<AppState<'_>>) -> Response {
-    let session_id = match utils::get_headers_value(&headers, "session_id") {
-        Ok(user_id) => user_id,
-        Err(_) => return MessageResponse::unauthorized("Unauthorized access".to_string()),
-    };
-    let hashed_session_id = crypto::hash_with_sha3(&session_id);
-    match state.database.delete_session(&hashed_session_id).await {
-        Ok(_) => return MessageResponse::ok("Session deleted".to_string()),
-        Err(_) => return MessageResponse::bad_request("Failed to delete session".to_string()),
+#[utoipa::path(
+    post,
+    path = "/v1/users/refresh",
+    tag = "users",
+    request_body = RefreshIn,
+    responses(
+        (status = 200, description = "New token pair", body = TokensOut),
+    ),
+    security(()),
+)]
+pub async fn post_users_refresh(
+    State(state): State<AppState<'_>>,
+    refresh: Result<Json<RefreshIn>, JsonRejection>,
+) -> Result<Response, Error> {
+    let refresh = refresh?.0;
+
+    let hashed_refresh_token = crypto::hash_with_sha3(&refresh.refresh_token);
+    let dbtoken = state
+        .database
+        .use_refresh_token(&hashed_refresh_token)
+        .await
+        .map_err(|_| invalid_refresh_token())?;
+    // a refresh token is only handed out once, seeing it again means it leaked
+    if dbtoken.reused {
+        tracing::warn!(family_id = %dbtoken.family_id, "refresh token reused, revoking its family");
+        if let Err(err) = state
+            .database
+            .revoke_session_family(&dbtoken.family_id)
+            .await
+        {
+            tracing::error!(error = ?err, "failed to revoke a session family");
+        }
+        return Err(invalid_refresh_token());
+    }
+    if dbtoken.expires_at < utils::get_current_timestamp() {
+        return Err(invalid_refresh_token());
     }
+
+    let tokens = create_tokens(&state, &dbtoken.user_id, &dbtoken.family_id).await?;
+    Ok(DataResponse::ok(tokens))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/users/logout",
+    tag = "users",
+    responses(
+        (status = 200, description = "Session deleted", body = MessageResponse),
+    ),
+)]
+pub async fn post_users_logout(
+    headers: HeaderMap,
+    State(state): State<AppState<'_>>,
+) -> Result<Response, Error> {
+    let access_token = utils::get_bearer_token(&headers)
+        .map_err(|_| Error::Unauthorized("Unauthorized access".to_string()))?;
+    let hashed_access_token = crypto::hash_with_sha3(&access_token);
+    state.database.delete_session(&hashed_access_token).await?;
+    Ok(MessageResponse::ok("Session deleted".to_string()))
 }
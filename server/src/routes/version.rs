@@ -0,0 +1,47 @@
+use crate::error::Error;
+use crate::model::DataResponse;
+use crate::versioning::{API_VERSION, SUPPORTED_VERSIONS};
+use axum::response::Response;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+// features clients may rely on, a new one is added whenever the api grows without a new version
+const CAPABILITIES: [&str; 11] = [
+    "srp",
+    "totp",
+    "webauthn",
+    "api_tokens",
+    "refresh_tokens",
+    "search",
+    "password_history",
+    "note_revisions",
+    "folders",
+    "tags",
+    "cbor",
+];
+
+#[derive(Serialize, ToSchema)]
+pub struct VersionOut {
+    api_version: &'static str,
+    supported_versions: Vec<&'static str>,
+    server_version: &'static str,
+    capabilities: Vec<&'static str>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/version",
+    tag = "version",
+    responses(
+        (status = 200, description = "Api versions and capabilities of the server", body = VersionOut),
+    ),
+    security(()),
+)]
+pub async fn get_version() -> Result<Response, Error> {
+    Ok(DataResponse::ok(VersionOut {
+        api_version: API_VERSION,
+        supported_versions: SUPPORTED_VERSIONS.to_vec(),
+        server_version: env!("CARGO_PKG_VERSION"),
+        capabilities: CAPABILITIES.to_vec(),
+    }))
+}
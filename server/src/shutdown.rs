@@ -0,0 +1,118 @@
+use crate::error::Error;
+use crate::routers::AppState;
+use crate::{database::Db, utils};
+use axum::{
+    extract::{Request, State},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use std::{future::IntoFuture, io, net::SocketAddr, time::Duration};
+use tokio::{net::TcpListener, signal, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+/// Cancels `shutdown` on SIGINT or SIGTERM.
+pub async fn cancel_on_signal(shutdown: CancellationToken) {
+    let interrupt = async {
+        signal::ctrl_c().await.expect("Failed to listen for SIGINT");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+    tracing::info!("shutdown requested");
+    shutdown.cancel();
+}
+
+/// Serves `app`, over TLS when `tls` is given, until `shutdown` is cancelled, then stops
+/// accepting connections and gives in-flight requests `drain_timeout` to finish before answering
+/// them with a 503.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    tls: Option<RustlsConfig>,
+    shutdown: CancellationToken,
+    drain_timeout: Duration,
+) -> io::Result<()> {
+    let deadline = CancellationToken::new();
+    let app = app.layer(middleware::from_fn_with_state(
+        deadline.clone(),
+        abort_on_deadline,
+    ));
+    let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let stopping = shutdown.clone();
+    let mut server = match tls {
+        Some(tls) => {
+            let handle = Handle::new();
+            let server = axum_server::from_tcp_rustls(listener.into_std()?, tls)
+                .handle(handle.clone())
+                .serve(make_service);
+            tokio::spawn(async move {
+                stopping.cancelled().await;
+                handle.graceful_shutdown(None);
+            });
+            tokio::spawn(server)
+        }
+        None => tokio::spawn(
+            axum::serve(listener, make_service)
+                .with_graceful_shutdown(async move { stopping.cancelled().await })
+                .into_future(),
+        ),
+    };
+
+    tokio::select! {
+        result = &mut server => return result.expect("Server task panicked"),
+        _ = shutdown.cancelled() => {},
+    }
+    if let Ok(result) = tokio::time::timeout(drain_timeout, &mut server).await {
+        return result.expect("Server task panicked");
+    }
+    tracing::warn!("drain timeout elapsed, aborting in-flight requests");
+    deadline.cancel();
+    server.await.expect("Server task panicked")
+}
+
+async fn abort_on_deadline(
+    State(deadline): State<CancellationToken>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // dropping the handler rolls back its open transaction
+    tokio::select! {
+        response = next.run(request) => response,
+        _ = deadline.cancelled() => Error::ShuttingDown.into_response(),
+    }
+}
+
+/// Periodically deletes expired sessions, tokens and login state until `shutdown` is cancelled,
+/// a purge already running is allowed to finish.
+pub fn spawn_purge(app_state: AppState<'static>, shutdown: CancellationToken) -> JoinHandle<()> {
+    let period = Duration::from_secs(app_state.config.purge_interval_seconds);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {
+                    let now = utils::get_current_timestamp();
+                    let attempts_before = now - app_state.config.login_max_lockout_seconds;
+                    match app_state.database.purge_expired(now, attempts_before).await {
+                        Ok(purged) => tracing::debug!(purged, "purged expired rows"),
+                        Err(err) => tracing::warn!(error = ?err, "failed to purge expired rows"),
+                    }
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,72 @@
+use crate::config::{Config, LogFormat};
+use crate::monitoring::DatabaseMetricsLayer;
+use axum::{
+    body::Body,
+    http::{header, HeaderName, Request},
+    Router,
+};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    sensitive_headers::SetSensitiveRequestHeadersLayer,
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::{Level, Span};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+const DEFAULT_LOG_FILTER: &str = "info";
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Installs the global subscriber, `RUST_LOG` selects what is logged and `LOG_FORMAT` how.
+pub fn init() {
+    let config = Config::from_env();
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    // the filter only applies to the output, database metrics need every span
+    let output = match config.log_format {
+        LogFormat::Pretty => fmt::layer().pretty().with_filter(filter).boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_filter(filter)
+            .boxed(),
+    };
+    // a subscriber may already be installed, e.g. by another test in the same binary
+    let _ = tracing_subscriber::registry()
+        .with(output)
+        .with(DatabaseMetricsLayer)
+        .try_init();
+}
+
+/// Gives every request an id, echoed back to the client, and a span logging its outcome.
+pub fn layer(router: Router) -> Router {
+    router.layer(
+        ServiceBuilder::new()
+            .layer(SetSensitiveRequestHeadersLayer::new([
+                header::AUTHORIZATION,
+                header::COOKIE,
+            ]))
+            .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(request_span)
+                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
+            )
+            .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER)),
+    )
+}
+
+fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    // only the path is recorded, query strings can carry search tokens
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = request.uri().path(),
+        request_id,
+    )
+}
@@ -0,0 +1,68 @@
+use axum::{
+    http::{header, HeaderValue},
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tower_http::set_header::SetResponseHeaderLayer;
+
+/// Loads a PEM certificate chain and private key.
+pub async fn load(cert_path: &Path, key_path: &Path) -> io::Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(cert_path, key_path).await
+}
+
+/// Tells browsers to only use HTTPS for the next `max_age` seconds.
+pub fn layer(router: Router, max_age: u64) -> Router {
+    let value = HeaderValue::from_str(&format!("max-age={max_age}; includeSubDomains"))
+        .expect("Invalid HSTS header");
+    router.layer(SetResponseHeaderLayer::if_not_present(
+        header::STRICT_TRANSPORT_SECURITY,
+        value,
+    ))
+}
+
+/// Reloads the certificate every time one of the files changes until `shutdown` is cancelled,
+/// a pair that fails to load leaves the current certificate in place.
+pub fn spawn_reload(
+    tls: RustlsConfig,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    period: Duration,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut modified = last_modified(&cert_path, &key_path).await;
+        let mut interval = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {
+                    let current = last_modified(&cert_path, &key_path).await;
+                    if current == modified {
+                        continue;
+                    }
+                    match tls.reload_from_pem_file(&cert_path, &key_path).await {
+                        Ok(()) => {
+                            tracing::info!("reloaded tls certificate");
+                            modified = current;
+                        }
+                        // the files may be halfway through being replaced, retried on the next tick
+                        Err(err) => tracing::warn!(error = ?err, "failed to reload tls certificate"),
+                    }
+                }
+            }
+        }
+    })
+}
+
+async fn last_modified(cert_path: &Path, key_path: &Path) -> Option<(SystemTime, SystemTime)> {
+    let cert = tokio::fs::metadata(cert_path).await.ok()?.modified().ok()?;
+    let key = tokio::fs::metadata(key_path).await.ok()?.modified().ok()?;
+    Some((cert, key))
+}
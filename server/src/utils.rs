@@ -13,6 +13,16 @@ pub fn get_headers_value(headers: &HeaderMap, key: &str) -> Result<String, Error
     }
 }
 
+pub fn get_bearer_token(headers: &HeaderMap) -> Result<String, Error> {
+    let value = get_headers_value(headers, "authorization")?;
+    match value.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") && !token.is_empty() => {
+            Ok(token.trim().to_string())
+        }
+        _ => Err(Error::HeadersError),
+    }
+}
+
 pub fn get_current_timestamp() -> i64 {
     chrono::Utc::now().timestamp()
 }
@@ -27,6 +37,12 @@ pub fn create_salt() -> [u8; 32] {
     salt
 }
 
+pub const API_TOKEN_PREFIX: &str = "vault_pat_";
+
+pub fn create_api_token() -> String {
+    format!("{API_TOKEN_PREFIX}{}", create_session_id())
+}
+
 pub fn create_session_id() -> String {
     rand::thread_rng()
         .sample_iter(&rand::distributions::Alphanumeric)
@@ -34,3 +50,21 @@ pub fn create_session_id() -> String {
         .map(char::from)
         .collect()
 }
+
+pub fn encode_base64url(data: &[u8]) -> String {
+    data_encoding::BASE64URL_NOPAD.encode(data)
+}
+
+pub fn decode_base64url(data: &str) -> Result<Vec<u8>, Error> {
+    data_encoding::BASE64URL_NOPAD
+        .decode(data.trim_end_matches('=').as_bytes())
+        .map_err(|_| Error::DecodingError)
+}
+
+pub fn create_recovery_code() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(12)
+        .map(|byte| char::from(byte).to_ascii_lowercase())
+        .collect()
+}
@@ -0,0 +1,67 @@
+use crate::config::Config;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue},
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+
+/// Prefix of the current version of the api.
+pub const API_VERSION: &str = "v1";
+/// Versions the server still answers, oldest first.
+pub const SUPPORTED_VERSIONS: [&str; 1] = [API_VERSION];
+
+pub const DEPRECATION_HEADER: HeaderName = HeaderName::from_static("deprecation");
+pub const SUNSET_HEADER: HeaderName = HeaderName::from_static("sunset");
+// unversioned routes are deprecated since /v1 was introduced, 2026-10-19
+const UNVERSIONED_DEPRECATED_AT: &str = "@1792368000";
+
+/// `path` without the version prefix, for checks that apply to a route whichever way it is
+/// reached.
+pub fn unversioned_path(path: &str) -> &str {
+    match path
+        .strip_prefix('/')
+        .and_then(|path| path.strip_prefix(API_VERSION))
+    {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => path,
+    }
+}
+
+#[derive(Clone)]
+struct Deprecation {
+    sunset: HeaderValue,
+}
+
+/// Serves `router` as the deprecated unversioned aliases of the current version, every response
+/// carries `Deprecation`, `Sunset` and a link to the versioned route.
+pub fn unversioned(router: Router, config: &Config) -> Router {
+    let deprecation = Deprecation {
+        sunset: HeaderValue::from_str(&config.unversioned_api_sunset)
+            .expect("Invalid UNVERSIONED_API_SUNSET value"),
+    };
+    router.layer(middleware::from_fn_with_state(deprecation, mark_deprecated))
+}
+
+async fn mark_deprecated(
+    State(deprecation): State<Deprecation>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let successor = format!(
+        "</{API_VERSION}{}>; rel=\"successor-version\"",
+        request.uri().path()
+    );
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert(
+        DEPRECATION_HEADER,
+        HeaderValue::from_static(UNVERSIONED_DEPRECATED_AT),
+    );
+    headers.insert(SUNSET_HEADER, deprecation.sunset);
+    if let Ok(successor) = HeaderValue::from_str(&successor) {
+        headers.append(header::LINK, successor);
+    }
+    response
+}
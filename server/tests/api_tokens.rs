@@ -0,0 +1,341 @@
+mod common;
+
+use axum::{
+    http::{Method, StatusCode},
+    Router,
+};
+use common::{call, logged_in, login, random_username, register};
+use serde_json::json;
+
+async fn create_password(app: &Router, access_token: &str, folder_id: Option<&str>) -> String {
+    let nonce = [0u8; 12];
+    let (status, password) = call(
+        app,
+        Method::POST,
+        "/passwords",
+        access_token,
+        Some(json!({
+            "domain_name": "example.com",
+            "username": "ci",
+            "password": [1, 2, 3],
+            "nonce": nonce,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let password_id = password["password_id"].as_str().unwrap().to_string();
+
+    if let Some(folder_id) = folder_id {
+        let (status, _) = call(
+            app,
+            Method::PUT,
+            &format!("/passwords/{password_id}/folder"),
+            access_token,
+            Some(json!({ "folder_id": folder_id })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    password_id
+}
+
+async fn create_token(app: &Router, access_token: &str, token: serde_json::Value) -> String {
+    let (status, token) = call(
+        app,
+        Method::POST,
+        "/users/tokens",
+        access_token,
+        Some(token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    token["token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn scoped_api_tokens() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+    let (_, _, session) = login(&app, &username, "password").await;
+    let access_token = session["access_token"].as_str().unwrap();
+
+    let nonce = [0u8; 12];
+    let (status, folder) = call(
+        &app,
+        Method::POST,
+        "/folders",
+        access_token,
+        Some(json!({ "name": [1], "name_nonce": nonce })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let folder_id = folder["folder_id"].as_str().unwrap();
+    let in_folder = create_password(&app, access_token, Some(folder_id)).await;
+    let listed = create_password(&app, access_token, None).await;
+    let other = create_password(&app, access_token, None).await;
+
+    let token = create_token(
+        &app,
+        access_token,
+        json!({
+            "name": "ci",
+            "read_only": true,
+            "folder_ids": [folder_id],
+            "entry_ids": [listed],
+        }),
+    )
+    .await;
+    assert!(token.starts_with("vault_pat_"));
+
+    // entries in scope, directly or through their folder
+    for password_id in [&in_folder, &listed] {
+        let (status, _) = call(
+            &app,
+            Method::GET,
+            &format!("/passwords/{password_id}"),
+            &token,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    // the same scope applies with and without the version prefix
+    for prefix in ["", "/v1"] {
+        let (status, page) = call(
+            &app,
+            Method::GET,
+            &format!("{prefix}/passwords?folder_id={folder_id}"),
+            &token,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{prefix}");
+        assert_eq!(page["entries"].as_array().unwrap().len(), 1);
+        let (status, _) = call(
+            &app,
+            Method::GET,
+            &format!("{prefix}/passwords/{in_folder}"),
+            &token,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{prefix}");
+
+        // everything else is refused
+        for (method, uri) in [
+            (Method::GET, format!("{prefix}/passwords/{other}")),
+            (Method::GET, format!("{prefix}/passwords")),
+            (Method::GET, format!("{prefix}/folders")),
+            (Method::DELETE, format!("{prefix}/passwords/{listed}")),
+            (Method::GET, format!("{prefix}/users/tokens")),
+        ] {
+            let (status, _) = call(&app, method, &uri, &token, None).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{uri}");
+        }
+
+        // tokens cannot mint other tokens
+        let (status, _) = call(
+            &app,
+            Method::POST,
+            &format!("{prefix}/users/tokens"),
+            &token,
+            Some(json!({ "name": "escalated" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{prefix}");
+    }
+
+    let (status, tokens) = call(&app, Method::GET, "/users/tokens", access_token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let listed_token = &tokens[0];
+    assert_eq!(listed_token["name"], "ci");
+    assert!(listed_token["last_used_at"].is_i64());
+    assert!(listed_token.get("token").is_none());
+
+    let token_id = listed_token["token_id"].as_str().unwrap();
+    let (status, _) = call(
+        &app,
+        Method::DELETE,
+        &format!("/users/tokens/{token_id}"),
+        access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &app,
+        Method::GET,
+        &format!("/passwords/{listed}"),
+        &token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn folder_scoped_tokens_only_write_inside_their_folders() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+    let access_token = logged_in(&app).await;
+
+    let nonce = [0u8; 12];
+    let mut folder_ids = Vec::new();
+    for _ in 0..2 {
+        let (status, folder) = call(
+            &app,
+            Method::POST,
+            "/folders",
+            &access_token,
+            Some(json!({ "name": [1], "name_nonce": nonce })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        folder_ids.push(folder["folder_id"].as_str().unwrap().to_string());
+    }
+    let (scoped, outside) = (&folder_ids[0], &folder_ids[1]);
+    let password_id = create_password(&app, &access_token, Some(scoped)).await;
+    let token = create_token(
+        &app,
+        &access_token,
+        json!({ "name": "ci", "folder_ids": [scoped] }),
+    )
+    .await;
+
+    for prefix in ["", "/v1"] {
+        // new entries start outside any folder, whatever the query says
+        for (uri, body) in [
+            (
+                format!("{prefix}/passwords?folder_id={scoped}"),
+                json!({
+                    "domain_name": "example.com",
+                    "username": "ci",
+                    "password": [1],
+                    "nonce": nonce,
+                }),
+            ),
+            (
+                format!("{prefix}/notes?folder_id={scoped}"),
+                json!({
+                    "title": [1],
+                    "title_nonce": nonce,
+                    "content": [1],
+                    "content_nonce": nonce,
+                }),
+            ),
+        ] {
+            let (status, _) = call(&app, Method::POST, &uri, &token, Some(body)).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{uri}");
+        }
+
+        // entries cannot be moved out of scope
+        let uri = format!("{prefix}/passwords/{password_id}/folder");
+        for folder_id in [json!(outside), json!(null)] {
+            let (status, _) = call(
+                &app,
+                Method::PUT,
+                &uri,
+                &token,
+                Some(json!({ "folder_id": folder_id })),
+            )
+            .await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{uri}");
+        }
+        let (status, password) = call(
+            &app,
+            Method::PUT,
+            &uri,
+            &token,
+            Some(json!({ "folder_id": scoped })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{uri}");
+        assert_eq!(password["folder_id"], scoped.as_str());
+    }
+
+    let (_, page) = call(&app, Method::GET, "/passwords", &access_token, None).await;
+    let entries = page["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["folder_id"], scoped.as_str());
+    let (_, page) = call(&app, Method::GET, "/notes", &access_token, None).await;
+    assert!(page["entries"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn unscoped_and_expired_api_tokens() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+    let (_, _, session) = login(&app, &username, "password").await;
+    let access_token = session["access_token"].as_str().unwrap();
+
+    let token = create_token(&app, access_token, json!({ "name": "deploy" })).await;
+    let password_id = create_password(&app, &token, None).await;
+    let (status, _) = call(
+        &app,
+        Method::DELETE,
+        &format!("/passwords/{password_id}"),
+        &token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = call(
+        &app,
+        Method::POST,
+        "/users/tokens",
+        access_token,
+        Some(json!({ "name": "old", "expires_at": 1 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let token = create_token(
+        &app,
+        access_token,
+        json!({ "name": "short", "expires_at": now + 1 }),
+    )
+    .await;
+    let (status, _) = call(&app, Method::GET, "/tags", &token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    // an expiring token cannot replace itself with one that never expires
+    for uri in ["/users/tokens", "/v1/users/tokens"] {
+        let (status, _) = call(
+            &app,
+            Method::POST,
+            uri,
+            &token,
+            Some(json!({ "name": "forever" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{uri}");
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(2100)).await;
+    let (status, _) = call(&app, Method::GET, "/tags", &token, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,167 @@
+// each test binary only uses some of the helpers
+#![allow(dead_code)]
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use crypto::{SrpClient, SrpClientSession};
+use http_body_util::BodyExt;
+use rand::Rng;
+use serde_json::json;
+use std::net::{Ipv4Addr, SocketAddr};
+use tower::ServiceExt;
+
+pub fn random_username() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect()
+}
+
+// requests come from an address of their own so the per address limits stay out of the way
+pub fn random_address() -> SocketAddr {
+    let [a, b, c] = rand::thread_rng().gen::<[u8; 3]>();
+    SocketAddr::from((Ipv4Addr::new(10, a, b, c), 443))
+}
+
+pub async fn request(
+    app: &Router,
+    uri: &str,
+    access_token: Option<&str>,
+    body: serde_json::Value,
+) -> (StatusCode, Option<String>, serde_json::Value) {
+    request_from(app, random_address(), uri, access_token, body).await
+}
+
+pub async fn request_from(
+    app: &Router,
+    address: SocketAddr,
+    uri: &str,
+    access_token: Option<&str>,
+    body: serde_json::Value,
+) -> (StatusCode, Option<String>, serde_json::Value) {
+    let mut builder = Request::post(uri)
+        .header("content-type", "application/json")
+        .extension(ConnectInfo(address));
+    if let Some(access_token) = access_token {
+        builder = builder.header("authorization", format!("Bearer {access_token}"));
+    }
+    let response = app
+        .clone()
+        .oneshot(builder.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+    let access_token = body["access_token"].as_str().map(str::to_string);
+    (status, access_token, body)
+}
+
+/// Sends any request with a bearer token, returning the status and the JSON body.
+pub async fn call(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    access_token: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .header("authorization", format!("Bearer {access_token}"));
+    let body = match body {
+        Some(body) => Body::from(body.to_string()),
+        None => Body::empty(),
+    };
+    let response = app
+        .clone()
+        .oneshot(builder.body(body).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+pub async fn register(app: &Router, username: &str, password: &str) -> StatusCode {
+    let srp_salt = crypto::srp_salt();
+    let verifier = crypto::srp_verifier(password, &srp_salt).unwrap();
+    let (status, _, _) = request(
+        app,
+        "/users/register",
+        None,
+        json!({ "username": username, "srp_salt": srp_salt, "verifier": verifier }),
+    )
+    .await;
+    status
+}
+
+/// Runs the first SRP step, returning the login id and the client side of the exchange.
+pub async fn login_start(
+    app: &Router,
+    username: &str,
+    password: &str,
+) -> (String, SrpClientSession) {
+    let client = SrpClient::new();
+    let (status, _, start) = request(
+        app,
+        "/users/login/start",
+        None,
+        json!({ "username": username, "client_public_key": client.public_key() }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let srp_salt: [u8; 32] = serde_json::from_value(start["srp_salt"].clone()).unwrap();
+    let server_public_key: Vec<u8> =
+        serde_json::from_value(start["server_public_key"].clone()).unwrap();
+    let session = client
+        .process(username, password, &srp_salt, &server_public_key)
+        .unwrap();
+    (start["login_id"].as_str().unwrap().to_string(), session)
+}
+
+pub async fn login(
+    app: &Router,
+    username: &str,
+    password: &str,
+) -> (StatusCode, Option<String>, serde_json::Value) {
+    let (login_id, session) = login_start(app, username, password).await;
+    let (status, access_token, body) = request(
+        app,
+        "/users/login",
+        None,
+        json!({
+            "username": username,
+            "login_id": login_id,
+            "client_proof": session.client_proof(),
+        }),
+    )
+    .await;
+
+    // a successful answer has to prove the server holds the verifier
+    if status.is_success() {
+        let server_proof: Vec<u8> = serde_json::from_value(body["server_proof"].clone()).unwrap();
+        session.verify_server(&server_proof).unwrap();
+    }
+    (status, access_token, body)
+}
+
+/// Registers a fresh account and logs it in, returning its access token.
+pub async fn logged_in(app: &Router) -> String {
+    let username = random_username();
+    assert_eq!(
+        register(app, &username, "password").await,
+        StatusCode::CREATED
+    );
+    let (_, access_token, _) = login(app, &username, "password").await;
+    access_token.unwrap()
+}
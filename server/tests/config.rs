@@ -0,0 +1,7 @@
+#[tokio::test]
+#[should_panic(expected = "Invalid PASSWORD_HISTORY_DEPTH value")]
+async fn negative_password_history_depth_is_rejected() {
+    // this binary only holds this test, so the setting does not leak into others
+    std::env::set_var("PASSWORD_HISTORY_DEPTH", "-1");
+    server::routers::AppState::build().await;
+}
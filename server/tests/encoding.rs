@@ -0,0 +1,133 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use common::{call, login, random_username, request};
+use data_encoding::BASE64;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn get(app: &Router, uri: &str, access_token: &str, accept: &str) -> (String, Vec<u8>) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get(uri)
+                .header("authorization", format!("Bearer {access_token}"))
+                .header("accept", accept)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let content_type = response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (content_type, body.to_vec())
+}
+
+#[tokio::test]
+async fn bytes_are_negotiated() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    // base64 is accepted wherever arrays of numbers are
+    let username = random_username();
+    let srp_salt = crypto::srp_salt();
+    let verifier = crypto::srp_verifier("password", &srp_salt).unwrap();
+    let (status, _, _) = request(
+        &app,
+        "/users/register",
+        None,
+        json!({
+            "username": username,
+            "srp_salt": BASE64.encode(&srp_salt),
+            "verifier": BASE64.encode(&verifier),
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, access_token, _) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+    let access_token = access_token.unwrap();
+
+    let content = b"ciphertext".to_vec();
+    let nonce = [3u8; 12];
+    let (status, body) = call(
+        &app,
+        Method::POST,
+        "/notes",
+        &access_token,
+        Some(json!({
+            "title": BASE64.encode(b"title"),
+            "title_nonce": BASE64.encode(&nonce),
+            "content": BASE64.encode(&content),
+            "content_nonce": nonce,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let uri = format!("/notes/{}", body["note_id"].as_str().unwrap());
+
+    // clients predating base64 keep getting arrays
+    let (content_type, body) = get(&app, &uri, &access_token, "application/json").await;
+    assert_eq!(content_type, "application/json");
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["content"], json!(content));
+    assert_eq!(body["title_nonce"], json!(nonce));
+
+    let (content_type, body) = get(
+        &app,
+        &uri,
+        &access_token,
+        "application/json; encoding=base64",
+    )
+    .await;
+    assert_eq!(content_type, "application/json");
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["content"], BASE64.encode(&content));
+    assert_eq!(body["content_nonce"], BASE64.encode(&nonce));
+
+    let (content_type, body) = get(&app, &uri, &access_token, "application/cbor").await;
+    assert_eq!(content_type, "application/cbor");
+    let body: ciborium::Value = ciborium::from_reader(body.as_slice()).unwrap();
+    let field = |name: &str| {
+        body.as_map()
+            .unwrap()
+            .iter()
+            .find(|(key, _)| key.as_text() == Some(name))
+            .map(|(_, value)| value.clone())
+            .unwrap()
+    };
+    assert_eq!(field("content"), ciborium::Value::Bytes(content));
+    assert_eq!(
+        field("content_nonce"),
+        ciborium::Value::Bytes(nonce.to_vec())
+    );
+
+    // a wrong length is a validation error like any mistyped field
+    let (status, body) = call(
+        &app,
+        Method::POST,
+        "/notes",
+        &access_token,
+        Some(json!({
+            "title": "",
+            "title_nonce": BASE64.encode(&[0u8; 11]),
+            "content": "",
+            "content_nonce": nonce,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "validation_failed");
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{call, login, random_username, register};
+use serde_json::json;
+
+#[tokio::test]
+async fn errors_have_status_and_code() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+    let (_, _, session) = login(&app, &username, "password").await;
+    let access_token = session["access_token"].as_str().unwrap();
+
+    let missing = format!("/passwords/{}", uuid::Uuid::new_v4());
+    let (status, body) = call(&app, Method::GET, &missing, access_token, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "not_found");
+
+    let (status, body) = call(&app, Method::GET, "/passwords/1", access_token, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "bad_request");
+
+    let (status, body) = call(
+        &app,
+        Method::POST,
+        "/tags",
+        access_token,
+        Some(json!({ "name": "not bytes" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "validation_failed");
+
+    let (status, body) = call(
+        &app,
+        Method::POST,
+        "/users/totp/confirm",
+        access_token,
+        Some(json!({ "code": "000000" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "conflict");
+    assert!(body["message"].is_string());
+
+    let (status, body) = call(&app, Method::GET, "/tags", "unknown", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "unauthorized");
+}
@@ -0,0 +1,304 @@
+mod common;
+
+use axum::{
+    http::{Method, StatusCode},
+    Router,
+};
+use common::{call, logged_in};
+use serde_json::json;
+
+const NONCE: [u8; 12] = [0; 12];
+
+async fn create_folder(app: &Router, access_token: &str, parent_id: Option<&str>) -> String {
+    let (status, folder) = call(
+        app,
+        Method::POST,
+        "/folders",
+        access_token,
+        Some(json!({ "parent_id": parent_id, "name": [1], "name_nonce": NONCE })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(folder["parent_id"].as_str(), parent_id);
+    folder["folder_id"].as_str().unwrap().to_string()
+}
+
+async fn create_tag(app: &Router, access_token: &str) -> String {
+    let (status, tag) = call(
+        app,
+        Method::POST,
+        "/tags",
+        access_token,
+        Some(json!({ "name": [1], "name_nonce": NONCE })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    tag["tag_id"].as_str().unwrap().to_string()
+}
+
+async fn create_password(app: &Router, access_token: &str) -> String {
+    let (status, password) = call(
+        app,
+        Method::POST,
+        "/passwords",
+        access_token,
+        Some(json!({
+            "domain_name": "example.com",
+            "username": "alice",
+            "password": [1, 2, 3],
+            "nonce": NONCE,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    password["password_id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn folder_crud_and_cycles() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+    let access_token = logged_in(&app).await;
+
+    let root = create_folder(&app, &access_token, None).await;
+    let child = create_folder(&app, &access_token, Some(&root)).await;
+    let grandchild = create_folder(&app, &access_token, Some(&child)).await;
+
+    let (status, folders) = call(&app, Method::GET, "/folders", &access_token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(folders.as_array().unwrap().len(), 3);
+
+    // renaming keeps the folder where it is
+    let (status, folder) = call(
+        &app,
+        Method::PATCH,
+        &format!("/folders/{child}"),
+        &access_token,
+        Some(json!({ "parent_id": root, "name": [2], "name_nonce": NONCE })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(folder["parent_id"], root.as_str());
+    let (_, folder) = call(
+        &app,
+        Method::GET,
+        &format!("/folders/{child}"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(folder["name"], json!([2]));
+
+    // a folder cannot end up inside itself or one of its descendants
+    for parent_id in [&root, &child, &grandchild] {
+        let (status, _) = call(
+            &app,
+            Method::PATCH,
+            &format!("/folders/{root}"),
+            &access_token,
+            Some(json!({ "parent_id": parent_id, "name": [1], "name_nonce": NONCE })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // moving a branch elsewhere is fine
+    let (status, folder) = call(
+        &app,
+        Method::PATCH,
+        &format!("/folders/{grandchild}"),
+        &access_token,
+        Some(json!({ "parent_id": null, "name": [1], "name_nonce": NONCE })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(folder["parent_id"].is_null());
+
+    // deleting a folder takes its subfolders along and leaves its entries without a folder
+    let password_id = create_password(&app, &access_token).await;
+    let (status, _) = call(
+        &app,
+        Method::PUT,
+        &format!("/passwords/{password_id}/folder"),
+        &access_token,
+        Some(json!({ "folder_id": child })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &app,
+        Method::DELETE,
+        &format!("/folders/{root}"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &app,
+        Method::GET,
+        &format!("/folders/{child}"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, password) = call(
+        &app,
+        Method::GET,
+        &format!("/passwords/{password_id}"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert!(password["folder_id"].is_null());
+    let (_, folders) = call(&app, Method::GET, "/folders", &access_token, None).await;
+    assert_eq!(folders[0]["folder_id"], grandchild.as_str());
+}
+
+#[tokio::test]
+async fn tag_crud() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+    let access_token = logged_in(&app).await;
+
+    let tag_id = create_tag(&app, &access_token).await;
+    let (status, tag) = call(
+        &app,
+        Method::PATCH,
+        &format!("/tags/{tag_id}"),
+        &access_token,
+        Some(json!({ "name": [2], "name_nonce": NONCE })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tag["name"], json!([2]));
+    let (status, tags) = call(&app, Method::GET, "/tags", &access_token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tags.as_array().unwrap().len(), 1);
+
+    let password_id = create_password(&app, &access_token).await;
+    let (status, password) = call(
+        &app,
+        Method::PUT,
+        &format!("/passwords/{password_id}/tags/{tag_id}"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(password["tag_ids"], json!([tag_id]));
+
+    // deleting a tag detaches it from its entries
+    let (status, _) = call(
+        &app,
+        Method::DELETE,
+        &format!("/tags/{tag_id}"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &app,
+        Method::GET,
+        &format!("/tags/{tag_id}"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, password) = call(
+        &app,
+        Method::GET,
+        &format!("/passwords/{password_id}"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(password["tag_ids"], json!([]));
+}
+
+#[tokio::test]
+async fn folders_and_tags_of_other_users_cannot_be_used() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+    let owner = logged_in(&app).await;
+    let other = logged_in(&app).await;
+
+    let folder_id = create_folder(&app, &owner, None).await;
+    let tag_id = create_tag(&app, &owner).await;
+    let password_id = create_password(&app, &other).await;
+    let (status, note) = call(
+        &app,
+        Method::POST,
+        "/notes",
+        &other,
+        Some(json!({
+            "title": [1],
+            "title_nonce": NONCE,
+            "content": [1],
+            "content_nonce": NONCE,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let note_id = note["note_id"].as_str().unwrap();
+
+    for uri in [
+        format!("/passwords/{password_id}/tags/{tag_id}"),
+        format!("/notes/{note_id}/tags/{tag_id}"),
+    ] {
+        let (status, _) = call(&app, Method::PUT, &uri, &other, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+    for uri in [
+        format!("/passwords/{password_id}/folder"),
+        format!("/notes/{note_id}/folder"),
+    ] {
+        let (status, _) = call(
+            &app,
+            Method::PUT,
+            &uri,
+            &other,
+            Some(json!({ "folder_id": folder_id })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    // nor can they be read, nested into or changed
+    for uri in [format!("/folders/{folder_id}"), format!("/tags/{tag_id}")] {
+        let (status, _) = call(&app, Method::GET, &uri, &other, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+    let (status, _) = call(
+        &app,
+        Method::POST,
+        "/folders",
+        &other,
+        Some(json!({ "parent_id": folder_id, "name": [1], "name_nonce": NONCE })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call(
+        &app,
+        Method::PATCH,
+        &format!("/tags/{tag_id}"),
+        &other,
+        Some(json!({ "name": [2], "name_nonce": NONCE })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, tags) = call(&app, Method::GET, "/tags", &owner, None).await;
+    assert_eq!(tags[0]["name"], json!([1]));
+}
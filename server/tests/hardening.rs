@@ -0,0 +1,146 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+};
+use common::{call, login, random_username, register};
+use serde_json::json;
+use server::{routers, shutdown};
+use std::time::Duration;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::sync::CancellationToken;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn body_limits_and_security_headers() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = routers::app().await;
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+    let (_, access_token, _) = login(&app, &username, "password").await;
+    let access_token = access_token.unwrap();
+
+    // a note may be larger than any other body
+    let nonce = [0u8; 12];
+    let large = vec![7u8; 300 * 1024];
+    let (status, body) = call(
+        &app,
+        Method::POST,
+        "/passwords",
+        &access_token,
+        Some(json!({
+            "domain_name": "example.com",
+            "username": "user",
+            "password": large,
+            "nonce": nonce,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(body["code"], "payload_too_large");
+    let (status, _) = call(
+        &app,
+        Method::POST,
+        "/notes",
+        &access_token,
+        Some(json!({
+            "title": [1],
+            "title_nonce": nonce,
+            "content": large,
+            "content_nonce": nonce,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let response = app
+        .oneshot(Request::get("/health/live").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let headers = response.headers();
+    assert_eq!(headers["x-content-type-options"], "nosniff");
+    assert_eq!(headers["x-frame-options"], "DENY");
+    assert_eq!(headers["cache-control"], "no-store");
+    assert!(headers.contains_key("content-security-policy"));
+}
+
+#[tokio::test]
+async fn cors_only_allows_listed_origins() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let mut app_state = routers::AppState::build().await;
+    app_state.config.cors_allowed_origins = vec!["https://vault.example".to_string()];
+    let app = routers::api(app_state);
+
+    for (origin, allowed) in [
+        ("https://vault.example", true),
+        ("https://evil.example", false),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::options("/passwords")
+                    .header("origin", origin)
+                    .header("access-control-request-method", "POST")
+                    .header("access-control-request-headers", "authorization")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let allow_origin = response.headers().get("access-control-allow-origin");
+        assert_eq!(allow_origin.is_some_and(|value| value == origin), allowed);
+    }
+}
+
+#[tokio::test]
+async fn slow_requests_time_out() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let mut app_state = routers::AppState::build().await;
+    app_state.config.request_timeout_seconds = 1;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let shutdown_token = CancellationToken::new();
+    let server = tokio::spawn(shutdown::serve(
+        listener,
+        routers::api(app_state),
+        None,
+        shutdown_token.clone(),
+        Duration::from_secs(1),
+    ));
+
+    // the body never arrives in full
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(
+            b"POST /users/register HTTP/1.1\r\nhost: localhost\r\ncontent-type: application/json\r\ncontent-length: 100\r\n\r\n{\"username\"",
+        )
+        .await
+        .unwrap();
+    let mut response = vec![0; 1024];
+    let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    let response = String::from_utf8_lossy(&response[..read]).to_string();
+    assert!(response.starts_with("HTTP/1.1 408"), "{response}");
+    assert!(response.contains("request_timeout"), "{response}");
+
+    shutdown_token.cancel();
+    server.await.unwrap().unwrap();
+}
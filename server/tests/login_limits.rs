@@ -0,0 +1,243 @@
+mod common;
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    Router,
+};
+use common::{login_start, random_address, random_username, register, request_from};
+use serde_json::json;
+use server::routers;
+use sqlx::postgres::PgPool;
+use std::net::SocketAddr;
+use tower::ServiceExt;
+
+const BACKOFF_SECONDS: i64 = 30;
+
+async fn limited_app() -> Router {
+    limited_app_with(2, 100, 100).await
+}
+
+async fn limited_app_with(
+    user_max_failures: i32,
+    ip_max_failures: i32,
+    start_ip_max_attempts: i32,
+) -> Router {
+    let mut app_state = routers::AppState::build().await;
+    app_state.config.login_user_max_failures = user_max_failures;
+    app_state.config.login_ip_max_failures = ip_max_failures;
+    app_state.config.login_start_ip_max_attempts = start_ip_max_attempts;
+    app_state.config.login_backoff_seconds = BACKOFF_SECONDS;
+    app_state.config.login_max_lockout_seconds = 60 * 60;
+    routers::api(app_state)
+}
+
+/// Runs both SRP steps, returning the status of the second one and its `Retry-After` header.
+async fn login_from(
+    app: &Router,
+    address: SocketAddr,
+    username: &str,
+    password: &str,
+) -> (StatusCode, Option<i64>) {
+    let (login_id, session) = login_start(app, username, password).await;
+    let body = json!({
+        "username": username,
+        "login_id": login_id,
+        "client_proof": session.client_proof(),
+    });
+    let request = Request::post("/users/login")
+        .header("content-type", "application/json")
+        .extension(ConnectInfo(address))
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let retry_after = response
+        .headers()
+        .get("retry-after")
+        .and_then(|value| value.to_str().ok()?.parse().ok());
+    (response.status(), retry_after)
+}
+
+async fn unlock(username: &str) {
+    let pool = PgPool::connect(&std::env::var("DATABASE_URL").unwrap())
+        .await
+        .unwrap();
+    sqlx::query("UPDATE login_attempts SET locked_until = 0 WHERE attempt_key = $1;")
+        .bind(format!("user:{}", username.to_lowercase()))
+        .execute(&pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn failed_logins_lock_the_username_out() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = limited_app().await;
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+
+    let address = random_address();
+    for _ in 0..2 {
+        let (status, retry_after) = login_from(&app, address, &username, "wrong").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(retry_after, None);
+    }
+
+    // the right password is refused too, under any spelling of the username
+    for username in [username.clone(), username.to_uppercase()] {
+        let (status, retry_after) = login_from(&app, random_address(), &username, "password").await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        let retry_after = retry_after.unwrap();
+        assert!(retry_after > 0 && retry_after <= BACKOFF_SECONDS);
+    }
+}
+
+#[tokio::test]
+async fn lockouts_double_with_every_further_failure() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = limited_app().await;
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+
+    let address = random_address();
+    for _ in 0..2 {
+        login_from(&app, address, &username, "wrong").await;
+    }
+    for lockout in [BACKOFF_SECONDS * 2, BACKOFF_SECONDS * 4] {
+        // the lockout ends but the failures are still counted
+        unlock(&username).await;
+        let (status, _) = login_from(&app, address, &username, "wrong").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, retry_after) = login_from(&app, address, &username, "password").await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        let retry_after = retry_after.unwrap();
+        assert!(retry_after > lockout / 2 && retry_after <= lockout);
+    }
+}
+
+#[tokio::test]
+async fn successful_logins_reset_the_failures() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = limited_app().await;
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+
+    let address = random_address();
+    let (status, _) = login_from(&app, address, &username, "wrong").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = login_from(&app, address, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // one more failure starts a new count instead of reaching the limit
+    let (status, _) = login_from(&app, address, &username, "wrong").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = login_from(&app, address, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn failed_logins_lock_the_address_out() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = limited_app_with(100, 3, 100).await;
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+
+    // guesses spread over many usernames still add up for the address they come from
+    let address = random_address();
+    for _ in 0..3 {
+        let (status, _) = login_from(&app, address, &random_username(), "wrong").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, retry_after) = login_from(&app, address, &username, "password").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert!(retry_after.unwrap() <= BACKOFF_SECONDS);
+
+    // other addresses are not affected
+    let (status, _) = login_from(&app, random_address(), &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn successful_logins_keep_the_address_failures() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = limited_app_with(100, 3, 100).await;
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+
+    let address = random_address();
+    for _ in 0..2 {
+        let (status, _) = login_from(&app, address, &random_username(), "wrong").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) = login_from(&app, address, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // logging into one's own account in between does not buy further guesses
+    let (status, _) = login_from(&app, address, &random_username(), "wrong").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = login_from(&app, address, &username, "password").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn login_starts_are_throttled_per_address() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = limited_app_with(100, 100, 3).await;
+    let client_public_key = crypto::SrpClient::new().public_key();
+    let start = |address| {
+        request_from(
+            &app,
+            address,
+            "/users/login/start",
+            None,
+            json!({ "username": random_username(), "client_public_key": client_public_key }),
+        )
+    };
+
+    // known or not, every username costs the server the same work, so they all count
+    let address = random_address();
+    for _ in 0..3 {
+        let (status, _, _) = start(address).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _, _) = start(address).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    let (status, _, _) = start(random_address()).await;
+    assert_eq!(status, StatusCode::OK);
+}
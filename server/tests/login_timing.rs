@@ -0,0 +1,84 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use common::{login, random_username, register};
+use crypto::SrpClient;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+
+const SAMPLES: usize = 5;
+
+async fn post(app: &Router, uri: &str, body: serde_json::Value) -> (StatusCode, Duration) {
+    let request = Request::post(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let start = Instant::now();
+    let response = app.clone().oneshot(request).await.unwrap();
+    (response.status(), start.elapsed())
+}
+
+fn median(mut durations: Vec<Duration>) -> Duration {
+    durations.sort();
+    durations[durations.len() / 2]
+}
+
+#[tokio::test]
+async fn login_does_not_reveal_existing_usernames() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    // only the handler is measured here, the rate limiter would answer early
+    std::env::set_var("LOGIN_USER_MAX_FAILURES", "1000");
+    std::env::set_var("LOGIN_IP_MAX_FAILURES", "1000");
+    let app = server::routers::app().await;
+
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+
+    let client_public_key = SrpClient::new().public_key();
+    let mut existing = Vec::new();
+    let mut unknown = Vec::new();
+    for _ in 0..SAMPLES {
+        let (status, elapsed) = post(
+            &app,
+            "/users/login/start",
+            json!({ "username": username, "client_public_key": client_public_key }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        existing.push(elapsed);
+
+        let (status, elapsed) = post(
+            &app,
+            "/users/login/start",
+            json!({ "username": random_username(), "client_public_key": client_public_key }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        unknown.push(elapsed);
+    }
+
+    let (existing, unknown) = (median(existing), median(unknown));
+    let ratio = existing.as_secs_f64() / unknown.as_secs_f64();
+    assert!(
+        (0.5..2.0).contains(&ratio),
+        "existing user took {existing:?}, unknown user took {unknown:?}"
+    );
+
+    let (status, _, _) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::{login, random_username, register};
+use http_body_util::BodyExt;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn metrics_are_served_on_the_admin_router() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    server::telemetry::init();
+    let app = server::routers::app().await;
+    let admin = server::routers::admin().await;
+
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+    let (_, _, session) = login(&app, &username, "password").await;
+    let access_token = session["access_token"].as_str().unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/tags")
+                .header("authorization", format!("Bearer {access_token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // the api does not expose metrics
+    let response = app
+        .clone()
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = admin
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let metrics = String::from_utf8(body.to_vec()).unwrap();
+    for expected in [
+        r#"http_requests_total{method="GET",route="/tags",status="200"}"#,
+        r#"http_request_duration_seconds_bucket{method="POST",route="/users/login""#,
+        r#"db_query_duration_seconds_bucket{query="validate_session""#,
+        r#"db_pool_connections{state="idle"}"#,
+        "active_sessions",
+    ] {
+        assert!(metrics.contains(expected), "missing {expected}");
+    }
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{login, random_username, register, request};
+use crypto::Hasher;
+use serde_json::json;
+use sqlx::{postgres::PgPool, Executor};
+use std::borrow::Cow;
+
+// the first migration run against a database that still had password hashes
+const SRP_MIGRATION: i64 = 11;
+
+#[tokio::test]
+async fn accounts_from_before_srp_are_upgraded_once() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+
+    // a database of its own, left at the schema that predates SRP
+    let database = format!("vault_upgrade_{}", random_username().to_lowercase());
+    let admin = PgPool::connect(&url).await.unwrap();
+    admin
+        .execute(format!("CREATE DATABASE {database};").as_str())
+        .await
+        .unwrap();
+    let (base, _) = url.rsplit_once('/').unwrap();
+    let upgrade_url = format!("{base}/{database}");
+
+    let pool = PgPool::connect(&upgrade_url).await.unwrap();
+    let mut migrator = sqlx::migrate!("./migrations");
+    migrator.migrations = Cow::Owned(
+        migrator
+            .iter()
+            .filter(|migration| migration.version < SRP_MIGRATION)
+            .cloned()
+            .collect(),
+    );
+    migrator.run(&pool).await.unwrap();
+
+    let username = random_username();
+    let user_id = uuid::Uuid::new_v4();
+    let salt = [7u8; 32];
+    sqlx::query(
+        "
+        INSERT INTO users (user_id, username, password, salt, created_at, connected_at)
+        VALUES ($1, $2, $3, $4, 0, 0);
+        ",
+    )
+    .bind(user_id)
+    .bind(username.to_lowercase())
+    .bind(
+        crypto::Argon2Hasher::new()
+            .hash_data(&"legacy password".to_string())
+            .unwrap(),
+    )
+    .bind(salt.as_slice())
+    .execute(&pool)
+    .await
+    .unwrap();
+    pool.close().await;
+
+    // this binary only holds this test, so pointing the app at the upgraded database is safe
+    std::env::set_var("DATABASE_URL", &upgrade_url);
+    let app = server::routers::app().await;
+
+    // the migrated account has no verifier yet, so it cannot log in
+    let (status, _, _) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // nor can it be taken over by registering its username again
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CONFLICT
+    );
+    let (status, _, _) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let pool = PgPool::connect(&upgrade_url).await.unwrap();
+    let (verifier, password): (Vec<u8>, Option<String>) =
+        sqlx::query_as("SELECT verifier, password FROM users WHERE user_id = $1;")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(verifier.is_empty());
+    assert!(password.is_some());
+
+    // upgrading takes the old password
+    let upgrade = |password: &str| {
+        let srp_salt = crypto::srp_salt();
+        let verifier = crypto::srp_verifier("password", &srp_salt).unwrap();
+        json!({
+            "username": username,
+            "password": password,
+            "srp_salt": srp_salt,
+            "verifier": verifier,
+        })
+    };
+    let (status, _, _) = request(&app, "/users/upgrade", None, upgrade("wrong password")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _, _) = request(&app, "/users/upgrade", None, upgrade("legacy password")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, access_token, user) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(access_token.is_some());
+    assert_eq!(user["user_id"], user_id.to_string());
+
+    // the key derivation salt is kept so the vault still decrypts
+    let (status, _, prelogin) = request(
+        &app,
+        "/users/prelogin",
+        None,
+        json!({ "username": username }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let prelogin_salt: [u8; 32] = serde_json::from_value(prelogin["salt"].clone()).unwrap();
+    assert_eq!(prelogin_salt, salt);
+
+    // the old hash is gone, so the upgrade cannot be replayed
+    let (status, _, _) = request(&app, "/users/upgrade", None, upgrade("legacy password")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let password: Option<String> =
+        sqlx::query_scalar("SELECT password FROM users WHERE user_id = $1;")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(password.is_none());
+    pool.close().await;
+
+    // once upgraded the account is not replaced by a later registration
+    assert_eq!(
+        register(&app, &username, "other password").await,
+        StatusCode::CREATED
+    );
+    let (status, _, _) = login(&app, &username, "other password").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _, _) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+
+    drop(app);
+    admin
+        .execute(format!("DROP DATABASE {database} WITH (FORCE);").as_str())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use axum::{
+    http::{Method, StatusCode},
+    Router,
+};
+use common::{call, logged_in};
+use serde_json::json;
+
+const KEPT_REVISIONS: usize = 2;
+
+async fn revisions_app() -> Router {
+    // every test of this binary builds its app the same way, so they agree on the policy
+    std::env::set_var("NOTE_REVISION_POLICY", format!("last:{KEPT_REVISIONS}"));
+    server::routers::app().await
+}
+
+fn note(revision: u8) -> serde_json::Value {
+    json!({
+        "title": [revision],
+        "title_nonce": vec![revision; 12],
+        "content": [revision, revision],
+        "content_nonce": vec![revision; 12],
+    })
+}
+
+async fn revisions(app: &Router, access_token: &str, note_id: &str) -> Vec<serde_json::Value> {
+    let (status, revisions) = call(
+        app,
+        Method::GET,
+        &format!("/notes/{note_id}/revisions"),
+        access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    revisions.as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn last_revisions_are_kept_and_restored() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = revisions_app().await;
+    let access_token = logged_in(&app).await;
+
+    let (status, created) = call(&app, Method::POST, "/notes", &access_token, Some(note(0))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let note_id = created["note_id"].as_str().unwrap();
+    for revision in 1..=4 {
+        let (status, _) = call(
+            &app,
+            Method::PATCH,
+            &format!("/notes/{note_id}"),
+            &access_token,
+            Some(note(revision)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    // only the latest revisions are kept, newest first
+    let kept = revisions(&app, &access_token, note_id).await;
+    assert_eq!(kept.len(), KEPT_REVISIONS);
+    assert_eq!(kept[0]["revision"], 4);
+    assert_eq!(kept[0]["content"], note(3)["content"]);
+    assert_eq!(kept[1]["revision"], 3);
+
+    // restoring brings the old content back as a new revision
+    let (status, restored) = call(
+        &app,
+        Method::POST,
+        &format!("/notes/{note_id}/revisions/3/restore"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(restored["title"], note(2)["title"]);
+    assert_eq!(restored["content"], note(2)["content"]);
+    assert_eq!(restored["content_nonce"], note(2)["content_nonce"]);
+    let kept = revisions(&app, &access_token, note_id).await;
+    assert_eq!(kept[0]["revision"], 5);
+    assert_eq!(kept[0]["content"], note(4)["content"]);
+
+    // pruned revisions are gone
+    let (status, _) = call(
+        &app,
+        Method::POST,
+        &format!("/notes/{note_id}/revisions/1/restore"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn revisions_of_other_users_are_not_found() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = revisions_app().await;
+    let owner = logged_in(&app).await;
+    let other = logged_in(&app).await;
+
+    let (_, created) = call(&app, Method::POST, "/notes", &owner, Some(note(0))).await;
+    let note_id = created["note_id"].as_str().unwrap();
+    call(
+        &app,
+        Method::PATCH,
+        &format!("/notes/{note_id}"),
+        &owner,
+        Some(note(1)),
+    )
+    .await;
+
+    let (status, _) = call(
+        &app,
+        Method::GET,
+        &format!("/notes/{note_id}/revisions"),
+        &other,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call(
+        &app,
+        Method::POST,
+        &format!("/notes/{note_id}/revisions/1/restore"),
+        &other,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(revisions(&app, &owner, note_id).await.len(), 1);
+}
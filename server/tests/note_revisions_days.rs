@@ -0,0 +1,76 @@
+mod common;
+
+use axum::{
+    http::{Method, StatusCode},
+    Router,
+};
+use common::{call, logged_in};
+use serde_json::json;
+use sqlx::postgres::PgPool;
+
+const KEPT_DAYS: i64 = 1;
+
+fn note(revision: u8) -> serde_json::Value {
+    json!({
+        "title": [revision],
+        "title_nonce": vec![revision; 12],
+        "content": [revision, revision],
+        "content_nonce": vec![revision; 12],
+    })
+}
+
+async fn patch(app: &Router, access_token: &str, note_id: &str, revision: u8) -> StatusCode {
+    let uri = format!("/notes/{note_id}");
+    let (status, _) = call(app, Method::PATCH, &uri, access_token, Some(note(revision))).await;
+    status
+}
+
+#[tokio::test]
+async fn revisions_are_kept_for_some_days() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+    // this binary only holds this test, so the policy does not leak into others
+    std::env::set_var("NOTE_REVISION_POLICY", format!("days:{KEPT_DAYS}"));
+    let app = server::routers::app().await;
+    let access_token = logged_in(&app).await;
+
+    let (_, created) = call(&app, Method::POST, "/notes", &access_token, Some(note(0))).await;
+    let note_id = created["note_id"].as_str().unwrap();
+    for revision in 1..=3 {
+        assert_eq!(
+            patch(&app, &access_token, note_id, revision).await,
+            StatusCode::CREATED
+        );
+    }
+
+    // recent revisions are all kept, however many there are
+    let uri = format!("/notes/{note_id}/revisions");
+    let (_, kept) = call(&app, Method::GET, &uri, &access_token, None).await;
+    assert_eq!(kept.as_array().unwrap().len(), 3);
+
+    // the first revision was replaced longer ago than the policy keeps
+    let pool = PgPool::connect(&url).await.unwrap();
+    sqlx::query(
+        "UPDATE note_revisions SET replaced_at = replaced_at - $1 WHERE note_id = $2::UUID AND revision = 1;",
+    )
+    .bind((KEPT_DAYS + 1) * 24 * 60 * 60)
+    .bind(note_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    assert_eq!(
+        patch(&app, &access_token, note_id, 4).await,
+        StatusCode::CREATED
+    );
+    let (_, kept) = call(&app, Method::GET, &uri, &access_token, None).await;
+    let kept: Vec<_> = kept
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|revision| revision["revision"].as_i64().unwrap())
+        .collect();
+    assert_eq!(kept, vec![4, 3, 2]);
+}
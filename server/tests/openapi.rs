@@ -0,0 +1,127 @@
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+};
+use http_body_util::BodyExt;
+use regex_lite::Regex;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use tower::ServiceExt;
+
+const ROUTERS: &str = include_str!("../src/routers.rs");
+// not part of the api it describes
+const UNDOCUMENTED: [&str; 1] = ["/v1/openapi.json"];
+
+/// Method and path of every route served by `routers::api`, read from the source by following
+/// the nested routers.
+fn routes() -> BTreeSet<(String, String)> {
+    let mut routes = BTreeSet::new();
+    collect_routes("api", "", &mut routes);
+    routes
+}
+
+fn collect_routes(router: &str, prefix: &str, routes: &mut BTreeSet<(String, String)>) {
+    let nest = Regex::new(r#"\.nest\(\s*"([^"]+)",\s*(\w+)\("#).unwrap();
+    let route = Regex::new(r#"\.route\(\s*"([^"]+)",\s*(get|post|put|patch|delete)\("#).unwrap();
+    let param = Regex::new(r":(\w+)").unwrap();
+    let start = ROUTERS
+        .find(&format!("pub fn {router}("))
+        .expect("nested router not found");
+    let end = ROUTERS[start + 1..]
+        .find("\npub ")
+        .map_or(ROUTERS.len(), |end| start + 1 + end);
+    let body = &ROUTERS[start..end];
+    for nested in nest.captures_iter(body) {
+        collect_routes(&nested[2], &format!("{prefix}{}", &nested[1]), routes);
+    }
+    for routed in route.captures_iter(body) {
+        let path = match &routed[1] {
+            "/" => prefix.to_string(),
+            path => format!("{prefix}{path}"),
+        };
+        let path = param.replace_all(&path, "{$1}").to_string();
+        routes.insert((routed[2].to_string(), path));
+    }
+}
+
+fn operations(spec: &Value) -> BTreeSet<(String, String)> {
+    let mut operations = BTreeSet::new();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in item.as_object().unwrap().keys() {
+            operations.insert((method.clone(), path.clone()));
+        }
+    }
+    operations
+}
+
+#[tokio::test]
+async fn spec_matches_routes() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/v1/openapi.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let spec: Value = serde_json::from_slice(&body).unwrap();
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+    assert_eq!(
+        spec["components"]["schemas"]["NoteIn"]["properties"]["content"]["format"],
+        "byte"
+    );
+
+    let routes = routes()
+        .into_iter()
+        .filter(|(_, path)| !UNDOCUMENTED.contains(&path.as_str()))
+        .collect::<BTreeSet<_>>();
+    let operations = operations(&spec);
+    assert!(!routes.is_empty());
+    let undocumented = routes.difference(&operations).collect::<Vec<_>>();
+    let unrouted = operations.difference(&routes).collect::<Vec<_>>();
+    assert!(
+        undocumented.is_empty(),
+        "routes missing from the spec: {undocumented:?}"
+    );
+    assert!(
+        unrouted.is_empty(),
+        "spec operations without a route: {unrouted:?}"
+    );
+
+    // the router agrees, an unknown route answers 404 without a body and a wrong method 405
+    let param = Regex::new(r"\{(\w+)\}").unwrap();
+    for (method, path) in &operations {
+        let uri = param.replace_all(path, |captures: &regex_lite::Captures| match &captures[1] {
+            "version" | "revision" => "1".to_string(),
+            "credential_id" => "AA".to_string(),
+            _ => uuid::Uuid::nil().to_string(),
+        });
+        let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method.clone())
+                    .uri(uri.as_ref())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_ne!(status, StatusCode::METHOD_NOT_ALLOWED, "{method} {path}");
+        assert!(
+            status != StatusCode::NOT_FOUND || !body.is_empty(),
+            "{method} {path} is not routed"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use axum::{
+    http::{Method, StatusCode},
+    Router,
+};
+use common::{call, logged_in};
+use serde_json::json;
+use std::collections::HashSet;
+
+const MAX_PAGE_LIMIT: usize = 3;
+const PASSWORDS: usize = 7;
+
+async fn pagination_app() -> Router {
+    // every test of this binary builds its app the same way, so they agree on the limit
+    std::env::set_var("MAX_PAGE_LIMIT", MAX_PAGE_LIMIT.to_string());
+    server::routers::app().await
+}
+
+async fn create_passwords(app: &Router, access_token: &str) -> Vec<String> {
+    let mut password_ids = Vec::new();
+    for _ in 0..PASSWORDS {
+        let (status, password) = call(
+            app,
+            Method::POST,
+            "/passwords",
+            access_token,
+            Some(json!({
+                "domain_name": "example.com",
+                "username": "alice",
+                "password": [1],
+                "nonce": [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        password_ids.push(password["password_id"].as_str().unwrap().to_string());
+    }
+    password_ids
+}
+
+/// Walks every page of `query` from `after` on, returning the ids of each page.
+async fn pages(
+    app: &Router,
+    access_token: &str,
+    query: &str,
+    mut after: Option<String>,
+) -> Vec<Vec<String>> {
+    let mut pages = Vec::new();
+    loop {
+        let uri = match &after {
+            Some(after) => format!("/passwords?{query}&after={after}"),
+            None => format!("/passwords?{query}"),
+        };
+        let (status, page) = call(app, Method::GET, &uri, access_token, None).await;
+        assert_eq!(status, StatusCode::OK);
+        pages.push(
+            page["entries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["password_id"].as_str().unwrap().to_string())
+                .collect(),
+        );
+        match page["next_cursor"].as_str() {
+            Some(next_cursor) => after = Some(next_cursor.to_string()),
+            None => return pages,
+        }
+    }
+}
+
+#[tokio::test]
+async fn cursors_walk_every_entry_once() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = pagination_app().await;
+    let access_token = logged_in(&app).await;
+    let mut password_ids = create_passwords(&app, &access_token).await;
+    password_ids.sort();
+
+    let by_id = pages(&app, &access_token, "limit=3", None).await;
+    assert_eq!(
+        by_id.iter().map(Vec::len).collect::<Vec<_>>(),
+        vec![3, 3, 1]
+    );
+    assert_eq!(by_id.concat(), password_ids);
+
+    // the same cursor keeps answering the same page
+    let (_, first) = call(&app, Method::GET, "/passwords?limit=3", &access_token, None).await;
+    let uri = format!(
+        "/passwords?limit=3&after={}",
+        first["next_cursor"].as_str().unwrap()
+    );
+    let (_, second) = call(&app, Method::GET, &uri, &access_token, None).await;
+    let (_, again) = call(&app, Method::GET, &uri, &access_token, None).await;
+    assert_eq!(second, again);
+
+    // entries updated while paging move ahead of the cursor instead of showing up twice
+    let query = "limit=3&order=updated_at";
+    let (_, first) = call(
+        &app,
+        Method::GET,
+        &format!("/passwords?{query}"),
+        &access_token,
+        None,
+    )
+    .await;
+    let seen = first["entries"][0]["password_id"].as_str().unwrap();
+    let (status, _) = call(
+        &app,
+        Method::PATCH,
+        &format!("/passwords/{seen}"),
+        &access_token,
+        Some(json!({
+            "domain_name": "example.com",
+            "username": "bob",
+            "password": [2],
+            "nonce": [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let rest = pages(
+        &app,
+        &access_token,
+        query,
+        first["next_cursor"].as_str().map(str::to_string),
+    )
+    .await
+    .concat();
+    let first: HashSet<_> = first["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["password_id"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(rest.len(), PASSWORDS - MAX_PAGE_LIMIT);
+    assert!(rest.iter().all(|password_id| !first.contains(password_id)));
+}
+
+#[tokio::test]
+async fn limits_are_clamped() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = pagination_app().await;
+    let access_token = logged_in(&app).await;
+    create_passwords(&app, &access_token).await;
+
+    for (limit, entries) in [(1000, MAX_PAGE_LIMIT), (0, 1), (-5, 1)] {
+        let (status, page) = call(
+            &app,
+            Method::GET,
+            &format!("/passwords?limit={limit}"),
+            &access_token,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["entries"].as_array().unwrap().len(), entries);
+        assert!(page["next_cursor"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn invalid_cursors_are_bad_requests() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = pagination_app().await;
+    let access_token = logged_in(&app).await;
+    let password_id = uuid::Uuid::new_v4();
+
+    for query in [
+        "after=garbage".to_string(),
+        "after=".to_string(),
+        format!("after=yesterday_{password_id}"),
+        "after=1700000000_garbage".to_string(),
+        "limit=many".to_string(),
+        "order=random".to_string(),
+    ] {
+        for resource in ["passwords", "notes"] {
+            let (status, _) = call(
+                &app,
+                Method::GET,
+                &format!("/{resource}?{query}"),
+                &access_token,
+                None,
+            )
+            .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{resource}?{query}");
+        }
+    }
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use axum::{
+    http::{Method, StatusCode},
+    Router,
+};
+use common::{call, logged_in};
+use serde_json::json;
+
+const HISTORY_DEPTH: usize = 2;
+
+async fn history_app() -> Router {
+    // every test of this binary builds its app the same way, so they agree on the depth
+    std::env::set_var("PASSWORD_HISTORY_DEPTH", HISTORY_DEPTH.to_string());
+    server::routers::app().await
+}
+
+fn password(version: u8) -> serde_json::Value {
+    json!({
+        "domain_name": "example.com",
+        "username": "alice",
+        "password": [version],
+        "nonce": vec![version; 12],
+    })
+}
+
+#[tokio::test]
+async fn updates_keep_a_bounded_history() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = history_app().await;
+    let access_token = logged_in(&app).await;
+
+    let (status, created) = call(
+        &app,
+        Method::POST,
+        "/passwords",
+        &access_token,
+        Some(password(0)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let password_id = created["password_id"].as_str().unwrap();
+
+    for version in 1..=4 {
+        let (status, _) = call(
+            &app,
+            Method::PATCH,
+            &format!("/passwords/{password_id}"),
+            &access_token,
+            Some(password(version)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    // only the latest versions are kept, newest first
+    let (status, history) = call(
+        &app,
+        Method::GET,
+        &format!("/passwords/{password_id}/history"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let history = history.as_array().unwrap();
+    assert_eq!(history.len(), HISTORY_DEPTH);
+    assert_eq!(history[0]["version"], 4);
+    assert_eq!(history[0]["password"], password(3)["password"]);
+    assert_eq!(history[1]["version"], 3);
+    assert_eq!(history[1]["password"], password(2)["password"]);
+
+    // restoring brings the old version back and keeps the replaced one in the history
+    let (status, restored) = call(
+        &app,
+        Method::POST,
+        &format!("/passwords/{password_id}/history/3/restore"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(restored["password"], password(2)["password"]);
+    assert_eq!(restored["nonce"], password(2)["nonce"]);
+
+    let (_, history) = call(
+        &app,
+        Method::GET,
+        &format!("/passwords/{password_id}/history"),
+        &access_token,
+        None,
+    )
+    .await;
+    let history = history.as_array().unwrap();
+    assert_eq!(history.len(), HISTORY_DEPTH);
+    assert_eq!(history[0]["version"], 5);
+    assert_eq!(history[0]["password"], password(4)["password"]);
+
+    // pruned versions are gone
+    let (status, _) = call(
+        &app,
+        Method::POST,
+        &format!("/passwords/{password_id}/history/1/restore"),
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn history_of_other_users_is_not_found() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = history_app().await;
+    let owner = logged_in(&app).await;
+    let other = logged_in(&app).await;
+
+    let (_, created) = call(&app, Method::POST, "/passwords", &owner, Some(password(0))).await;
+    let password_id = created["password_id"].as_str().unwrap();
+    let (status, _) = call(
+        &app,
+        Method::PATCH,
+        &format!("/passwords/{password_id}"),
+        &owner,
+        Some(password(1)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, _) = call(
+        &app,
+        Method::GET,
+        &format!("/passwords/{password_id}/history"),
+        &other,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call(
+        &app,
+        Method::POST,
+        &format!("/passwords/{password_id}/history/1/restore"),
+        &other,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // the owner's entry is untouched
+    let (_, current) = call(
+        &app,
+        Method::GET,
+        &format!("/passwords/{password_id}"),
+        &owner,
+        None,
+    )
+    .await;
+    assert_eq!(current["password"], password(1)["password"]);
+}
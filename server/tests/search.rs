@@ -0,0 +1,146 @@
+mod common;
+
+use axum::{
+    http::{Method, StatusCode},
+    Router,
+};
+use common::{call, logged_in};
+use serde_json::json;
+
+async fn create_password(app: &Router, access_token: &str) -> String {
+    let (status, password) = call(
+        app,
+        Method::POST,
+        "/passwords",
+        access_token,
+        Some(json!({
+            "domain_name": "example.com",
+            "username": "alice",
+            "password": [1],
+            "nonce": [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    password["password_id"].as_str().unwrap().to_string()
+}
+
+async fn set_tokens(
+    app: &Router,
+    access_token: &str,
+    password_id: &str,
+    tokens: &[u8],
+) -> StatusCode {
+    let tokens: Vec<_> = tokens.iter().map(|token| vec![*token; 32]).collect();
+    let (status, _) = call(
+        app,
+        Method::PUT,
+        &format!("/passwords/{password_id}/search_tokens"),
+        access_token,
+        Some(json!({ "tokens": tokens })),
+    )
+    .await;
+    status
+}
+
+/// Ids of the passwords found with the token made of `token` repeated.
+async fn search(app: &Router, access_token: &str, token: u8) -> Vec<String> {
+    let (status, found) = call(
+        app,
+        Method::GET,
+        &format!("/passwords/search?token={}", hex::encode([token; 32])),
+        access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    found
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|password| password["password_id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn search_tokens_are_replaced() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+    let access_token = logged_in(&app).await;
+    let password_id = create_password(&app, &access_token).await;
+
+    assert_eq!(
+        set_tokens(&app, &access_token, &password_id, &[1, 2]).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        search(&app, &access_token, 1).await,
+        vec![password_id.clone()]
+    );
+    assert_eq!(
+        search(&app, &access_token, 2).await,
+        vec![password_id.clone()]
+    );
+
+    // a new set of tokens drops the previous one
+    assert_eq!(
+        set_tokens(&app, &access_token, &password_id, &[3]).await,
+        StatusCode::OK
+    );
+    assert!(search(&app, &access_token, 1).await.is_empty());
+    assert!(search(&app, &access_token, 2).await.is_empty());
+    assert_eq!(
+        search(&app, &access_token, 3).await,
+        vec![password_id.clone()]
+    );
+
+    assert_eq!(
+        set_tokens(&app, &access_token, &password_id, &[]).await,
+        StatusCode::OK
+    );
+    assert!(search(&app, &access_token, 3).await.is_empty());
+
+    let (status, _) = call(
+        &app,
+        Method::GET,
+        "/passwords/search?token=not-hex",
+        &access_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn search_is_scoped_to_the_caller() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+    let owner = logged_in(&app).await;
+    let other = logged_in(&app).await;
+    let owned = create_password(&app, &owner).await;
+    let others = create_password(&app, &other).await;
+
+    // the same token on both accounts only ever finds the caller's entries
+    assert_eq!(set_tokens(&app, &owner, &owned, &[7]).await, StatusCode::OK);
+    assert_eq!(
+        set_tokens(&app, &other, &others, &[7]).await,
+        StatusCode::OK
+    );
+    assert_eq!(search(&app, &owner, 7).await, vec![owned.clone()]);
+    assert_eq!(search(&app, &other, 7).await, vec![others.clone()]);
+
+    // nor can tokens be put on someone else's entry
+    assert_eq!(
+        set_tokens(&app, &other, &owned, &[8]).await,
+        StatusCode::NOT_FOUND
+    );
+    assert!(search(&app, &other, 8).await.is_empty());
+    assert!(search(&app, &owner, 8).await.is_empty());
+    assert_eq!(search(&app, &owner, 7).await, vec![owned]);
+}
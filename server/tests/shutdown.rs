@@ -0,0 +1,96 @@
+use axum::{body::Body, http::Request, http::StatusCode, routing::get};
+use server::{routers, shutdown};
+use std::time::{Duration, Instant};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::sync::CancellationToken;
+use tower::ServiceExt;
+
+async fn slow() -> &'static str {
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    "done"
+}
+
+// raw http so the connection stays open across the shutdown
+async fn get_slow(address: std::net::SocketAddr) -> String {
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    String::from_utf8(response).unwrap()
+}
+
+#[tokio::test]
+async fn shutdown_drains_in_flight_requests() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app_state = routers::AppState::build().await;
+    let app = routers::api(app_state.clone()).route("/slow", get(slow));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let shutdown_token = CancellationToken::new();
+    let purge = shutdown::spawn_purge(app_state.clone(), shutdown_token.clone());
+    let server = tokio::spawn(shutdown::serve(
+        listener,
+        app.clone(),
+        None,
+        shutdown_token.clone(),
+        Duration::from_secs(5),
+    ));
+
+    let request = tokio::spawn(get_slow(address));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    shutdown_token.cancel();
+
+    let response = request.await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.ends_with("done"), "{response}");
+    server.await.unwrap().unwrap();
+    purge.await.unwrap();
+    assert!(TcpStream::connect(address).await.is_err());
+
+    // once the pool is closed the server no longer reports itself ready
+    app_state.database.close().await;
+    let response = app
+        .oneshot(Request::get("/health/ready").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn shutdown_aborts_requests_past_the_drain_timeout() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = routers::app().await.route("/slow", get(slow));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let shutdown_token = CancellationToken::new();
+    let server = tokio::spawn(shutdown::serve(
+        listener,
+        app,
+        None,
+        shutdown_token.clone(),
+        Duration::from_millis(100),
+    ));
+
+    let request = tokio::spawn(get_slow(address));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let start = Instant::now();
+    shutdown_token.cancel();
+
+    let response = request.await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 503"), "{response}");
+    assert!(response.contains("shutting_down"), "{response}");
+    server.await.unwrap().unwrap();
+    assert!(start.elapsed() < Duration::from_millis(400));
+}
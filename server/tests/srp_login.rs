@@ -0,0 +1,251 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{login, login_start, random_username, register, request};
+use serde_json::json;
+use sqlx::postgres::PgPool;
+
+#[tokio::test]
+async fn srp_registration_and_login() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+
+    let (status, access_token, user) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(access_token.is_some());
+    assert_eq!(user["username"], username.to_lowercase());
+
+    let (status, access_token, _) = login(&app, &username, "wrong password").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(access_token.is_none());
+
+    // unknown usernames get a salt and a server key like everyone else, then fail
+    let (status, _, _) = login(&app, &random_username(), "password").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn logins_update_the_connection_time() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    };
+    let app = server::routers::app().await;
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+
+    let pool = PgPool::connect(&url).await.unwrap();
+    let connected_at = || {
+        sqlx::query_scalar::<_, i64>("SELECT connected_at FROM users WHERE username = $1;")
+            .bind(username.to_lowercase())
+            .fetch_one(&pool)
+    };
+    sqlx::query("UPDATE users SET connected_at = 0 WHERE username = $1;")
+        .bind(username.to_lowercase())
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(connected_at().await.unwrap(), 0);
+
+    let (status, _, _) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(connected_at().await.unwrap() > 0);
+}
+
+#[tokio::test]
+async fn srp_login_ids_are_single_use() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+
+    let (login_id, session) = login_start(&app, &username, "password").await;
+    let finish = json!({
+        "username": username,
+        "login_id": login_id,
+        "client_proof": session.client_proof(),
+    });
+
+    // the username has to match the one the login was started for
+    let mut other_user = finish.clone();
+    other_user["username"] = json!(random_username());
+    let (status, _, _) = request(&app, "/users/login", None, other_user).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (login_id, session) = login_start(&app, &username, "password").await;
+    let finish = json!({
+        "username": username,
+        "login_id": login_id,
+        "client_proof": session.client_proof(),
+    });
+    let (status, _, user) = request(&app, "/users/login", None, finish.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let server_proof: Vec<u8> = serde_json::from_value(user["server_proof"].clone()).unwrap();
+    assert!(session.verify_server(&server_proof).is_ok());
+
+    let (status, _, _) = request(&app, "/users/login", None, finish).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn srp_registration_rejects_invalid_verifiers() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    let (srp_salt, verifier) = ([0u8; 32], [1u8; 16]);
+    let (status, _, _) = request(
+        &app,
+        "/users/register",
+        None,
+        json!({ "username": random_username(), "srp_salt": srp_salt, "verifier": verifier }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _, _) = request(
+        &app,
+        "/users/register",
+        None,
+        json!({ "username": random_username(), "password": "password" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn srp_usernames_longer_than_the_column_are_rejected() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    let username = "a".repeat(17);
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+    let (status, _, _) = request(
+        &app,
+        "/users/prelogin",
+        None,
+        json!({ "username": username }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _, _) = request(
+        &app,
+        "/v1/users/login/start",
+        None,
+        json!({ "username": username, "client_public_key": crypto::SrpClient::new().public_key() }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn unknown_usernames_get_stable_fake_answers() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    let known = random_username();
+    assert_eq!(
+        register(&app, &known, "password").await,
+        StatusCode::CREATED
+    );
+    let unknown = random_username();
+
+    let prelogin = |username: String| {
+        let app = app.clone();
+        async move {
+            let (status, _, body) = request(
+                &app,
+                "/users/prelogin",
+                None,
+                json!({ "username": username }),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+            body
+        }
+    };
+    let real = prelogin(known.clone()).await;
+    let fake = prelogin(unknown.clone()).await;
+
+    // the fake answer has the shape and parameters of a real one
+    let keys = |body: &serde_json::Value| {
+        let mut keys: Vec<_> = body.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    };
+    assert_eq!(keys(&fake), keys(&real));
+    assert_eq!(fake["kdf"], real["kdf"]);
+    assert_eq!(fake["kdf_params"], real["kdf_params"]);
+    let salt: [u8; 32] = serde_json::from_value(fake["salt"].clone()).unwrap();
+    assert_ne!(salt, [0; 32]);
+
+    // and like a real salt it does not change between requests or with the username's case
+    assert_eq!(prelogin(unknown.clone()).await, fake);
+    assert_eq!(prelogin(unknown.to_uppercase()).await, fake);
+    assert_eq!(prelogin(known.to_uppercase()).await, real);
+    assert_ne!(prelogin(random_username()).await["salt"], fake["salt"]);
+
+    // the first SRP step answers the same way
+    let login_start = |username: String| {
+        let app = app.clone();
+        async move {
+            let client_public_key = crypto::SrpClient::new().public_key();
+            let (status, _, body) = request(
+                &app,
+                "/users/login/start",
+                None,
+                json!({ "username": username, "client_public_key": client_public_key }),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+            body
+        }
+    };
+    let real = login_start(known.clone()).await;
+    let fake = login_start(unknown.clone()).await;
+    assert_eq!(keys(&fake), keys(&real));
+    let key_length = |body: &serde_json::Value| {
+        serde_json::from_value::<Vec<u8>>(body["server_public_key"].clone())
+            .unwrap()
+            .len()
+    };
+    assert_eq!(key_length(&fake), key_length(&real));
+    assert_eq!(
+        login_start(unknown.to_uppercase()).await["srp_salt"],
+        fake["srp_salt"]
+    );
+    assert_eq!(
+        login_start(known.to_uppercase()).await["srp_salt"],
+        real["srp_salt"]
+    );
+}
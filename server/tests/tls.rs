@@ -0,0 +1,107 @@
+use axum::{routing::get, Router};
+use rcgen::CertifiedKey;
+use server::{shutdown, tls};
+use std::{net::SocketAddr, path::Path, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_rustls::{
+    rustls::{pki_types::ServerName, ClientConfig, RootCertStore},
+    TlsConnector,
+};
+use tokio_util::sync::CancellationToken;
+
+fn self_signed() -> CertifiedKey {
+    rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap()
+}
+
+fn write_pem(dir: &Path, certified: &CertifiedKey) {
+    std::fs::write(dir.join("cert.pem"), certified.cert.pem()).unwrap();
+    std::fs::write(dir.join("key.pem"), certified.key_pair.serialize_pem()).unwrap();
+}
+
+// only a client trusting `trusted` gets an answer
+async fn get_over_tls(address: SocketAddr, trusted: &CertifiedKey) -> std::io::Result<String> {
+    let mut roots = RootCertStore::empty();
+    roots.add(trusted.cert.der().clone()).unwrap();
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connector = TlsConnector::from(Arc::new(config));
+    let stream = TcpStream::connect(address).await?;
+    let server_name = ServerName::try_from("localhost").unwrap();
+    let mut stream = connector.connect(server_name, stream).await?;
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    Ok(String::from_utf8(response).unwrap())
+}
+
+#[tokio::test]
+async fn tls_with_hsts_and_certificate_reload() {
+    let dir = std::env::temp_dir().join(format!("vault-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let first = self_signed();
+    write_pem(&dir, &first);
+    let (cert_path, key_path) = (dir.join("cert.pem"), dir.join("key.pem"));
+
+    let tls_config = tls::load(&cert_path, &key_path).await.unwrap();
+    let app = tls::layer(Router::new().route("/", get(|| async { "ok" })), 600);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let shutdown_token = CancellationToken::new();
+    let reload = tls::spawn_reload(
+        tls_config.clone(),
+        cert_path,
+        key_path,
+        Duration::from_millis(50),
+        shutdown_token.clone(),
+    );
+    let server = tokio::spawn(shutdown::serve(
+        listener,
+        app,
+        Some(tls_config),
+        shutdown_token.clone(),
+        Duration::from_secs(1),
+    ));
+
+    let response = get_over_tls(address, &first).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(
+        response.contains("strict-transport-security: max-age=600; includeSubDomains"),
+        "{response}"
+    );
+    assert!(response.ends_with("ok"), "{response}");
+
+    // plain http is not answered
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response).await;
+    assert!(!String::from_utf8_lossy(&response).contains("200 OK"));
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let second = self_signed();
+    write_pem(&dir, &second);
+    let mut reloaded = false;
+    for _ in 0..40 {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        if get_over_tls(address, &second).await.is_ok() {
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(reloaded, "certificate was not reloaded");
+    assert!(get_over_tls(address, &first).await.is_err());
+
+    shutdown_token.cancel();
+    server.await.unwrap().unwrap();
+    reload.await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::{login, random_username, register, request};
+use serde_json::json;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn access_tokens_expire() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    // kept apart from the other token tests so the lifetime does not leak into them
+    std::env::set_var("ACCESS_TOKEN_LIFETIME_SECONDS", "0");
+    let app = server::routers::app().await;
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+    let (status, _, session) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(session["expires_in"], 0);
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    assert_eq!(
+        app.clone()
+            .oneshot(
+                Request::get("/tags")
+                    .header(
+                        "authorization",
+                        format!("Bearer {}", session["access_token"].as_str().unwrap()),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+    let (status, _, _) = request(
+        &app,
+        "/users/refresh",
+        None,
+        json!({ "refresh_token": session["refresh_token"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use common::{login, random_username, register, request};
+use serde_json::json;
+use tower::ServiceExt;
+
+async fn get_tags(app: &Router, access_token: &str) -> StatusCode {
+    let request = Request::get("/tags")
+        .header("authorization", format!("Bearer {access_token}"))
+        .body(Body::empty())
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+async fn refresh(app: &Router, refresh_token: &str) -> (StatusCode, serde_json::Value) {
+    let (status, _, tokens) = request(
+        app,
+        "/users/refresh",
+        None,
+        json!({ "refresh_token": refresh_token }),
+    )
+    .await;
+    (status, tokens)
+}
+
+async fn logged_in_user(app: &Router) -> serde_json::Value {
+    let username = random_username();
+    assert_eq!(
+        register(app, &username, "password").await,
+        StatusCode::CREATED
+    );
+    let (status, _, session) = login(app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(session["token_type"], "Bearer");
+    session
+}
+
+#[tokio::test]
+async fn refresh_tokens_rotate() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+    let session = logged_in_user(&app).await;
+    let access_token = session["access_token"].as_str().unwrap();
+    let refresh_token = session["refresh_token"].as_str().unwrap();
+
+    assert_eq!(get_tags(&app, access_token).await, StatusCode::OK);
+    assert_eq!(get_tags(&app, "unknown").await, StatusCode::UNAUTHORIZED);
+
+    let (status, tokens) = refresh(&app, refresh_token).await;
+    assert_eq!(status, StatusCode::OK);
+    let new_refresh_token = tokens["refresh_token"].as_str().unwrap();
+    assert_ne!(new_refresh_token, refresh_token);
+    assert_eq!(
+        get_tags(&app, tokens["access_token"].as_str().unwrap()).await,
+        StatusCode::OK
+    );
+
+    let (status, tokens) = refresh(&app, new_refresh_token).await;
+    assert_eq!(status, StatusCode::OK);
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    // logging out ends the whole family
+    let (status, _, _) = request(&app, "/users/logout", Some(access_token), json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(get_tags(&app, access_token).await, StatusCode::UNAUTHORIZED);
+    let (status, _) = refresh(&app, tokens["refresh_token"].as_str().unwrap()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn reused_refresh_token_revokes_the_family() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+    let session = logged_in_user(&app).await;
+    let refresh_token = session["refresh_token"].as_str().unwrap();
+
+    let (status, tokens) = refresh(&app, refresh_token).await;
+    assert_eq!(status, StatusCode::OK);
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    // an attacker replays the first refresh token
+    let (status, _) = refresh(&app, refresh_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    assert_eq!(get_tags(&app, access_token).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        get_tags(&app, session["access_token"].as_str().unwrap()).await,
+        StatusCode::UNAUTHORIZED
+    );
+    let (status, _) = refresh(&app, tokens["refresh_token"].as_str().unwrap()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // other logins of the same user are not affected
+    let other = logged_in_user(&app).await;
+    assert_eq!(
+        get_tags(&app, other["access_token"].as_str().unwrap()).await,
+        StatusCode::OK
+    );
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use axum::{http::StatusCode, Router};
+use common::{login, random_username, register, request};
+use crypto::Totp;
+use serde_json::json;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const PERIOD: u64 = 30;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Code of the step `offset` periods away from now, the server accepts one step either way.
+fn code(totp: &Totp, offset: i64) -> String {
+    totp.code_at(now().saturating_add_signed(offset * PERIOD as i64))
+}
+
+/// Logs in with the password, returning the id of the second factor challenge.
+async fn challenge(app: &Router, username: &str) -> String {
+    let (status, _, challenge) = login(app, username, "password").await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(challenge["second_factors"], json!(["totp"]));
+    challenge["challenge_id"].as_str().unwrap().to_string()
+}
+
+async fn login_totp(app: &Router, username: &str, second_factor: serde_json::Value) -> StatusCode {
+    let mut body = second_factor;
+    body["challenge_id"] = challenge(app, username).await.into();
+    let (status, access_token, _) = request(app, "/users/login/totp", None, body).await;
+    assert_eq!(status == StatusCode::OK, access_token.is_some());
+    status
+}
+
+#[tokio::test]
+async fn totp_enrollment_login_and_recovery() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    // the codes below are picked around the current step, which should not move on midway
+    let remaining = PERIOD - now() % PERIOD;
+    if remaining < PERIOD / 2 {
+        tokio::time::sleep(Duration::from_secs(remaining + 1)).await;
+    }
+
+    let app = server::routers::app().await;
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+    let (_, access_token, _) = login(&app, &username, "password").await;
+    let access_token = access_token.unwrap();
+
+    let (status, _, enrollment) =
+        request(&app, "/users/totp/enroll", Some(&access_token), json!({})).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let totp = Totp::parse(enrollment["otpauth_uri"].as_str().unwrap()).unwrap();
+    assert_eq!(totp.secret_base32(), enrollment["secret"].as_str().unwrap());
+
+    // enrollment only takes effect once a code proves the secret was stored
+    let (status, _, _) = request(
+        &app,
+        "/users/totp/confirm",
+        Some(&access_token),
+        json!({ "code": "000000x" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _, _) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, confirmed) = request(
+        &app,
+        "/users/totp/confirm",
+        Some(&access_token),
+        json!({ "code": code(&totp, -1) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let recovery_codes: Vec<String> =
+        serde_json::from_value(confirmed["recovery_codes"].clone()).unwrap();
+    assert_eq!(recovery_codes.len(), 10);
+
+    // the password alone now only gets a challenge
+    assert_eq!(
+        login_totp(&app, &username, json!({ "code": code(&totp, 0) })).await,
+        StatusCode::OK
+    );
+
+    // a used code does not log in again, nor do the codes of earlier steps
+    for offset in [0, -1] {
+        assert_eq!(
+            login_totp(&app, &username, json!({ "code": code(&totp, offset) })).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    // recovery codes work once, whatever their case
+    let recovery_code = recovery_codes[0].to_uppercase();
+    assert_eq!(
+        login_totp(&app, &username, json!({ "recovery_code": recovery_code })).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        login_totp(&app, &username, json!({ "recovery_code": recovery_code })).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // disabling takes a valid second factor too
+    for second_factor in [
+        json!({ "code": code(&totp, 0) }),
+        json!({ "recovery_code": recovery_codes[0] }),
+        json!({}),
+    ] {
+        let (status, _, _) = request(
+            &app,
+            "/users/totp/disable",
+            Some(&access_token),
+            second_factor,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _, _) = request(
+        &app,
+        "/users/totp/disable",
+        Some(&access_token),
+        json!({ "code": code(&totp, 1) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, access_token, _) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(access_token.is_some());
+}
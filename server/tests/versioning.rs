@@ -0,0 +1,87 @@
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+mod common;
+
+#[tokio::test]
+async fn versioned_routes_and_unversioned_aliases() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/v1/version").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("deprecation").is_none());
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["api_version"], "v1");
+    assert_eq!(body["supported_versions"], json!(["v1"]));
+    assert!(body["capabilities"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("srp")));
+
+    let prelogin = |uri: &'static str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                json!({"username": common::random_username()}).to_string(),
+            ))
+            .unwrap()
+    };
+    let response = app
+        .clone()
+        .oneshot(prelogin("/v1/users/prelogin"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("deprecation").is_none());
+    assert!(response.headers().get("sunset").is_none());
+
+    let response = app
+        .clone()
+        .oneshot(prelogin("/users/prelogin"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["deprecation"]
+        .to_str()
+        .unwrap()
+        .starts_with('@'));
+    assert!(response.headers()["sunset"]
+        .to_str()
+        .unwrap()
+        .ends_with(" GMT"));
+    assert_eq!(
+        response.headers()[header::LINK],
+        "</v1/users/prelogin>; rel=\"successor-version\""
+    );
+
+    // errors of aliases are flagged as well, probes stay unversioned
+    let response = app
+        .clone()
+        .oneshot(Request::get("/passwords").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response.headers().get("deprecation").is_some());
+    let response = app
+        .oneshot(Request::get("/health/live").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("deprecation").is_none());
+}
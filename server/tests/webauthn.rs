@@ -0,0 +1,238 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use ciborium::Value;
+use common::{login, random_username, register, request};
+use data_encoding::BASE64URL_NOPAD;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use rand::Rng;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tower::ServiceExt;
+
+const RP_ID: &str = "localhost";
+const ORIGIN: &str = "http://localhost";
+
+struct SoftAuthenticator {
+    credential_id: Vec<u8>,
+    signing_key: SigningKey,
+    sign_count: u32,
+}
+
+impl SoftAuthenticator {
+    fn new() -> Self {
+        Self {
+            credential_id: rand::thread_rng().gen::<[u8; 16]>().to_vec(),
+            signing_key: SigningKey::random(&mut rand::rngs::OsRng),
+            sign_count: 0,
+        }
+    }
+
+    fn authenticator_data(&self, flags: u8) -> Vec<u8> {
+        let mut auth_data = Sha256::digest(RP_ID.as_bytes()).to_vec();
+        auth_data.push(flags);
+        auth_data.extend_from_slice(&self.sign_count.to_be_bytes());
+        auth_data
+    }
+
+    fn client_data(ceremony: &str, challenge: &str) -> Vec<u8> {
+        json!({ "type": ceremony, "challenge": challenge, "origin": ORIGIN })
+            .to_string()
+            .into_bytes()
+    }
+
+    fn create(&self, challenge: &str) -> serde_json::Value {
+        let point = self.signing_key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer(2.into())),
+            (Value::Integer(3.into()), Value::Integer((-7).into())),
+            (Value::Integer((-1).into()), Value::Integer(1.into())),
+            (
+                Value::Integer((-2).into()),
+                Value::Bytes(point.x().unwrap().to_vec()),
+            ),
+            (
+                Value::Integer((-3).into()),
+                Value::Bytes(point.y().unwrap().to_vec()),
+            ),
+        ]);
+
+        let mut auth_data = self.authenticator_data(0x41);
+        auth_data.extend_from_slice(&[0u8; 16]);
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        ciborium::ser::into_writer(&cose_key, &mut auth_data).unwrap();
+
+        let attestation = Value::Map(vec![
+            (Value::Text("fmt".into()), Value::Text("none".into())),
+            (Value::Text("attStmt".into()), Value::Map(vec![])),
+            (Value::Text("authData".into()), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = Vec::new();
+        ciborium::ser::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        json!({
+            "name": "soft key",
+            "client_data_json": BASE64URL_NOPAD.encode(&Self::client_data("webauthn.create", challenge)),
+            "attestation_object": BASE64URL_NOPAD.encode(&attestation_object),
+        })
+    }
+
+    fn get(&mut self, challenge_id: &str, challenge: &str) -> serde_json::Value {
+        self.sign_count += 1;
+        let client_data_json = Self::client_data("webauthn.get", challenge);
+        let auth_data = self.authenticator_data(0x01);
+        let mut message = auth_data.clone();
+        message.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature: Signature = self.signing_key.sign(&message);
+
+        json!({
+            "challenge_id": challenge_id,
+            "credential_id": BASE64URL_NOPAD.encode(&self.credential_id),
+            "client_data_json": BASE64URL_NOPAD.encode(&client_data_json),
+            "authenticator_data": BASE64URL_NOPAD.encode(&auth_data),
+            "signature": BASE64URL_NOPAD.encode(signature.to_der().as_bytes()),
+        })
+    }
+}
+
+async fn login_with_key(
+    app: &Router,
+    username: &str,
+    authenticator: &mut SoftAuthenticator,
+) -> (StatusCode, Option<String>) {
+    let (status, _, challenge) = login(app, username, "password").await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(challenge["second_factors"], json!(["webauthn"]));
+    let challenge_id = challenge["challenge_id"].as_str().unwrap();
+
+    let (status, _, options) = request(
+        app,
+        "/users/webauthn/login/start",
+        None,
+        json!({ "challenge_id": challenge_id }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(options["rpId"], RP_ID);
+    assert_eq!(
+        options["allowCredentials"][0]["id"],
+        BASE64URL_NOPAD.encode(&authenticator.credential_id)
+    );
+
+    let assertion = authenticator.get(challenge_id, options["challenge"].as_str().unwrap());
+    let (status, access_token, _) =
+        request(app, "/users/webauthn/login/finish", None, assertion).await;
+    (status, access_token)
+}
+
+#[tokio::test]
+async fn webauthn_registration_and_login() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let app = server::routers::app().await;
+
+    let username = random_username();
+    assert_eq!(
+        register(&app, &username, "password").await,
+        StatusCode::CREATED
+    );
+
+    let (status, access_token, _) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+    let access_token = access_token.unwrap();
+
+    // registration ceremony
+    let mut authenticator = SoftAuthenticator::new();
+    let (status, _, options) = request(
+        &app,
+        "/users/webauthn/register/start",
+        Some(&access_token),
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(options["rp"]["id"], RP_ID);
+    let challenge = options["challenge"].as_str().unwrap();
+
+    let (status, _, _) = request(
+        &app,
+        "/users/webauthn/register/finish",
+        Some(&access_token),
+        authenticator.create("wrong challenge"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _, options) = request(
+        &app,
+        "/users/webauthn/register/start",
+        Some(&access_token),
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let challenge_from_options = options["challenge"].as_str().unwrap();
+    assert_ne!(challenge, challenge_from_options);
+    let (status, _, credential) = request(
+        &app,
+        "/users/webauthn/register/finish",
+        Some(&access_token),
+        authenticator.create(challenge_from_options),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        credential["credential_id"],
+        BASE64URL_NOPAD.encode(&authenticator.credential_id)
+    );
+
+    // authentication ceremony
+    let (status, access_token) = login_with_key(&app, &username, &mut authenticator).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(access_token.is_some());
+
+    // a cloned authenticator replays an old counter
+    authenticator.sign_count -= 1;
+    let (status, access_token) = login_with_key(&app, &username, &mut authenticator).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(access_token.is_none());
+
+    // a different key with the same credential id
+    let mut impostor = SoftAuthenticator::new();
+    impostor.credential_id = authenticator.credential_id.clone();
+    impostor.sign_count = 10;
+    let (status, _) = login_with_key(&app, &username, &mut impostor).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    authenticator.sign_count += 1;
+    let (status, access_token) = login_with_key(&app, &username, &mut authenticator).await;
+    assert_eq!(status, StatusCode::OK);
+    let access_token = access_token.unwrap();
+
+    // removing the only key turns the second factor off
+    let response = app
+        .clone()
+        .oneshot(
+            Request::delete(format!(
+                "/users/webauthn/credentials/{}",
+                BASE64URL_NOPAD.encode(&authenticator.credential_id)
+            ))
+            .header("authorization", format!("Bearer {access_token}"))
+            .body(Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let (status, access_token, _) = login(&app, &username, "password").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(access_token.is_some());
+}
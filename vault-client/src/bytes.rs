@@ -0,0 +1,83 @@
+//! Serde helpers for byte fields, sent and read as base64 strings, used with
+//! `#[serde(with = "crate::bytes")]`.
+
+use data_encoding::BASE64;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+pub trait Bytes: Sized {
+    fn serialize_bytes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+    fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+pub fn serialize<T: Bytes, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    value.serialize_bytes(serializer)
+}
+
+pub fn deserialize<'de, T: Bytes, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    T::deserialize_bytes(deserializer)
+}
+
+struct Wrapper<T>(T);
+
+impl<T: Bytes> Serialize for Wrapper<&T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_bytes(serializer)
+    }
+}
+
+impl<'de, T: Bytes> Deserialize<'de> for Wrapper<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_bytes(deserializer).map(Wrapper)
+    }
+}
+
+impl Bytes for Vec<u8> {
+    fn serialize_bytes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(self))
+    }
+
+    fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        BASE64
+            .decode(value.as_bytes())
+            .map_err(|_| de::Error::custom("invalid base64"))
+    }
+}
+
+impl<const N: usize> Bytes for [u8; N] {
+    fn serialize_bytes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(self))
+    }
+
+    fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize_bytes(deserializer)?;
+        let length = bytes.len();
+        bytes
+            .try_into()
+            .map_err(|_| de::Error::invalid_length(length, &format!("{N} bytes").as_str()))
+    }
+}
+
+impl<T: Bytes> Bytes for Option<T> {
+    fn serialize_bytes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Some(value) => serializer.serialize_some(&Wrapper(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<Wrapper<T>>::deserialize(deserializer).map(|value| value.map(|value| value.0))
+    }
+}
+
+impl<const N: usize> Bytes for Vec<[u8; N]> {
+    fn serialize_bytes<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter().map(Wrapper))
+    }
+
+    fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let values = Vec::<Wrapper<[u8; N]>>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|value| value.0).collect())
+    }
+}
@@ -0,0 +1,917 @@
+use crate::error::{Error, ErrorBody, Result};
+use crate::model::*;
+use crypto::{AesGcmEncrypter, Encrypter, SrpClient, SrpClientSession, SrpError};
+use reqwest::{header, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::{sync::Mutex, task};
+use uuid::Uuid;
+
+/// Api version this client speaks.
+pub const API_VERSION: &str = "v1";
+// binary fields as base64 strings instead of the deprecated arrays of numbers
+const ACCEPT: &str = "application/json; encoding=base64";
+// refreshed ahead of expiry so a request in flight does not outlive its token
+const REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_at: Instant,
+}
+
+impl From<TokensOut> for Session {
+    fn from(tokens: TokensOut) -> Self {
+        Self {
+            access_token: tokens.access_token,
+            refresh_token: tokens.refresh_token,
+            expires_at: Instant::now() + Duration::from_secs(tokens.expires_in.max(0) as u64),
+        }
+    }
+}
+
+enum Credentials {
+    None,
+    Session(Session),
+    ApiToken(String),
+}
+
+struct Inner {
+    http: reqwest::Client,
+    address: String,
+    // held across a refresh, a refresh token is single use and a reused one revokes the session
+    credentials: Mutex<Credentials>,
+    encrypter: RwLock<Option<Arc<AesGcmEncrypter>>>,
+}
+
+/// Async client of the vault api, cheap to clone and share between tasks.
+///
+/// Entries are encrypted before they are sent and decrypted when they are read, with the vault
+/// key derived from the master password by [`Client::login`] or [`Client::unlock`].
+#[derive(Clone)]
+pub struct Client {
+    inner: Arc<Inner>,
+}
+
+impl Client {
+    /// Client of the server at `address`, e.g. `https://vault.example.com`.
+    pub fn new(address: &str) -> Result<Self> {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::ACCEPT, header::HeaderValue::from_static(ACCEPT));
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                http,
+                address: address.trim_end_matches('/').to_string(),
+                credentials: Mutex::new(Credentials::None),
+                encrypter: RwLock::new(None),
+            }),
+        })
+    }
+
+    /// Like [`Client::new`], failing unless the server speaks [`API_VERSION`].
+    pub async fn connect(address: &str) -> Result<Self> {
+        let client = Self::new(address)?;
+        let version = client.version().await.map_err(|err| match err {
+            // servers predating versioning
+            Error::NotFound(_) | Error::Server(StatusCode::NOT_FOUND, _) => {
+                Error::UnsupportedVersion(Vec::new())
+            }
+            err => err,
+        })?;
+        if !version
+            .supported_versions
+            .iter()
+            .any(|supported| supported == API_VERSION)
+        {
+            return Err(Error::UnsupportedVersion(version.supported_versions));
+        }
+        Ok(client)
+    }
+
+    /// Authenticates with a personal access token instead of a session.
+    pub async fn use_api_token(&self, token: String) {
+        *self.inner.credentials.lock().await = Credentials::ApiToken(token);
+    }
+
+    pub async fn session(&self) -> Option<Session> {
+        match &*self.inner.credentials.lock().await {
+            Credentials::Session(session) => Some(session.clone()),
+            _ => None,
+        }
+    }
+
+    /// Forgets the vault key, entries can no longer be read or written.
+    pub fn lock(&self) {
+        *self.inner.encrypter.write().unwrap() = None;
+    }
+
+    // SERVER
+
+    pub async fn version(&self) -> Result<VersionOut> {
+        self.send(self.request(Method::GET, "/version")).await
+    }
+
+    pub async fn health_live(&self) -> Result<MessageOut> {
+        let url = format!("{}/health/live", self.inner.address);
+        self.send(self.inner.http.get(url)).await
+    }
+
+    /// Readiness of the server, also returned while it answers 503.
+    pub async fn health_ready(&self) -> Result<ReadyOut> {
+        let url = format!("{}/health/ready", self.inner.address);
+        let response = self.inner.http.get(url).send().await?;
+        match response.status() {
+            StatusCode::OK | StatusCode::SERVICE_UNAVAILABLE => Ok(response.json().await?),
+            _ => Err(error(response).await),
+        }
+    }
+
+    // ACCOUNT
+
+    /// Registers `username`, only the SRP verifier of `password` leaves the client.
+    pub async fn register(&self, username: &str, password: &str) -> Result<MessageOut> {
+        let srp_salt = crypto::srp_salt();
+        let plain_password = password.to_string();
+        let verifier =
+            task::spawn_blocking(move || crypto::srp_verifier(&plain_password, &srp_salt))
+                .await
+                .expect("Key derivation panicked")?;
+        let user = UserIn {
+            username,
+            srp_salt,
+            verifier,
+        };
+        self.send(self.request(Method::POST, "/users/register").json(&user))
+            .await
+    }
+
+    pub async fn prelogin(&self, username: &str) -> Result<PreloginOut> {
+        self.send(
+            self.request(Method::POST, "/users/prelogin")
+                .json(&PreloginIn { username }),
+        )
+        .await
+    }
+
+    /// Derives the vault key from `password`, enough to read entries with an api token.
+    pub async fn unlock(&self, username: &str, password: &str) -> Result<()> {
+        let prelogin = self.prelogin(username).await?;
+        let plain_password = password.to_string();
+        let encrypter = task::spawn_blocking(move || {
+            AesGcmEncrypter::build_with_params(plain_password, &prelogin.salt, &prelogin.kdf_params)
+        })
+        .await
+        .expect("Key derivation panicked")
+        .map_err(|_| Error::Kdf)?;
+        *self.inner.encrypter.write().unwrap() = Some(Arc::new(encrypter));
+        Ok(())
+    }
+
+    /// Proves knowledge of `password` with SRP, checks the server proves it knows the account,
+    /// then unlocks the vault and keeps the session.
+    pub async fn login(&self, username: &str, password: &str) -> Result<Login> {
+        let client = SrpClient::new();
+        let start: LoginStartOut = self
+            .send(
+                self.request(Method::POST, "/users/login/start")
+                    .json(&LoginStartIn {
+                        username,
+                        client_public_key: client.public_key(),
+                    }),
+            )
+            .await?;
+        let (plain_username, plain_password) = (username.to_string(), password.to_string());
+        let (srp_salt, server_public_key) = (start.srp_salt, start.server_public_key);
+        let srp_session = task::spawn_blocking(move || {
+            client.process(
+                &plain_username,
+                &plain_password,
+                &srp_salt,
+                &server_public_key,
+            )
+        })
+        .await
+        .expect("Key derivation panicked")?;
+        let response = self
+            .execute(self.request(Method::POST, "/users/login").json(&LoginIn {
+                username,
+                login_id: &start.login_id,
+                client_proof: srp_session.client_proof().to_vec(),
+            }))
+            .await?;
+
+        if response.status() == StatusCode::ACCEPTED {
+            let challenge: LoginChallengeOut = response.json().await?;
+            verify_server(&srp_session, challenge.server_proof)?;
+            self.unlock(username, password).await?;
+            return Ok(Login::SecondFactor(challenge));
+        }
+        let session: SessionOut = response.json().await?;
+        verify_server(&srp_session, session.user.server_proof)?;
+        self.unlock(username, password).await?;
+        *self.inner.credentials.lock().await = Credentials::Session(session.tokens.into());
+        Ok(Login::LoggedIn(session.user))
+    }
+
+    /// Finishes a login answered with [`Login::SecondFactor`].
+    pub async fn login_second_factor(
+        &self,
+        challenge_id: &str,
+        second_factor: &SecondFactorIn,
+    ) -> Result<UserOut> {
+        let session: SessionOut = self
+            .send(
+                self.request(Method::POST, "/users/login/totp")
+                    .json(&LoginSecondFactorIn {
+                        challenge_id,
+                        second_factor,
+                    }),
+            )
+            .await?;
+        *self.inner.credentials.lock().await = Credentials::Session(session.tokens.into());
+        Ok(session.user)
+    }
+
+    /// Assertion options to hand to the authenticator.
+    pub async fn webauthn_login_start(&self, challenge_id: &str) -> Result<Value> {
+        self.send(
+            self.request(Method::POST, "/users/webauthn/login/start")
+                .json(&WebauthnLoginStartIn { challenge_id }),
+        )
+        .await
+    }
+
+    pub async fn webauthn_login_finish(&self, assertion: &WebauthnLoginIn) -> Result<UserOut> {
+        let session: SessionOut = self
+            .send(
+                self.request(Method::POST, "/users/webauthn/login/finish")
+                    .json(assertion),
+            )
+            .await?;
+        *self.inner.credentials.lock().await = Credentials::Session(session.tokens.into());
+        Ok(session.user)
+    }
+
+    /// Exchanges the refresh token for a new session, done on its own before the session
+    /// expires.
+    pub async fn refresh(&self) -> Result<()> {
+        let mut credentials = self.inner.credentials.lock().await;
+        let Credentials::Session(session) = &mut *credentials else {
+            return Err(Error::NotLoggedIn);
+        };
+        *session = self.refresh_session(&session.refresh_token).await?;
+        Ok(())
+    }
+
+    /// Deletes the session and locks the vault.
+    pub async fn logout(&self) -> Result<MessageOut> {
+        let message = self
+            .send(self.authorized(Method::POST, "/users/logout").await?)
+            .await;
+        *self.inner.credentials.lock().await = Credentials::None;
+        self.lock();
+        message
+    }
+
+    pub async fn totp_enroll(&self) -> Result<TotpEnrollmentOut> {
+        self.send(self.authorized(Method::POST, "/users/totp/enroll").await?)
+            .await
+    }
+
+    pub async fn totp_confirm(&self, second_factor: &SecondFactorIn) -> Result<RecoveryCodesOut> {
+        self.send(
+            self.authorized(Method::POST, "/users/totp/confirm")
+                .await?
+                .json(second_factor),
+        )
+        .await
+    }
+
+    pub async fn totp_disable(&self, second_factor: &SecondFactorIn) -> Result<MessageOut> {
+        self.send(
+            self.authorized(Method::POST, "/users/totp/disable")
+                .await?
+                .json(second_factor),
+        )
+        .await
+    }
+
+    /// Creation options to hand to the authenticator.
+    pub async fn webauthn_register_start(&self) -> Result<Value> {
+        self.send(
+            self.authorized(Method::POST, "/users/webauthn/register/start")
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn webauthn_register_finish(
+        &self,
+        registration: &WebauthnRegistrationIn,
+    ) -> Result<WebauthnCredentialOut> {
+        self.send(
+            self.authorized(Method::POST, "/users/webauthn/register/finish")
+                .await?
+                .json(registration),
+        )
+        .await
+    }
+
+    pub async fn webauthn_credentials(&self) -> Result<Vec<WebauthnCredentialOut>> {
+        self.send(
+            self.authorized(Method::GET, "/users/webauthn/credentials")
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn delete_webauthn_credential(&self, credential_id: &str) -> Result<MessageOut> {
+        self.send(
+            self.authorized(
+                Method::DELETE,
+                &format!("/users/webauthn/credentials/{credential_id}"),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    pub async fn create_api_token(&self, api_token: &ApiTokenIn) -> Result<ApiTokenOut> {
+        self.send(
+            self.authorized(Method::POST, "/users/tokens")
+                .await?
+                .json(api_token),
+        )
+        .await
+    }
+
+    pub async fn api_tokens(&self) -> Result<Vec<ApiTokenOut>> {
+        self.send(self.authorized(Method::GET, "/users/tokens").await?)
+            .await
+    }
+
+    pub async fn delete_api_token(&self, token_id: Uuid) -> Result<MessageOut> {
+        self.send(
+            self.authorized(Method::DELETE, &format!("/users/tokens/{token_id}"))
+                .await?,
+        )
+        .await
+    }
+
+    // PASSWORDS
+
+    /// Creates a password entry and indexes its domain for search.
+    pub async fn create_password(&self, password: &PasswordIn) -> Result<Password> {
+        let encrypter = self.encrypter()?;
+        let body = encrypt_password(encrypter.as_ref(), password)?;
+        let created: PasswordOut = self
+            .send(
+                self.authorized(Method::POST, "/passwords")
+                    .await?
+                    .json(&body),
+            )
+            .await?;
+        let created = created.decrypt(encrypter.as_ref())?;
+        self.update_search_tokens(created.password_id, &created.domain_name)
+            .await?;
+        Ok(created)
+    }
+
+    pub async fn passwords(&self, filter: &EntriesFilter) -> Result<Page<Password>> {
+        let page: PageOut<PasswordOut> = self
+            .send(
+                self.authorized(Method::GET, "/passwords")
+                    .await?
+                    .query(filter),
+            )
+            .await?;
+        page.decrypt(self.encrypter()?.as_ref(), PasswordOut::decrypt)
+    }
+
+    pub async fn password(&self, password_id: Uuid) -> Result<Password> {
+        let password: PasswordOut = self
+            .send(
+                self.authorized(Method::GET, &format!("/passwords/{password_id}"))
+                    .await?,
+            )
+            .await?;
+        password.decrypt(self.encrypter()?.as_ref())
+    }
+
+    /// Replaces a password entry, the previous one is kept in its history.
+    pub async fn update_password(
+        &self,
+        password_id: Uuid,
+        password: &PasswordIn,
+    ) -> Result<Password> {
+        let encrypter = self.encrypter()?;
+        let body = encrypt_password(encrypter.as_ref(), password)?;
+        let updated: PasswordOut = self
+            .send(
+                self.authorized(Method::PATCH, &format!("/passwords/{password_id}"))
+                    .await?
+                    .json(&body),
+            )
+            .await?;
+        let updated = updated.decrypt(encrypter.as_ref())?;
+        self.update_search_tokens(updated.password_id, &updated.domain_name)
+            .await?;
+        Ok(updated)
+    }
+
+    pub async fn delete_password(&self, password_id: Uuid) -> Result<MessageOut> {
+        self.send(
+            self.authorized(Method::DELETE, &format!("/passwords/{password_id}"))
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn password_history(&self, password_id: Uuid) -> Result<Vec<PasswordVersion>> {
+        let history: Vec<PasswordHistoryOut> = self
+            .send(
+                self.authorized(Method::GET, &format!("/passwords/{password_id}/history"))
+                    .await?,
+            )
+            .await?;
+        let encrypter = self.encrypter()?;
+        history
+            .into_iter()
+            .map(|version| version.decrypt(encrypter.as_ref()))
+            .collect()
+    }
+
+    pub async fn restore_password(&self, password_id: Uuid, version: i32) -> Result<Password> {
+        let restored: PasswordOut = self
+            .send(
+                self.authorized(
+                    Method::POST,
+                    &format!("/passwords/{password_id}/history/{version}/restore"),
+                )
+                .await?,
+            )
+            .await?;
+        let restored = restored.decrypt(self.encrypter()?.as_ref())?;
+        self.update_search_tokens(restored.password_id, &restored.domain_name)
+            .await?;
+        Ok(restored)
+    }
+
+    pub async fn set_password_folder(
+        &self,
+        password_id: Uuid,
+        folder_id: Option<Uuid>,
+    ) -> Result<Password> {
+        let password: PasswordOut = self
+            .send(
+                self.authorized(Method::PUT, &format!("/passwords/{password_id}/folder"))
+                    .await?
+                    .json(&EntryFolderIn { folder_id }),
+            )
+            .await?;
+        password.decrypt(self.encrypter()?.as_ref())
+    }
+
+    /// Sets the totp secret or `otpauth://` uri of a password entry, `None` removes it.
+    pub async fn set_password_totp(
+        &self,
+        password_id: Uuid,
+        totp: Option<&str>,
+    ) -> Result<Password> {
+        let encrypter = self.encrypter()?;
+        let body = match totp {
+            Some(totp) => {
+                let encrypted = seal(encrypter.as_ref(), totp)?;
+                PasswordTotpIn {
+                    totp: Some(encrypted.content),
+                    totp_nonce: Some(encrypted.nonce),
+                }
+            }
+            None => PasswordTotpIn {
+                totp: None,
+                totp_nonce: None,
+            },
+        };
+        let password: PasswordOut = self
+            .send(
+                self.authorized(Method::PUT, &format!("/passwords/{password_id}/totp"))
+                    .await?
+                    .json(&body),
+            )
+            .await?;
+        password.decrypt(encrypter.as_ref())
+    }
+
+    pub async fn add_password_tag(&self, password_id: Uuid, tag_id: Uuid) -> Result<Password> {
+        let password: PasswordOut = self
+            .send(
+                self.authorized(
+                    Method::PUT,
+                    &format!("/passwords/{password_id}/tags/{tag_id}"),
+                )
+                .await?,
+            )
+            .await?;
+        password.decrypt(self.encrypter()?.as_ref())
+    }
+
+    pub async fn remove_password_tag(&self, password_id: Uuid, tag_id: Uuid) -> Result<Password> {
+        let password: PasswordOut = self
+            .send(
+                self.authorized(
+                    Method::DELETE,
+                    &format!("/passwords/{password_id}/tags/{tag_id}"),
+                )
+                .await?,
+            )
+            .await?;
+        password.decrypt(self.encrypter()?.as_ref())
+    }
+
+    /// Indexes the tokens of `domain_name` with the vault key, the server never sees the domain
+    /// searched for.
+    pub async fn update_search_tokens(
+        &self,
+        password_id: Uuid,
+        domain_name: &str,
+    ) -> Result<MessageOut> {
+        let tokens = self
+            .encrypter()?
+            .blind_indexer()
+            .domain_indexes(domain_name);
+        self.send(
+            self.authorized(
+                Method::PUT,
+                &format!("/passwords/{password_id}/search_tokens"),
+            )
+            .await?
+            .json(&SearchTokensIn { tokens }),
+        )
+        .await
+    }
+
+    pub async fn search_passwords(&self, query: &str) -> Result<Vec<Password>> {
+        let encrypter = self.encrypter()?;
+        let token = encrypter
+            .blind_indexer()
+            .index(&crypto::normalize_domain(query))
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let passwords: Vec<PasswordOut> = self
+            .send(
+                self.authorized(Method::GET, "/passwords/search")
+                    .await?
+                    .query(&SearchQuery { token }),
+            )
+            .await?;
+        passwords
+            .into_iter()
+            .map(|password| password.decrypt(encrypter.as_ref()))
+            .collect()
+    }
+
+    // NOTES
+
+    pub async fn create_note(&self, note: &NoteIn) -> Result<Note> {
+        let encrypter = self.encrypter()?;
+        let body = encrypt_note(encrypter.as_ref(), note)?;
+        let created: NoteOut = self
+            .send(self.authorized(Method::POST, "/notes").await?.json(&body))
+            .await?;
+        created.decrypt(encrypter.as_ref())
+    }
+
+    pub async fn notes(&self, filter: &EntriesFilter) -> Result<Page<Note>> {
+        let page: PageOut<NoteOut> = self
+            .send(self.authorized(Method::GET, "/notes").await?.query(filter))
+            .await?;
+        page.decrypt(self.encrypter()?.as_ref(), NoteOut::decrypt)
+    }
+
+    pub async fn note(&self, note_id: Uuid) -> Result<Note> {
+        let note: NoteOut = self
+            .send(
+                self.authorized(Method::GET, &format!("/notes/{note_id}"))
+                    .await?,
+            )
+            .await?;
+        note.decrypt(self.encrypter()?.as_ref())
+    }
+
+    /// Replaces a note, the previous one is kept as a revision.
+    pub async fn update_note(&self, note_id: Uuid, note: &NoteIn) -> Result<Note> {
+        let encrypter = self.encrypter()?;
+        let body = encrypt_note(encrypter.as_ref(), note)?;
+        let updated: NoteOut = self
+            .send(
+                self.authorized(Method::PATCH, &format!("/notes/{note_id}"))
+                    .await?
+                    .json(&body),
+            )
+            .await?;
+        updated.decrypt(encrypter.as_ref())
+    }
+
+    pub async fn delete_note(&self, note_id: Uuid) -> Result<MessageOut> {
+        self.send(
+            self.authorized(Method::DELETE, &format!("/notes/{note_id}"))
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn note_revisions(&self, note_id: Uuid) -> Result<Vec<NoteRevision>> {
+        let revisions: Vec<NoteRevisionOut> = self
+            .send(
+                self.authorized(Method::GET, &format!("/notes/{note_id}/revisions"))
+                    .await?,
+            )
+            .await?;
+        let encrypter = self.encrypter()?;
+        revisions
+            .into_iter()
+            .map(|revision| revision.decrypt(encrypter.as_ref()))
+            .collect()
+    }
+
+    pub async fn restore_note(&self, note_id: Uuid, revision: i32) -> Result<Note> {
+        let note: NoteOut = self
+            .send(
+                self.authorized(
+                    Method::POST,
+                    &format!("/notes/{note_id}/revisions/{revision}/restore"),
+                )
+                .await?,
+            )
+            .await?;
+        note.decrypt(self.encrypter()?.as_ref())
+    }
+
+    pub async fn set_note_folder(&self, note_id: Uuid, folder_id: Option<Uuid>) -> Result<Note> {
+        let note: NoteOut = self
+            .send(
+                self.authorized(Method::PUT, &format!("/notes/{note_id}/folder"))
+                    .await?
+                    .json(&EntryFolderIn { folder_id }),
+            )
+            .await?;
+        note.decrypt(self.encrypter()?.as_ref())
+    }
+
+    pub async fn add_note_tag(&self, note_id: Uuid, tag_id: Uuid) -> Result<Note> {
+        let note: NoteOut = self
+            .send(
+                self.authorized(Method::PUT, &format!("/notes/{note_id}/tags/{tag_id}"))
+                    .await?,
+            )
+            .await?;
+        note.decrypt(self.encrypter()?.as_ref())
+    }
+
+    pub async fn remove_note_tag(&self, note_id: Uuid, tag_id: Uuid) -> Result<Note> {
+        let note: NoteOut = self
+            .send(
+                self.authorized(Method::DELETE, &format!("/notes/{note_id}/tags/{tag_id}"))
+                    .await?,
+            )
+            .await?;
+        note.decrypt(self.encrypter()?.as_ref())
+    }
+
+    // FOLDERS
+
+    pub async fn create_folder(&self, parent_id: Option<Uuid>, name: &str) -> Result<Folder> {
+        let encrypter = self.encrypter()?;
+        let body = encrypt_folder(encrypter.as_ref(), parent_id, name)?;
+        let created: FolderOut = self
+            .send(self.authorized(Method::POST, "/folders").await?.json(&body))
+            .await?;
+        created.decrypt(encrypter.as_ref())
+    }
+
+    pub async fn folders(&self) -> Result<Vec<Folder>> {
+        let folders: Vec<FolderOut> = self
+            .send(self.authorized(Method::GET, "/folders").await?)
+            .await?;
+        let encrypter = self.encrypter()?;
+        folders
+            .into_iter()
+            .map(|folder| folder.decrypt(encrypter.as_ref()))
+            .collect()
+    }
+
+    pub async fn folder(&self, folder_id: Uuid) -> Result<Folder> {
+        let folder: FolderOut = self
+            .send(
+                self.authorized(Method::GET, &format!("/folders/{folder_id}"))
+                    .await?,
+            )
+            .await?;
+        folder.decrypt(self.encrypter()?.as_ref())
+    }
+
+    /// Renames or moves a folder, `parent_id` of `None` moves it to the top.
+    pub async fn update_folder(
+        &self,
+        folder_id: Uuid,
+        parent_id: Option<Uuid>,
+        name: &str,
+    ) -> Result<Folder> {
+        let encrypter = self.encrypter()?;
+        let body = encrypt_folder(encrypter.as_ref(), parent_id, name)?;
+        let updated: FolderOut = self
+            .send(
+                self.authorized(Method::PATCH, &format!("/folders/{folder_id}"))
+                    .await?
+                    .json(&body),
+            )
+            .await?;
+        updated.decrypt(encrypter.as_ref())
+    }
+
+    pub async fn delete_folder(&self, folder_id: Uuid) -> Result<MessageOut> {
+        self.send(
+            self.authorized(Method::DELETE, &format!("/folders/{folder_id}"))
+                .await?,
+        )
+        .await
+    }
+
+    // TAGS
+
+    pub async fn create_tag(&self, name: &str) -> Result<Tag> {
+        let encrypter = self.encrypter()?;
+        let body = encrypt_tag(encrypter.as_ref(), name)?;
+        let created: TagOut = self
+            .send(self.authorized(Method::POST, "/tags").await?.json(&body))
+            .await?;
+        created.decrypt(encrypter.as_ref())
+    }
+
+    pub async fn tags(&self) -> Result<Vec<Tag>> {
+        let tags: Vec<TagOut> = self
+            .send(self.authorized(Method::GET, "/tags").await?)
+            .await?;
+        let encrypter = self.encrypter()?;
+        tags.into_iter()
+            .map(|tag| tag.decrypt(encrypter.as_ref()))
+            .collect()
+    }
+
+    pub async fn tag(&self, tag_id: Uuid) -> Result<Tag> {
+        let tag: TagOut = self
+            .send(
+                self.authorized(Method::GET, &format!("/tags/{tag_id}"))
+                    .await?,
+            )
+            .await?;
+        tag.decrypt(self.encrypter()?.as_ref())
+    }
+
+    pub async fn update_tag(&self, tag_id: Uuid, name: &str) -> Result<Tag> {
+        let encrypter = self.encrypter()?;
+        let body = encrypt_tag(encrypter.as_ref(), name)?;
+        let updated: TagOut = self
+            .send(
+                self.authorized(Method::PATCH, &format!("/tags/{tag_id}"))
+                    .await?
+                    .json(&body),
+            )
+            .await?;
+        updated.decrypt(encrypter.as_ref())
+    }
+
+    pub async fn delete_tag(&self, tag_id: Uuid) -> Result<MessageOut> {
+        self.send(
+            self.authorized(Method::DELETE, &format!("/tags/{tag_id}"))
+                .await?,
+        )
+        .await
+    }
+
+    // REQUESTS
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!("{}/{API_VERSION}{path}", self.inner.address);
+        self.inner.http.request(method, url)
+    }
+
+    async fn authorized(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let access_token = self.access_token().await?;
+        Ok(self.request(method, path).bearer_auth(access_token))
+    }
+
+    async fn access_token(&self) -> Result<String> {
+        let mut credentials = self.inner.credentials.lock().await;
+        match &mut *credentials {
+            Credentials::None => Err(Error::NotLoggedIn),
+            Credentials::ApiToken(token) => Ok(token.clone()),
+            Credentials::Session(session) => {
+                if session.expires_at <= Instant::now() + REFRESH_MARGIN {
+                    *session = self.refresh_session(&session.refresh_token).await?;
+                }
+                Ok(session.access_token.clone())
+            }
+        }
+    }
+
+    async fn refresh_session(&self, refresh_token: &str) -> Result<Session> {
+        let tokens: TokensOut = self
+            .send(
+                self.request(Method::POST, "/users/refresh")
+                    .json(&RefreshIn { refresh_token }),
+            )
+            .await?;
+        Ok(tokens.into())
+    }
+
+    fn encrypter(&self) -> Result<Arc<AesGcmEncrypter>> {
+        self.inner
+            .encrypter
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or(Error::Locked)
+    }
+
+    async fn execute(&self, request: RequestBuilder) -> Result<Response> {
+        let response = request.send().await?;
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(error(response).await)
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        Ok(self.execute(request).await?.json().await?)
+    }
+}
+
+async fn error(response: Response) -> Error {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    let body = response.json::<ErrorBody>().await.ok();
+    Error::from_response(status, body, retry_after)
+}
+
+fn verify_server(session: &SrpClientSession, server_proof: Option<[u8; 32]>) -> Result<()> {
+    let server_proof = server_proof.ok_or(Error::Srp(SrpError::Proof))?;
+    Ok(session.verify_server(&server_proof)?)
+}
+
+fn encrypt_password<'a>(
+    encrypter: &dyn Encrypter,
+    password: &'a PasswordIn,
+) -> Result<EncryptedPasswordIn<'a>> {
+    let encrypted = seal(encrypter, &password.password)?;
+    Ok(EncryptedPasswordIn {
+        domain_name: &password.domain_name,
+        username: &password.username,
+        password: encrypted.content,
+        nonce: encrypted.nonce,
+    })
+}
+
+fn encrypt_note(encrypter: &dyn Encrypter, note: &NoteIn) -> Result<EncryptedNoteIn> {
+    let title = seal(encrypter, &note.title)?;
+    let content = seal(encrypter, &note.content)?;
+    Ok(EncryptedNoteIn {
+        title: title.content,
+        title_nonce: title.nonce,
+        content: content.content,
+        content_nonce: content.nonce,
+    })
+}
+
+fn encrypt_folder(
+    encrypter: &dyn Encrypter,
+    parent_id: Option<Uuid>,
+    name: &str,
+) -> Result<FolderIn> {
+    let name = seal(encrypter, name)?;
+    Ok(FolderIn {
+        parent_id,
+        name: name.content,
+        name_nonce: name.nonce,
+    })
+}
+
+fn encrypt_tag(encrypter: &dyn Encrypter, name: &str) -> Result<TagIn> {
+    let name = seal(encrypter, name)?;
+    Ok(TagIn {
+        name: name.content,
+        name_nonce: name.nonce,
+    })
+}
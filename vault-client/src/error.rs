@@ -0,0 +1,112 @@
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    NotFound(String),
+    Conflict(String),
+    Validation(String),
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    /// Message and seconds to wait before retrying, when the server told.
+    TooManyRequests(String, Option<u64>),
+    PayloadTooLarge(String),
+    RequestTimeout(String),
+    /// Database down or server shutting down, worth retrying later.
+    Unavailable(String),
+    Server(StatusCode, String),
+    /// Versions the server supports, none of them known to this client.
+    UnsupportedVersion(Vec<String>),
+    NotLoggedIn,
+    /// No vault key, log in or unlock first.
+    Locked,
+    Srp(crypto::SrpError),
+    Kdf,
+    Encryption,
+    Decryption,
+    Http(reqwest::Error),
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ErrorBody {
+    code: String,
+    message: String,
+}
+
+impl Error {
+    pub(crate) fn from_response(
+        status: StatusCode,
+        body: Option<ErrorBody>,
+        retry_after: Option<u64>,
+    ) -> Self {
+        let Some(ErrorBody { code, message }) = body else {
+            return Error::Server(status, status.to_string());
+        };
+        match code.as_str() {
+            "not_found" => Error::NotFound(message),
+            "conflict" => Error::Conflict(message),
+            "validation_failed" => Error::Validation(message),
+            "bad_request" => Error::BadRequest(message),
+            "unauthorized" => Error::Unauthorized(message),
+            "forbidden" => Error::Forbidden(message),
+            "too_many_requests" => Error::TooManyRequests(message, retry_after),
+            "payload_too_large" => Error::PayloadTooLarge(message),
+            "request_timeout" => Error::RequestTimeout(message),
+            "database_unavailable" | "shutting_down" => Error::Unavailable(message),
+            _ => Error::Server(status, message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(value: reqwest::Error) -> Self {
+        Error::Http(value)
+    }
+}
+
+impl From<crypto::SrpError> for Error {
+    fn from(value: crypto::SrpError) -> Self {
+        Error::Srp(value)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(message)
+            | Error::Conflict(message)
+            | Error::Validation(message)
+            | Error::BadRequest(message)
+            | Error::Unauthorized(message)
+            | Error::Forbidden(message)
+            | Error::TooManyRequests(message, _)
+            | Error::PayloadTooLarge(message)
+            | Error::RequestTimeout(message)
+            | Error::Unavailable(message) => f.write_str(message),
+            Error::Server(status, message) => write!(f, "{status}: {message}"),
+            Error::UnsupportedVersion(versions) => {
+                write!(f, "Unsupported server api versions {versions:?}")
+            }
+            Error::NotLoggedIn => f.write_str("Not logged in"),
+            Error::Locked => f.write_str("Vault is locked"),
+            Error::Srp(_) => f.write_str("Server could not prove it knows the account"),
+            Error::Kdf => f.write_str("Failed to derive the vault key"),
+            Error::Encryption => f.write_str("Failed to encrypt data"),
+            Error::Decryption => f.write_str("Failed to decrypt data"),
+            Error::Http(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(err) => Some(err),
+            _ => None,
+        }
+    }
+}
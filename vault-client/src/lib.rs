@@ -0,0 +1,14 @@
+mod bytes;
+mod client;
+mod error;
+mod model;
+
+pub use client::{Client, Session, API_VERSION};
+pub use error::{Error, Result};
+pub use model::{
+    ApiTokenIn, ApiTokenOut, ComponentOut, ComponentsOut, EntriesFilter, EntriesOrder, Folder,
+    Login, LoginChallengeOut, MessageOut, Note, NoteIn, NoteRevision, Page, Password, PasswordIn,
+    PasswordVersion, PreloginOut, ReadyOut, RecoveryCodesOut, SecondFactorIn, Tag, TokensOut,
+    TotpEnrollmentOut, UserOut, VersionOut, WebauthnCredentialOut, WebauthnLoginIn,
+    WebauthnRegistrationIn,
+};
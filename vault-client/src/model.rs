@@ -0,0 +1,574 @@
+use crate::error::{Error, Result};
+use crypto::{EncryptedData, Encrypter, KdfParams};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub(crate) fn seal(encrypter: &dyn Encrypter, data: &str) -> Result<EncryptedData> {
+    encrypter
+        .encrypt(data.to_string())
+        .map_err(|_| Error::Encryption)
+}
+
+pub(crate) fn open(encrypter: &dyn Encrypter, content: Vec<u8>, nonce: [u8; 12]) -> Result<String> {
+    encrypter
+        .decrypt(EncryptedData { content, nonce })
+        .map_err(|_| Error::Decryption)
+}
+
+// ACCOUNT
+
+#[derive(Serialize)]
+pub(crate) struct UserIn<'a> {
+    pub username: &'a str,
+    #[serde(with = "crate::bytes")]
+    pub srp_salt: [u8; 32],
+    #[serde(with = "crate::bytes")]
+    pub verifier: Vec<u8>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct PreloginIn<'a> {
+    pub username: &'a str,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PreloginOut {
+    #[serde(with = "crate::bytes")]
+    pub salt: [u8; 32],
+    pub kdf: String,
+    pub kdf_params: KdfParams,
+}
+
+#[derive(Serialize)]
+pub(crate) struct LoginStartIn<'a> {
+    pub username: &'a str,
+    #[serde(with = "crate::bytes")]
+    pub client_public_key: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct LoginStartOut {
+    pub login_id: String,
+    #[serde(with = "crate::bytes")]
+    pub srp_salt: [u8; 32],
+    #[serde(with = "crate::bytes")]
+    pub server_public_key: Vec<u8>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct LoginIn<'a> {
+    pub username: &'a str,
+    pub login_id: &'a str,
+    #[serde(with = "crate::bytes")]
+    pub client_proof: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct UserOut {
+    pub user_id: Uuid,
+    pub username: String,
+    #[serde(with = "crate::bytes")]
+    pub salt: [u8; 32],
+    #[serde(default, with = "crate::bytes")]
+    pub server_proof: Option<[u8; 32]>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RefreshIn<'a> {
+    pub refresh_token: &'a str,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokensOut {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SessionOut {
+    #[serde(flatten)]
+    pub user: UserOut,
+    #[serde(flatten)]
+    pub tokens: TokensOut,
+}
+
+/// Outcome of a password login.
+#[derive(Clone, Debug)]
+pub enum Login {
+    LoggedIn(UserOut),
+    /// The account has a second factor, answer the challenge to finish the login.
+    SecondFactor(LoginChallengeOut),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LoginChallengeOut {
+    pub challenge_id: String,
+    pub second_factors: Vec<String>,
+    #[serde(default, with = "crate::bytes")]
+    pub server_proof: Option<[u8; 32]>,
+}
+
+/// Totp code or one of the recovery codes.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SecondFactorIn {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct LoginSecondFactorIn<'a> {
+    pub challenge_id: &'a str,
+    #[serde(flatten)]
+    pub second_factor: &'a SecondFactorIn,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TotpEnrollmentOut {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RecoveryCodesOut {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MessageOut {
+    pub message: String,
+}
+
+// WEBAUTHN, ceremonies run by the caller's authenticator, options are passed through as sent
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WebauthnRegistrationIn {
+    pub name: String,
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct WebauthnLoginStartIn<'a> {
+    pub challenge_id: &'a str,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WebauthnLoginIn {
+    pub challenge_id: String,
+    pub credential_id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebauthnCredentialOut {
+    pub credential_id: String,
+    pub name: String,
+    pub created_at: i64,
+}
+
+// API TOKENS
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ApiTokenIn {
+    pub name: String,
+    pub read_only: bool,
+    pub folder_ids: Vec<Uuid>,
+    pub entry_ids: Vec<Uuid>,
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiTokenOut {
+    pub token_id: Uuid,
+    pub name: String,
+    pub read_only: bool,
+    pub folder_ids: Vec<Uuid>,
+    pub entry_ids: Vec<Uuid>,
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub created_at: i64,
+    /// Only returned once, when the token is created.
+    pub token: Option<String>,
+}
+
+// SERVER
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct VersionOut {
+    pub api_version: String,
+    pub supported_versions: Vec<String>,
+    pub server_version: String,
+    pub capabilities: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ComponentOut {
+    pub status: String,
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ComponentsOut {
+    pub database: ComponentOut,
+    pub migrations: ComponentOut,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ReadyOut {
+    pub status: String,
+    pub components: ComponentsOut,
+}
+
+// ENTRIES
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntriesOrder {
+    #[default]
+    Id,
+    UpdatedAt,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EntriesFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    pub order: EntriesOrder,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct PageOut<T> {
+    pub entries: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Page<T> {
+    pub entries: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> PageOut<T> {
+    pub fn decrypt<U>(
+        self,
+        encrypter: &dyn Encrypter,
+        decrypt: fn(T, &dyn Encrypter) -> Result<U>,
+    ) -> Result<Page<U>> {
+        Ok(Page {
+            entries: self
+                .entries
+                .into_iter()
+                .map(|entry| decrypt(entry, encrypter))
+                .collect::<Result<_>>()?,
+            next_cursor: self.next_cursor,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct EntryFolderIn {
+    pub folder_id: Option<Uuid>,
+}
+
+// PASSWORDS
+
+/// Password entry as typed by the user, encrypted before it is sent.
+#[derive(Clone, Debug)]
+pub struct PasswordIn {
+    pub domain_name: String,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct EncryptedPasswordIn<'a> {
+    pub domain_name: &'a str,
+    pub username: &'a str,
+    #[serde(with = "crate::bytes")]
+    pub password: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    pub nonce: [u8; 12],
+}
+
+#[derive(Serialize)]
+pub(crate) struct PasswordTotpIn {
+    #[serde(with = "crate::bytes")]
+    pub totp: Option<Vec<u8>>,
+    #[serde(with = "crate::bytes")]
+    pub totp_nonce: Option<[u8; 12]>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct SearchTokensIn {
+    #[serde(with = "crate::bytes")]
+    pub tokens: Vec<[u8; 32]>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct SearchQuery {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct PasswordOut {
+    password_id: Uuid,
+    domain_name: String,
+    username: String,
+    #[serde(with = "crate::bytes")]
+    password: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    nonce: [u8; 12],
+    #[serde(default, with = "crate::bytes")]
+    totp: Option<Vec<u8>>,
+    #[serde(default, with = "crate::bytes")]
+    totp_nonce: Option<[u8; 12]>,
+    folder_id: Option<Uuid>,
+    tag_ids: Vec<Uuid>,
+    updated_at: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Password {
+    pub password_id: Uuid,
+    pub domain_name: String,
+    pub username: String,
+    pub password: String,
+    /// Totp secret or `otpauth://` uri.
+    pub totp: Option<String>,
+    pub folder_id: Option<Uuid>,
+    pub tag_ids: Vec<Uuid>,
+    pub updated_at: i64,
+}
+
+impl PasswordOut {
+    pub fn decrypt(self, encrypter: &dyn Encrypter) -> Result<Password> {
+        let totp = match (self.totp, self.totp_nonce) {
+            (Some(totp), Some(nonce)) => Some(open(encrypter, totp, nonce)?),
+            _ => None,
+        };
+        Ok(Password {
+            password_id: self.password_id,
+            domain_name: self.domain_name,
+            username: self.username,
+            password: open(encrypter, self.password, self.nonce)?,
+            totp,
+            folder_id: self.folder_id,
+            tag_ids: self.tag_ids,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct PasswordHistoryOut {
+    password_id: Uuid,
+    version: i32,
+    domain_name: String,
+    username: String,
+    #[serde(with = "crate::bytes")]
+    password: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    nonce: [u8; 12],
+    replaced_at: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct PasswordVersion {
+    pub password_id: Uuid,
+    pub version: i32,
+    pub domain_name: String,
+    pub username: String,
+    pub password: String,
+    pub replaced_at: i64,
+}
+
+impl PasswordHistoryOut {
+    pub fn decrypt(self, encrypter: &dyn Encrypter) -> Result<PasswordVersion> {
+        Ok(PasswordVersion {
+            password_id: self.password_id,
+            version: self.version,
+            domain_name: self.domain_name,
+            username: self.username,
+            password: open(encrypter, self.password, self.nonce)?,
+            replaced_at: self.replaced_at,
+        })
+    }
+}
+
+// NOTES
+
+#[derive(Clone, Debug)]
+pub struct NoteIn {
+    pub title: String,
+    pub content: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct EncryptedNoteIn {
+    #[serde(with = "crate::bytes")]
+    pub title: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    pub title_nonce: [u8; 12],
+    #[serde(with = "crate::bytes")]
+    pub content: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    pub content_nonce: [u8; 12],
+}
+
+#[derive(Deserialize)]
+pub(crate) struct NoteOut {
+    note_id: Uuid,
+    #[serde(with = "crate::bytes")]
+    title: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    title_nonce: [u8; 12],
+    #[serde(with = "crate::bytes")]
+    content: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    content_nonce: [u8; 12],
+    folder_id: Option<Uuid>,
+    tag_ids: Vec<Uuid>,
+    updated_at: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Note {
+    pub note_id: Uuid,
+    pub title: String,
+    pub content: String,
+    pub folder_id: Option<Uuid>,
+    pub tag_ids: Vec<Uuid>,
+    pub updated_at: i64,
+}
+
+impl NoteOut {
+    pub fn decrypt(self, encrypter: &dyn Encrypter) -> Result<Note> {
+        Ok(Note {
+            note_id: self.note_id,
+            title: open(encrypter, self.title, self.title_nonce)?,
+            content: open(encrypter, self.content, self.content_nonce)?,
+            folder_id: self.folder_id,
+            tag_ids: self.tag_ids,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct NoteRevisionOut {
+    note_id: Uuid,
+    revision: i32,
+    #[serde(with = "crate::bytes")]
+    title: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    title_nonce: [u8; 12],
+    #[serde(with = "crate::bytes")]
+    content: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    content_nonce: [u8; 12],
+    replaced_at: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct NoteRevision {
+    pub note_id: Uuid,
+    pub revision: i32,
+    pub title: String,
+    pub content: String,
+    pub replaced_at: i64,
+}
+
+impl NoteRevisionOut {
+    pub fn decrypt(self, encrypter: &dyn Encrypter) -> Result<NoteRevision> {
+        Ok(NoteRevision {
+            note_id: self.note_id,
+            revision: self.revision,
+            title: open(encrypter, self.title, self.title_nonce)?,
+            content: open(encrypter, self.content, self.content_nonce)?,
+            replaced_at: self.replaced_at,
+        })
+    }
+}
+
+// FOLDERS AND TAGS
+
+#[derive(Serialize)]
+pub(crate) struct FolderIn {
+    pub parent_id: Option<Uuid>,
+    #[serde(with = "crate::bytes")]
+    pub name: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    pub name_nonce: [u8; 12],
+}
+
+#[derive(Deserialize)]
+pub(crate) struct FolderOut {
+    folder_id: Uuid,
+    parent_id: Option<Uuid>,
+    #[serde(with = "crate::bytes")]
+    name: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    name_nonce: [u8; 12],
+}
+
+#[derive(Clone, Debug)]
+pub struct Folder {
+    pub folder_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+}
+
+impl FolderOut {
+    pub fn decrypt(self, encrypter: &dyn Encrypter) -> Result<Folder> {
+        Ok(Folder {
+            folder_id: self.folder_id,
+            parent_id: self.parent_id,
+            name: open(encrypter, self.name, self.name_nonce)?,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct TagIn {
+    #[serde(with = "crate::bytes")]
+    pub name: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    pub name_nonce: [u8; 12],
+}
+
+#[derive(Deserialize)]
+pub(crate) struct TagOut {
+    tag_id: Uuid,
+    #[serde(with = "crate::bytes")]
+    name: Vec<u8>,
+    #[serde(with = "crate::bytes")]
+    name_nonce: [u8; 12],
+}
+
+#[derive(Clone, Debug)]
+pub struct Tag {
+    pub tag_id: Uuid,
+    pub name: String,
+}
+
+impl TagOut {
+    pub fn decrypt(self, encrypter: &dyn Encrypter) -> Result<Tag> {
+        Ok(Tag {
+            tag_id: self.tag_id,
+            name: open(encrypter, self.name, self.name_nonce)?,
+        })
+    }
+}
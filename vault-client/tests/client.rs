@@ -0,0 +1,177 @@
+use rand::Rng;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use vault_client::{ApiTokenIn, Client, EntriesFilter, Error, Login, NoteIn, PasswordIn};
+
+fn random_username() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect()
+}
+
+async fn spawn_server() -> (String, CancellationToken) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let shutdown = CancellationToken::new();
+    let app = server::routers::app().await;
+    tokio::spawn(server::shutdown::serve(
+        listener,
+        app,
+        None,
+        shutdown.clone(),
+        Duration::from_secs(1),
+    ));
+    (address, shutdown)
+}
+
+#[tokio::test]
+async fn client_round_trip() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let (address, shutdown) = spawn_server().await;
+    let client = Client::connect(&address).await.unwrap();
+    let username = random_username();
+    client.register(&username, "master password").await.unwrap();
+    assert!(matches!(
+        client.password(uuid::Uuid::new_v4()).await,
+        Err(Error::NotLoggedIn)
+    ));
+    let Login::LoggedIn(user) = client.login(&username, "master password").await.unwrap() else {
+        panic!("unexpected second factor");
+    };
+    assert_eq!(user.username, username.to_lowercase());
+
+    // entries go out encrypted and come back decrypted
+    let folder = client.create_folder(None, "work").await.unwrap();
+    let tag = client.create_tag("important").await.unwrap();
+    let password = client
+        .create_password(&PasswordIn {
+            domain_name: "mail.example.com".to_string(),
+            username: "alice".to_string(),
+            password: "hunter2".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(password.password, "hunter2");
+    client
+        .set_password_folder(password.password_id, Some(folder.folder_id))
+        .await
+        .unwrap();
+    client
+        .add_password_tag(password.password_id, tag.tag_id)
+        .await
+        .unwrap();
+    let password = client
+        .set_password_totp(password.password_id, Some("JBSWY3DPEHPK3PXP"))
+        .await
+        .unwrap();
+    assert_eq!(password.totp.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+    assert_eq!(password.folder_id, Some(folder.folder_id));
+    assert_eq!(password.tag_ids, vec![tag.tag_id]);
+
+    let updated = client
+        .update_password(
+            password.password_id,
+            &PasswordIn {
+                domain_name: "mail.example.com".to_string(),
+                username: "alice".to_string(),
+                password: "correct horse".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.password, "correct horse");
+    let history = client.password_history(password.password_id).await.unwrap();
+    assert_eq!(history[0].password, "hunter2");
+    let found = client.search_passwords("example.com").await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].password_id, password.password_id);
+
+    let note = client
+        .create_note(&NoteIn {
+            title: "groceries".to_string(),
+            content: "milk".to_string(),
+        })
+        .await
+        .unwrap();
+    client
+        .update_note(
+            note.note_id,
+            &NoteIn {
+                title: "groceries".to_string(),
+                content: "milk, eggs".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    let revisions = client.note_revisions(note.note_id).await.unwrap();
+    let restored = client
+        .restore_note(note.note_id, revisions[0].revision)
+        .await
+        .unwrap();
+    assert_eq!(restored.content, "milk");
+    let page = client
+        .notes(&EntriesFilter {
+            limit: Some(10),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.entries.len(), 1);
+    assert_eq!(page.entries[0].title, "groceries");
+    assert_eq!(client.folders().await.unwrap()[0].name, "work");
+    assert_eq!(client.tags().await.unwrap()[0].name, "important");
+
+    // server errors map to typed errors
+    assert!(matches!(
+        client.password(uuid::Uuid::new_v4()).await,
+        Err(Error::NotFound(_))
+    ));
+
+    // a second client reads the vault with a personal access token and the master password
+    let api_token = client
+        .create_api_token(&ApiTokenIn {
+            name: "ci".to_string(),
+            read_only: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let script = Client::connect(&address).await.unwrap();
+    script.use_api_token(api_token.token.unwrap()).await;
+    assert!(matches!(
+        script.passwords(&EntriesFilter::default()).await,
+        Err(Error::Locked)
+    ));
+    script.unlock(&username, "master password").await.unwrap();
+    let page = script.passwords(&EntriesFilter::default()).await.unwrap();
+    assert_eq!(page.entries[0].password, "correct horse");
+
+    client.refresh().await.unwrap();
+    client.logout().await.unwrap();
+    assert!(matches!(client.tags().await, Err(Error::NotLoggedIn)));
+    shutdown.cancel();
+}
+
+#[tokio::test]
+async fn login_with_wrong_password_fails() {
+    if std::env::var("DATABASE_URL").is_err() {
+        eprintln!("DATABASE_URL not set, skipping");
+        return;
+    }
+    let (address, shutdown) = spawn_server().await;
+    let client = Client::connect(&address).await.unwrap();
+    let username = random_username();
+    client.register(&username, "master password").await.unwrap();
+    assert!(matches!(
+        client.login(&username, "wrong password").await,
+        Err(Error::Unauthorized(_))
+    ));
+    assert!(matches!(client.tags().await, Err(Error::NotLoggedIn)));
+    shutdown.cancel();
+}